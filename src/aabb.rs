@@ -0,0 +1,290 @@
+use crate::geometry::{Dir3, Point3};
+use crate::interval::Interval;
+use crate::number::Number;
+use crate::ray::Ray;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    // the box spanned by two opposite corners, in any order.
+    pub fn new(a: &Point3, b: &Point3) -> Self {
+        let (mut min, mut max) = (a.clone(), a.clone());
+        min.min(b);
+        max.max(b);
+        Self { min, max }
+    }
+
+    // radius out from the y axis, y0 to y1 along it: the local-space box
+    // of the shapes built around that axis.
+    pub fn around_y(radius: &Number, y0: &Number, y1: &Number) -> Self {
+        let mut r = radius.clone();
+        r.neg();
+        Self::new(&Point3::new(r.clone(), y0.clone(), r), &Point3::new(radius.clone(), y1.clone(), radius.clone()))
+    }
+
+    // contains nothing; the identity for union and expand.
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::new(Number::MAX, Number::MAX, Number::MAX),
+            max: Point3::new(Number::MIN, Number::MIN, Number::MIN),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let (min, max) = (self.min.vec3(), self.max.vec3());
+        max.x.lt(&min.x) || max.y.lt(&min.y) || max.z.lt(&min.z)
+    }
+}
+
+impl Aabb {
+    pub fn union(&mut self, other: &Self) {
+        self.min.min(&other.min);
+        self.max.max(&other.max);
+    }
+
+    // grows the box to take in p.
+    pub fn expand(&mut self, p: &Point3) {
+        self.min.min(p);
+        self.max.max(p);
+    }
+
+    // max - min per axis, held at Number::MAX where that overflows.
+    pub fn extent(&self) -> Dir3 {
+        if self.is_empty() {
+            return Dir3::zero();
+        }
+        let (min, max) = (self.min.vec3(), self.max.vec3());
+        Dir3::new(max.x.saturating_sub(&min.x), max.y.saturating_sub(&min.y), max.z.saturating_sub(&min.z))
+    }
+
+    // 2 (xy + yz + zx); held at Number::MAX for boxes too large for 16.16,
+    // so a few hundred units a side.
+    pub fn surface_area(&self) -> Number {
+        let e = self.extent().into_vec3();
+        let half = Number::try_dot(&[e.x.clone(), e.y.clone(), e.z.clone()], &[e.y.clone(), e.z.clone(), e.x.clone()]);
+        let mut area = match half {
+            Ok(half) => half,
+            Err(_) => return Number::MAX,
+        };
+        if area.try_add(&area.clone()).is_err() {
+            return Number::MAX;
+        }
+        area
+    }
+
+    // (min + max) / 2, without overflowing on the sum.
+    pub fn centroid(&self) -> Point3 {
+        let (min, max) = (self.min.vec3(), self.max.vec3());
+        let mid = |a: &Number, b: &Number| Number::dot(&[a.clone(), b.clone()], &[Number::HALF, Number::HALF]);
+        Point3::new(mid(&min.x, &max.x), mid(&min.y, &max.y), mid(&min.z, &max.z))
+    }
+}
+
+impl Aabb {
+    // the part of [t_min, t_max] the ray spends inside the box, by slabs.
+    // faces count as inside, so rays grazing a face, edge or corner hit. a
+    // zero direction component never divides: the ray is parallel to that
+    // slab and is either always or never between its planes.
+    pub fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Interval> {
+        let mut span = Interval::new(t_min, t_max);
+        let (min, max, origin, direction) = (self.min.vec3(), self.max.vec3(), ray.origin.vec3(), ray.direction.vec3());
+        let axes = [
+            (&min.x, &max.x, &origin.x, &direction.x, &ray.inv_direction.x),
+            (&min.y, &max.y, &origin.y, &direction.y, &ray.inv_direction.y),
+            (&min.z, &max.z, &origin.z, &direction.z, &ray.inv_direction.z),
+        ];
+
+        for (lo, hi, origin, direction, inv) in axes {
+            if *direction == Number::ZERO {
+                if !Interval::new(lo, hi).contains(origin) {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = lo.saturating_sub(origin).saturating_mul(inv);
+            let t1 = hi.saturating_sub(origin).saturating_mul(inv);
+            if !span.intersect(&Interval::new(&t0, &t1)) {
+                return None;
+            }
+        }
+        Some(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn unit() -> Aabb {
+        Aabb::new(&Point3::from([-1.0, -1.0, -1.0]), &Point3::from([1.0, 1.0, 1.0]))
+    }
+
+    fn ray(o: [f64; 3], d: [f64; 3]) -> Ray {
+        Ray::new(Point3::from(o), Dir3::from(d))
+    }
+
+    fn hit(b: &Aabb, r: &Ray) -> Option<(f64, f64)> {
+        b.hit(r, &Number::ZERO, &Number::MAX).map(|s| (f64::from(&s.lo), f64::from(&s.hi)))
+    }
+
+    // slab test in f64, faces inclusive.
+    fn reference(b: &Aabb, r: &Ray) -> Option<(f64, f64)> {
+        let (min, max) = (<[f64; 3]>::from(&b.min), <[f64; 3]>::from(&b.max));
+        let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+        let (mut lo, mut hi) = (0.0, f64::MAX);
+        for i in 0..3 {
+            if d[i] == 0.0 {
+                if o[i] < min[i] || o[i] > max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min[i] - o[i]) / d[i], (max[i] - o[i]) / d[i]);
+            lo = f64::max(lo, t0.min(t1));
+            hi = f64::min(hi, t0.max(t1));
+        }
+        if lo <= hi { Some((lo, hi)) } else { None }
+    }
+
+    #[test]
+    fn construction() {
+        let b = Aabb::new(&Point3::from([3.0, -1.0, 2.0]), &Point3::from([-2.0, 4.0, 2.0]));
+        assert_eq!(b.min, Point3::from([-2.0, -1.0, 2.0]));
+        assert_eq!(b.max, Point3::from([3.0, 4.0, 2.0]));
+
+        assert!(Aabb::empty().is_empty());
+        assert!(!b.is_empty());
+
+        let mut e = Aabb::empty();
+        e.union(&b);
+        assert_eq!(e, b);
+
+        let mut e = Aabb::empty();
+        e.expand(&Point3::from([1.0, 2.0, 3.0]));
+        assert_eq!(e, Aabb::new(&Point3::from([1.0, 2.0, 3.0]), &Point3::from([1.0, 2.0, 3.0])));
+        e.expand(&Point3::from([-1.0, 5.0, 0.0]));
+        assert_eq!(e, Aabb::new(&Point3::from([-1.0, 2.0, 0.0]), &Point3::from([1.0, 5.0, 3.0])));
+
+        let mut u = unit();
+        u.union(&Aabb::new(&Point3::from([0.0, 0.0, 0.0]), &Point3::from([5.0, 0.5, 0.5])));
+        assert_eq!(u, Aabb::new(&Point3::from([-1.0, -1.0, -1.0]), &Point3::from([5.0, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn measures() {
+        let b = Aabb::new(&Point3::from([-1.0, 0.0, 2.0]), &Point3::from([2.0, 4.0, 7.0]));
+        assert_eq!(b.extent(), Dir3::from([3.0, 4.0, 5.0]));
+        assert_eq!(b.surface_area(), Number::from(94));
+        assert_eq!(b.centroid(), Point3::from([0.5, 2.0, 4.5]));
+
+        assert_eq!(Aabb::empty().surface_area(), Number::ZERO);
+
+        let huge = Aabb::new(&Point3::new(Number::MIN, Number::MIN, Number::MIN), &Point3::new(Number::MAX, Number::MAX, Number::MAX));
+        assert_eq!(huge.extent(), Dir3::new(Number::MAX, Number::MAX, Number::MAX));
+        assert_eq!(huge.surface_area(), Number::MAX);
+        assert_eq!(f64::from(&huge.centroid().vec3().x), -1.0 / 65536.0);
+    }
+
+    #[test]
+    fn hit_through() {
+        let b = unit();
+        assert_eq!(hit(&b, &ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0])), Some((4.0, 6.0)));
+        assert_eq!(hit(&b, &ray([0.0, 0.0, 0.0], [0.0, 0.0, -2.0])), Some((0.0, 0.5)));
+        assert_eq!(hit(&b, &ray([-5.0, -5.0, -5.0], [1.0, 1.0, 1.0])), Some((4.0, 6.0)));
+        assert_eq!(hit(&b, &ray([5.0, 0.0, 0.0], [1.0, 0.0, 0.0])), None);
+
+        // clipped to [t_min, t_max]
+        let s = b.hit(&ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]), &Number::from(5), &Number::from(10)).unwrap();
+        assert_eq!((s.lo, s.hi), (Number::from(5), Number::from(6)));
+        assert_eq!(b.hit(&ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]), &Number::ZERO, &Number::from(3)), None);
+    }
+
+    #[test]
+    fn parallel_to_faces() {
+        let b = unit();
+        for axis in 0..3 {
+            for side in [-1.0, 1.0] {
+                // travelling along axis + 1, offset along axis: inside, on the
+                // face and just outside it.
+                let along = (axis + 1) % 3;
+                let mut d = [0.0; 3];
+                d[along] = 1.0;
+                for (offset, inside) in [(0.5, true), (1.0, true), (1.0 + 1.0 / 65536.0, false), (3.0, false)] {
+                    let mut o = [0.0; 3];
+                    o[axis] = side * offset;
+                    o[along] = -5.0;
+                    let r = ray(o, d);
+                    assert_eq!(hit(&b, &r).is_some(), inside, "axis {} side {} offset {}", axis, side, offset);
+                    if inside {
+                        assert_eq!(hit(&b, &r), Some((4.0, 6.0)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grazing() {
+        let b = Aabb::new(&Point3::from([0.0, 0.0, 0.0]), &Point3::from([1.0, 1.0, 1.0]));
+
+        // along an edge
+        assert_eq!(hit(&b, &ray([-3.0, 1.0, 1.0], [1.0, 0.0, 0.0])), Some((3.0, 4.0)));
+        assert_eq!(hit(&b, &ray([0.0, -2.0, 0.0], [0.0, 0.5, 0.0])), Some((4.0, 6.0)));
+
+        // across an edge, touching it at a single point
+        assert_eq!(hit(&b, &ray([-1.0, 0.0, 0.5], [1.0, 1.0, 0.0])), Some((1.0, 1.0)));
+        assert_eq!(hit(&b, &ray([2.0, 0.0, 0.5], [-1.0, 1.0, 0.0])), Some((1.0, 1.0)));
+        // just inside and just outside that edge
+        let h = hit(&b, &ray([-1.0, 0.0, 0.5], [1.0, 1.0 - 1.0 / 256.0, 0.0])).unwrap();
+        assert!(h.0 == 1.0 && h.1 > 1.0, "{:?}", h);
+        assert_eq!(hit(&b, &ray([-1.0, 0.0, 0.5], [1.0, 1.0 + 1.0 / 256.0, 0.0])), None);
+
+        // through a corner
+        assert_eq!(hit(&b, &ray([2.0, 0.0, 0.0], [-1.0, 1.0, 1.0])), Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn tiny_directions() {
+        // nonzero but too small to invert: inv_direction is +-MAX and the
+        // slab times saturate instead of wrapping.
+        let b = unit();
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::new(Number::ULP, Number::ZERO, Number::ONE));
+        assert_eq!(hit(&b, &r), Some((4.0, 6.0)));
+
+        let r = Ray::new(Point3::from([3.0, 0.0, -5.0]), Dir3::new(Number::ULP, Number::ZERO, Number::ONE));
+        assert_eq!(hit(&b, &r), None);
+    }
+
+    #[test]
+    fn against_reference() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let c = [rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0)];
+            let b = Aabb::new(&Point3::from(c), &Point3::from([c[0] + rng.gen_range(0.5..5.0), c[1] + rng.gen_range(0.5..5.0), c[2] + rng.gen_range(0.5..5.0)]));
+            let o = [rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)];
+            // half-integer directions invert exactly, so t agrees with f64.
+            let d = [rng.gen_range(-4..=4) as f64 / 2.0, rng.gen_range(-4..=4) as f64 / 2.0, rng.gen_range(-4..=4) as f64 / 2.0];
+            if d == [0.0; 3] || d.iter().any(|c| c.abs() == 1.5) {
+                continue;
+            }
+            let r = ray(o, d);
+
+            let (got, want) = (hit(&b, &r), reference(&b, &r));
+            match (got, want) {
+                (Some(g), Some(w)) => {
+                    assert!((g.0 - w.0).abs() < 4.0 / 65536.0 && (g.1 - w.1).abs() < 4.0 / 65536.0, "got {:?}, want {:?}", g, w)
+                }
+                (None, None) => {}
+                // rounding may decide a near-graze either way.
+                (Some(g), None) | (None, Some(g)) => assert!(g.1 - g.0 < 4.0 / 65536.0, "got {:?}, want {:?}", got, want),
+            }
+        }
+    }
+}
@@ -0,0 +1,549 @@
+use crate::aabb::Aabb;
+use crate::geometry::Point3;
+use crate::int32::Int32;
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// centroid bins per axis for the SAH sweep.
+const BINS: usize = 12;
+// a node with this many primitives or fewer may become a leaf; above it,
+// nodes are always split.
+const LEAF: usize = 4;
+// traversal keeps its stack in a fixed array, which holds one entry per
+// level and one more; nodes this deep become leaves whatever their size.
+const STACK: usize = 64;
+const DEPTH: usize = STACK - 2;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Split {
+    // halves by count along the longest axis of the centroids.
+    Median,
+    // binned surface area heuristic: the split with the least area-weighted
+    // primitive count.
+    Sah,
+}
+
+// one entry of the flattened tree. nodes are stored depth first, so an
+// interior node's first child is the next node and only the second needs
+// an index: every field is an integer or a Number, ready to lay out as
+// parallel arrays.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub bounds: Aabb,
+    // a leaf's first entry in order, or an interior node's second child.
+    pub offset: usize,
+    // the leaf's primitives; 0 for an interior node.
+    pub count: usize,
+    // the axis an interior node was split on, to pick the near child by the
+    // ray's direction.
+    pub axis: usize,
+}
+
+// a hierarchy over primitives given only by their boxes. it holds indices,
+// not the primitives: hit and occluded call back with each index to test.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bvh {
+    pub nodes: Vec<Node>,
+    // primitive indices, each leaf's a contiguous run
+    pub order: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Aabb], split: Split) -> Self {
+        let mut bvh = Self { nodes: Vec::new(), order: (0..bounds.len()).collect() };
+        if !bounds.is_empty() {
+            let centroids: Vec<Point3> = bounds.iter().map(Aabb::centroid).collect();
+            bvh.build(bounds, &centroids, 0, bounds.len(), 0, split);
+        }
+        bvh
+    }
+
+    // the node over order[start..end], and below it its children.
+    fn build(&mut self, bounds: &[Aabb], centroids: &[Point3], start: usize, end: usize, depth: usize, split: Split) {
+        let (mut node_bounds, mut spread) = (Aabb::empty(), Aabb::empty());
+        for &i in &self.order[start..end] {
+            node_bounds.union(&bounds[i]);
+            spread.expand(&centroids[i]);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node { bounds: node_bounds, offset: start, count: end - start, axis: 0 });
+
+        // with every centroid in one place there is nothing to split by
+        let axis = longest(&spread);
+        let count = end - start;
+        if count == 1 || depth >= DEPTH || *coord(spread.extent().vec3(), axis) == Number::ZERO {
+            return;
+        }
+        let mid = match split {
+            Split::Median if count <= LEAF => return,
+            Split::Median => self.median(centroids, start, end, axis),
+            Split::Sah => match self.sah(bounds, centroids, start, end, axis, &spread) {
+                Some(mid) => mid,
+                None => return,
+            },
+        };
+
+        self.build(bounds, centroids, start, mid, depth + 1, split);
+        let second = self.nodes.len();
+        self.build(bounds, centroids, mid, end, depth + 1, split);
+        let node = &mut self.nodes[index];
+        (node.offset, node.count, node.axis) = (second, 0, axis);
+    }
+
+    fn median(&mut self, centroids: &[Point3], start: usize, end: usize, axis: usize) -> usize {
+        let mid = start + (end - start) / 2;
+        self.order[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            let (a, b) = (coord(centroids[*a].vec3(), axis), coord(centroids[*b].vec3(), axis));
+            if a.lt(b) {
+                std::cmp::Ordering::Less
+            } else if b.lt(a) {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        });
+        mid
+    }
+
+    // bins the centroids along axis and sweeps the BINS - 1 planes between
+    // them for the least cost, counting a box test as one primitive test.
+    // None when a small node is cheaper left as a leaf.
+    fn sah(&mut self, bounds: &[Aabb], centroids: &[Point3], start: usize, end: usize, axis: usize, spread: &Aabb) -> Option<usize> {
+        let lo = coord(spread.min.vec3(), axis);
+        let mut scale = Number::from(BINS as i16);
+        if scale.try_div(coord(spread.extent().vec3(), axis)).is_err() {
+            scale = Number::MAX;
+        }
+        let bin = |i: usize| {
+            let b = coord(centroids[i].vec3(), axis).saturating_sub(lo).saturating_mul(&scale);
+            (i32::from(b.0) >> 16).clamp(0, BINS as i32 - 1) as usize
+        };
+
+        let (mut boxes, mut counts) = (vec![Aabb::empty(); BINS], [0usize; BINS]);
+        for &i in &self.order[start..end] {
+            let b = bin(i);
+            boxes[b].union(&bounds[i]);
+            counts[b] += 1;
+        }
+
+        // areas in units of the node's longest side, so that boxes of any
+        // size in 16.16 can be compared, and counts as raw Numbers, so that
+        // any count fits: the costs are only compared with each other.
+        let mut node = Aabb::empty();
+        boxes.iter().for_each(|b| node.union(b));
+        let e = node.extent().into_vec3();
+        let side = [&e.x, &e.y, &e.z][longest(&node)];
+        let mut unit = Number::ONE;
+        if unit.try_div(side).is_err() {
+            unit = Number::ULP;
+        }
+        let area = |b: &Aabb| {
+            if b.is_empty() {
+                return Number::ZERO;
+            }
+            let mut e = b.extent();
+            e.scale(&unit);
+            let e = e.into_vec3();
+            Number::try_dot(&[e.x.clone(), e.y.clone(), e.z.clone()], &[e.y, e.z, e.x]).unwrap_or(Number::MAX)
+        };
+        let raw = |n: usize| Number(Int32::from(n as i32));
+
+        let mut below = vec![(Aabb::empty(), 0); BINS];
+        let mut acc = (Aabb::empty(), 0);
+        for k in 0..BINS {
+            acc.0.union(&boxes[k]);
+            acc.1 += counts[k];
+            below[k] = acc.clone();
+        }
+        let mut best: Option<(Int64, usize)> = None;
+        let mut above = (Aabb::empty(), 0);
+        for k in (1..BINS).rev() {
+            above.0.union(&boxes[k]);
+            above.1 += counts[k];
+            let (left, right) = (&below[k - 1], &above);
+            if left.1 == 0 || right.1 == 0 {
+                continue;
+            }
+            let cost = Number::sum_of_products(&[area(&left.0), area(&right.0), area(&node)], &[raw(left.1), raw(right.1), raw(1)]);
+            if best.as_ref().is_none_or(|(b, _)| cost.lt_unsigned(b)) {
+                best = Some((cost, k));
+            }
+        }
+
+        let (cost, k) = best?;
+        let count = end - start;
+        if count <= LEAF && !cost.lt_unsigned(&Number::sum_of_products(&[area(&node)], &[raw(count)])) {
+            return None;
+        }
+        let (left, right): (Vec<usize>, Vec<usize>) = self.order[start..end].iter().partition(|i| bin(**i) < k);
+        let mid = start + left.len();
+        self.order[start..mid].copy_from_slice(&left);
+        self.order[mid..end].copy_from_slice(&right);
+        Some(mid)
+    }
+}
+
+impl Bvh {
+    // the nearest hit. test(i, t_max) hits primitive i, or not, before
+    // t_max; t_max shrinks to each hit found, so boxes behind it are passed
+    // over.
+    pub fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number, test: impl Fn(usize, &Number) -> Option<Hit>) -> Option<Hit> {
+        let mut nearest: Option<Hit> = None;
+        self.walk(ray, t_min, t_max, false, |i, far| {
+            let hit = test(i, far)?;
+            let t = hit.t.clone();
+            nearest = Some(hit);
+            Some(t)
+        });
+        nearest
+    }
+
+    // whether any primitive blocks the ray, stopping at the first that does.
+    pub fn occluded(&self, ray: &Ray, t_min: &Number, t_max: &Number, test: impl Fn(usize, &Number) -> bool) -> bool {
+        self.walk(ray, t_min, t_max, true, |i, far| test(i, far).then(|| far.clone()))
+    }
+
+    // visits the leaves the ray meets before the nearest t visit has
+    // returned so far, nearer child first; with first, stops at the first
+    // Some. whether visit ever returned Some.
+    fn walk(&self, ray: &Ray, t_min: &Number, t_max: &Number, first: bool, mut visit: impl FnMut(usize, &Number) -> Option<Number>) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let d = ray.direction.vec3();
+        let backwards = [d.x.is_negative(), d.y.is_negative(), d.z.is_negative()];
+
+        let (mut far, mut found) = (t_max.clone(), false);
+        let (mut stack, mut top) = ([0usize; STACK], 1);
+        while top > 0 {
+            top -= 1;
+            let index = stack[top];
+            let node = &self.nodes[index];
+            if node.bounds.hit(ray, t_min, &far).is_none() {
+                continue;
+            }
+
+            if node.count > 0 {
+                for &i in &self.order[node.offset..node.offset + node.count] {
+                    if let Some(t) = visit(i, &far) {
+                        if first {
+                            return true;
+                        }
+                        (far, found) = (t, true);
+                    }
+                }
+                continue;
+            }
+
+            // the far child goes on first, to come off after the near one
+            let (near, other) = if backwards[node.axis] { (node.offset, index + 1) } else { (index + 1, node.offset) };
+            stack[top] = other;
+            stack[top + 1] = near;
+            top += 2;
+        }
+        found
+    }
+}
+
+// shapes under one Bvh. unbounded ones, such as planes, sit outside it and
+// every ray tests them.
+pub struct Group {
+    pub bounded: Vec<Box<dyn Shape>>,
+    pub unbounded: Vec<Box<dyn Shape>>,
+    pub bvh: Bvh,
+}
+
+impl Group {
+    pub fn new(shapes: Vec<Box<dyn Shape>>, split: Split) -> Self {
+        let (mut bounded, mut unbounded, mut boxes) = (Vec::new(), Vec::new(), Vec::new());
+        for shape in shapes {
+            match shape.bounds() {
+                Some(b) => {
+                    boxes.push(b);
+                    bounded.push(shape);
+                }
+                None => unbounded.push(shape),
+            }
+        }
+        Self { bounded, unbounded, bvh: Bvh::new(&boxes, split) }
+    }
+}
+
+impl Shape for Group {
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let mut nearest = self.bvh.hit(ray, t_min, t_max, |i, far| self.bounded[i].hit(ray, t_min, far));
+        for shape in &self.unbounded {
+            let far = nearest.as_ref().map_or(t_max, |h| &h.t);
+            if let Some(hit) = shape.hit(ray, t_min, far) {
+                nearest = Some(hit);
+            }
+        }
+        nearest
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> bool {
+        self.unbounded.iter().any(|s| s.occluded(ray, t_min, t_max))
+            || self.bvh.occluded(ray, t_min, t_max, |i, far| self.bounded[i].occluded(ray, t_min, far))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.bvh.nodes.first().map(|n| n.bounds.clone())
+    }
+}
+
+// the axis the box is longest along.
+fn longest(b: &Aabb) -> usize {
+    let e = b.extent().into_vec3();
+    if e.x.lt(&e.y) {
+        if e.y.lt(&e.z) { 2 } else { 1 }
+    } else if e.x.lt(&e.z) {
+        2
+    } else {
+        0
+    }
+}
+
+fn coord(v: &crate::vec3::Vec3, axis: usize) -> &Number {
+    [&v.x, &v.y, &v.z][axis]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::cone::Cone;
+    use crate::csg::Csg;
+    use crate::cuboid::Cuboid;
+    use crate::cylinder::Cylinder;
+    use crate::disk::Disk;
+    use crate::geometry::{Dir3, Normal3};
+    use crate::instance::Instance;
+    use crate::mesh::{Face, Mesh};
+    use crate::plane::Plane;
+    use crate::rect::Rect;
+    use crate::sphere::Sphere;
+    use crate::torus::Torus;
+    use crate::transform::Transform;
+    use crate::triangle::Triangle;
+    use crate::vec3::Vec3;
+
+    fn contains(outer: &Aabb, inner: &Aabb) -> bool {
+        let (o, i) = ((outer.min.vec3(), outer.max.vec3()), (inner.min.vec3(), inner.max.vec3()));
+        (0..3).all(|a| !coord(i.0, a).lt(coord(o.0, a)) && !coord(o.1, a).lt(coord(i.1, a)))
+    }
+
+    // every primitive in exactly one leaf, and inside every box above it.
+    fn check(bvh: &Bvh, bounds: &[Aabb]) -> usize {
+        fn node(bvh: &Bvh, bounds: &[Aabb], index: usize, seen: &mut [bool]) -> usize {
+            let n = &bvh.nodes[index];
+            if n.count > 0 {
+                for &i in &bvh.order[n.offset..n.offset + n.count] {
+                    assert!(!seen[i], "{} twice", i);
+                    seen[i] = true;
+                    assert!(contains(&n.bounds, &bounds[i]));
+                }
+                return 1;
+            }
+            assert!(index + 1 < n.offset && n.offset < bvh.nodes.len());
+            for child in [index + 1, n.offset] {
+                assert!(contains(&n.bounds, &bvh.nodes[child].bounds));
+            }
+            1 + node(bvh, bounds, index + 1, seen).max(node(bvh, bounds, n.offset, seen))
+        }
+        let mut seen = vec![false; bounds.len()];
+        let depth = node(bvh, bounds, 0, &mut seen);
+        assert!(seen.iter().all(|s| *s));
+        depth
+    }
+
+    fn random_boxes(rng: &mut impl Rng, n: usize) -> Vec<Aabb> {
+        (0..n)
+            .map(|_| {
+                let c = [0, 1, 2].map(|_| rng.gen_range(-100.0..100.0));
+                let s = [0, 1, 2].map(|_| rng.gen_range(0.0..4.0));
+                Aabb::new(&Point3::from([0, 1, 2].map(|i| c[i] - s[i])), &Point3::from([0, 1, 2].map(|i| c[i] + s[i])))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn structure() {
+        let mut rng = StdRng::seed_from_u64(50);
+        for split in [Split::Median, Split::Sah] {
+            for n in [1, 2, 5, 1000] {
+                let bounds = random_boxes(&mut rng, n);
+                let bvh = Bvh::new(&bounds, split);
+                let depth = check(&bvh, &bounds);
+                assert!(depth <= 24, "{:?} {} deep for {}", split, depth, n);
+                assert!(bvh.nodes.iter().all(|n| n.count <= LEAF));
+            }
+            // the same box many times over can't be split
+            let same = vec![Aabb::new(&Point3::from([0.0, 0.0, 0.0]), &Point3::from([1.0, 1.0, 1.0])); 100];
+            let bvh = Bvh::new(&same, split);
+            check(&bvh, &same);
+            assert_eq!(bvh.nodes.len(), 1);
+        }
+        assert!(Bvh::new(&[], Split::Sah).nodes.is_empty());
+    }
+
+    #[test]
+    fn spheres_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let spheres: Vec<Sphere> = (0..150)
+            .map(|i| {
+                let c = [0, 1, 2].map(|_| rng.gen_range(-50.0..50.0));
+                Sphere::new(Point3::from(c), Number::from(rng.gen_range(0.5..3.0)), i)
+            })
+            .collect();
+        let brute = |r: &Ray, t_max: &Number| {
+            let mut best: Option<Hit> = None;
+            for s in &spheres {
+                let far = best.as_ref().map_or(t_max, |h| &h.t);
+                if let Some(h) = s.hit(r, &Number::ULP, far) {
+                    best = Some(h);
+                }
+            }
+            best
+        };
+
+        for split in [Split::Median, Split::Sah] {
+            let group = Group::new(spheres.iter().map(|s| Box::new(s.clone()) as Box<dyn Shape>).collect(), split);
+            let mut hits = 0;
+            for _ in 0..250 {
+                let o = [0, 1, 2].map(|_| rng.gen_range(-80.0..80.0));
+                // near a sphere, to hit often enough
+                let c = <[f64; 3]>::from(&spheres[rng.gen_range(0..spheres.len())].center);
+                let at = c.map(|c| c + rng.gen_range(-3.0..3.0));
+                let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+                let t_max = Number::from(rng.gen_range(10.0..300.0));
+                let (got, want) = (group.hit(&r, &Number::ULP, &t_max), brute(&r, &t_max));
+                assert_eq!(got.as_ref().map(|h| &h.t), want.as_ref().map(|h| &h.t));
+                if let (Some(a), Some(b)) = (&got, &want) {
+                    hits += 1;
+                    // spheres can touch the ray at the same t
+                    assert!(a.material == b.material || spheres[a.material as usize].hit(&r, &Number::ULP, &t_max).unwrap().t == b.t);
+                }
+                assert_eq!(group.occluded(&r, &Number::ULP, &t_max), want.is_some());
+            }
+            assert!(hits > 60, "{} hits", hits);
+        }
+    }
+
+    #[test]
+    fn mesh_against_brute_force() {
+        // a bumpy 20 x 20 sheet, 800 triangles
+        let mut rng = StdRng::seed_from_u64(50);
+        let n: usize = 20;
+        let vertices = (0..(n + 1) * (n + 1))
+            .map(|i| Point3::from([(i % (n + 1)) as f64 - 10.0, rng.gen_range(-1.0..1.0), (i / (n + 1)) as f64 - 10.0]))
+            .collect();
+        let faces = (0..n * n)
+            .flat_map(|c| {
+                let (i, j) = (c % n, c / n);
+                let v = |di: usize, dj: usize| (j + dj) * (n + 1) + i + di;
+                [[v(0, 0), v(0, 1), v(1, 1)], [v(0, 0), v(1, 1), v(1, 0)]]
+            })
+            .map(|vertices| Face { vertices, normals: None })
+            .collect();
+        let mesh = Mesh::new(vertices, Vec::new(), faces, 2);
+        let triangles: Vec<Triangle> = (0..mesh.faces.len()).map(|i| mesh.triangle(i)).collect();
+        let b = mesh.bounds().unwrap();
+        assert!(f64::from(&b.min.vec3().x) == -10.0 && f64::from(&b.max.vec3().z) == 10.0);
+
+        let mut hits = 0;
+        for _ in 0..100 {
+            let o = [rng.gen_range(-15.0..15.0), rng.gen_range(5.0..15.0), rng.gen_range(-15.0..15.0)];
+            let at = [rng.gen_range(-10.0..10.0), 0.0, rng.gen_range(-10.0..10.0)];
+            let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+            let mut want: Option<Hit> = None;
+            for t in &triangles {
+                let far = want.as_ref().map_or(&Number::MAX, |h| &h.t);
+                if let Some(h) = t.hit(&r, &Number::ZERO, far) {
+                    want = Some(h);
+                }
+            }
+            let got = mesh.hit(&r, &Number::ZERO, &Number::MAX);
+            assert_eq!(got.as_ref().map(|h| &h.t), want.as_ref().map(|h| &h.t));
+            let t = want.as_ref().map(|h| h.t.clone());
+            hits += t.is_some() as usize;
+            // a shadow ray stopping short of the surface gets through
+            if let Some(mut t) = t {
+                assert!(mesh.occluded(&r, &Number::ZERO, &Number::MAX));
+                t.sub(&Number::from(0.5));
+                assert!(!mesh.occluded(&r, &Number::ZERO, &t));
+            }
+        }
+        assert!(hits > 75, "{} hits", hits);
+    }
+
+    #[test]
+    fn unbounded() {
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::new(Point3::from([0.0, 1.0, -5.0]), Number::ONE, 1)),
+            Box::new(Plane::new(Point3::origin(), Normal3::from([0.0, 1.0, 0.0]), 2)),
+        ];
+        let group = Group::new(shapes, Split::Sah);
+        assert_eq!((group.bounded.len(), group.unbounded.len()), (1, 1));
+        assert!(group.bounds().is_none());
+
+        let down = |x: f64| Ray::new(Point3::from([x, 5.0, -5.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = group.hit(&down(0.0), &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!((h.t, h.material), (Number::from(3), 1));
+        let h = group.hit(&down(3.0), &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!((h.t, h.material), (Number::from(5), 2));
+        assert!(group.occluded(&down(3.0), &Number::ZERO, &Number::MAX));
+        assert!(!group.occluded(&down(3.0), &Number::ZERO, &Number::from(4)));
+
+        let group = Group::new(Vec::new(), Split::Median);
+        assert!(group.bounds().is_none() && group.hit(&down(0.0), &Number::ZERO, &Number::MAX).is_none());
+    }
+
+    #[test]
+    fn bounds_hold_hits() {
+        let sphere: Rc<dyn Shape> = Rc::new(Sphere::new(Point3::from([0.5, 0.0, 0.0]), Number::ONE, 0));
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::new(Point3::from([1.0, -2.0, 0.5]), Number::from(1.5), 0)),
+            Box::new(Cylinder::new(Number::ONE, Number::from(-1), Number::from(2), 0)),
+            Box::new(Cone::new(Number::from(1.5), Number::from(2), 0)),
+            Box::new(Disk::new(Number::from(2), 0)),
+            Box::new(Rect::new(Number::from(2), Number::ONE, 0)),
+            Box::new(Torus::new(Number::from(2), Number::from(0.5), 0)),
+            Box::new(Triangle::new([[0.0, 0.0, 0.0], [2.0, 1.0, 0.0], [0.0, -1.0, 3.0]].map(Point3::from), 0)),
+            Box::new(Cuboid::new(&Point3::from([-1.0, 0.0, -2.0]), &Point3::from([1.0, 1.0, 2.0]), 0)),
+            Box::new(Instance::new(sphere.clone(), Transform::rotate_z(&Number::from(0.7)))),
+            Box::new(Csg::difference(
+                Box::new(Instance::new(sphere.clone(), Transform::scale(&Vec3::from([2.0, 1.0, 1.0])))),
+                Box::new(Cuboid::new(&Point3::from([0.0, -2.0, -2.0]), &Point3::from([3.0, 2.0, 2.0]), 0)),
+            )),
+        ];
+
+        // aimed at points of the bounds, which may be flat
+        let mut rng = StdRng::seed_from_u64(50);
+        let slack = 1.0 / 256.0;
+        for shape in &shapes {
+            let b = shape.bounds().unwrap();
+            let (lo, hi) = (<[f64; 3]>::from(&b.min), <[f64; 3]>::from(&b.max));
+            let mut hits = 0;
+            for _ in 0..300 {
+                let o = [0, 1, 2].map(|_| rng.gen_range(-8.0..8.0));
+                let at = [0, 1, 2].map(|i| lo[i] + (hi[i] - lo[i]) * rng.gen::<f64>());
+                let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+                if let Some(h) = shape.hit(&r, &Number::ZERO, &Number::MAX) {
+                    hits += 1;
+                    let p = <[f64; 3]>::from(&h.point);
+                    assert!((0..3).all(|i| lo[i] - slack <= p[i] && p[i] <= hi[i] + slack), "{:?} outside {:?} {:?}", p, lo, hi);
+                }
+            }
+            assert!(hits > 50, "{} hits", hits);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use crate::int32::Int32;
+use crate::number::Number;
+
+// Rec.709 luminance weights in 16.16; they sum to exactly ONE, so white
+// has luminance 1.
+const LUMA: [Number; 3] = [
+    Number(Int32 { parts: [0x6D, 0x36, 0, 0] }),
+    Number(Int32 { parts: [0x17, 0xB7, 0, 0] }),
+    Number(Int32 { parts: [0x7C, 0x12, 0, 0] }),
+];
+
+// the sRGB transfer curve sampled at i / 256 in 16.16, with linear
+// interpolation between samples. encoding is steep near black, where this
+// is only good to about 1/512; above 1/4 it is within 2 ulp.
+const ENCODE: [i32; 257] = [
+    0, 3255, 5552, 7237, 8618, 9809, 10868, 11828, 12711, 13531, 14300, 15026,
+    15713, 16368, 16995, 17596, 18173, 18731, 19269, 19790, 20295, 20786, 21264, 21728,
+    22182, 22624, 23056, 23479, 23892, 24297, 24694, 25083, 25466, 25841, 26209, 26571,
+    26928, 27278, 27623, 27963, 28298, 28628, 28953, 29274, 29590, 29903, 30211, 30515,
+    30816, 31113, 31406, 31696, 31983, 32267, 32547, 32825, 33099, 33371, 33640, 33906,
+    34170, 34431, 34689, 34946, 35199, 35451, 35700, 35947, 36192, 36435, 36676, 36914,
+    37151, 37386, 37619, 37850, 38080, 38307, 38533, 38758, 38980, 39201, 39421, 39638,
+    39855, 40070, 40283, 40495, 40705, 40914, 41122, 41329, 41534, 41737, 41940, 42141,
+    42341, 42540, 42738, 42934, 43129, 43324, 43517, 43709, 43899, 44089, 44278, 44466,
+    44652, 44838, 45023, 45206, 45389, 45571, 45752, 45932, 46111, 46289, 46466, 46643,
+    46818, 46993, 47167, 47339, 47512, 47683, 47854, 48023, 48192, 48361, 48528, 48695,
+    48861, 49026, 49191, 49354, 49517, 49680, 49842, 50003, 50163, 50323, 50482, 50640,
+    50798, 50955, 51111, 51267, 51422, 51577, 51731, 51884, 52037, 52189, 52341, 52492,
+    52643, 52793, 52942, 53091, 53239, 53387, 53534, 53681, 53827, 53973, 54118, 54263,
+    54407, 54550, 54693, 54836, 54978, 55120, 55261, 55402, 55542, 55682, 55821, 55960,
+    56098, 56236, 56374, 56511, 56648, 56784, 56920, 57055, 57190, 57325, 57459, 57592,
+    57726, 57859, 57991, 58123, 58255, 58386, 58517, 58648, 58778, 58908, 59037, 59166,
+    59295, 59423, 59551, 59678, 59806, 59933, 60059, 60185, 60311, 60436, 60561, 60686,
+    60811, 60935, 61059, 61182, 61305, 61428, 61550, 61672, 61794, 61916, 62037, 62158,
+    62278, 62399, 62518, 62638, 62757, 62876, 62995, 63114, 63232, 63350, 63467, 63585,
+    63702, 63818, 63935, 64051, 64167, 64282, 64398, 64513, 64627, 64742, 64856, 64970,
+    65084, 65197, 65310, 65423, 65536,
+];
+const DECODE: [i32; 257] = [
+    0, 20, 40, 59, 79, 99, 119, 139, 159, 178, 198, 218,
+    240, 263, 286, 312, 338, 365, 394, 424, 456, 489, 523, 558,
+    595, 633, 673, 714, 756, 800, 845, 892, 940, 990, 1041, 1094,
+    1148, 1204, 1262, 1320, 1381, 1443, 1507, 1572, 1639, 1707, 1778, 1850,
+    1923, 1998, 2075, 2154, 2234, 2316, 2400, 2485, 2572, 2661, 2752, 2845,
+    2939, 3035, 3133, 3233, 3334, 3438, 3543, 3650, 3759, 3870, 3983, 4097,
+    4214, 4332, 4452, 4575, 4699, 4825, 4953, 5083, 5215, 5349, 5486, 5624,
+    5764, 5906, 6050, 6196, 6344, 6494, 6646, 6801, 6957, 7115, 7276, 7439,
+    7603, 7770, 7939, 8110, 8283, 8459, 8636, 8816, 8997, 9181, 9368, 9556,
+    9746, 9939, 10134, 10331, 10530, 10732, 10936, 11142, 11350, 11561, 11774, 11989,
+    12206, 12426, 12647, 12872, 13098, 13327, 13558, 13792, 14027, 14266, 14506, 14749,
+    14994, 15241, 15491, 15744, 15998, 16255, 16515, 16777, 17041, 17307, 17576, 17848,
+    18122, 18398, 18677, 18958, 19242, 19528, 19817, 20108, 20401, 20697, 20996, 21297,
+    21601, 21907, 22215, 22526, 22840, 23156, 23475, 23796, 24120, 24446, 24775, 25107,
+    25441, 25777, 26116, 26458, 26803, 27150, 27499, 27851, 28206, 28564, 28924, 29287,
+    29652, 30020, 30391, 30764, 31140, 31518, 31900, 32284, 32670, 33060, 33452, 33847,
+    34244, 34644, 35047, 35453, 35861, 36272, 36686, 37102, 37522, 37944, 38368, 38796,
+    39226, 39659, 40095, 40534, 40975, 41419, 41866, 42316, 42769, 43224, 43682, 44144,
+    44607, 45074, 45544, 46016, 46491, 46969, 47450, 47934, 48421, 48910, 49403, 49898,
+    50396, 50897, 51401, 51908, 52418, 52931, 53446, 53965, 54486, 55011, 55538, 56068,
+    56601, 57138, 57677, 58219, 58764, 59312, 59862, 60416, 60973, 61533, 62096, 62662,
+    63231, 63802, 64377, 64955, 65536,
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Color {
+    pub r: Number,
+    pub g: Number,
+    pub b: Number,
+}
+
+impl Color {
+    pub fn new(r: Number, g: Number, b: Number) -> Self {
+        Self { r, g, b }
+    }
+
+    pub fn black() -> Self {
+        Self::new(Number::ZERO, Number::ZERO, Number::ZERO)
+    }
+
+    pub fn white() -> Self {
+        Self::new(Number::ONE, Number::ONE, Number::ONE)
+    }
+}
+
+// host-side, like From<f64> for Number.
+impl From<[f64; 3]> for Color {
+    fn from(c: [f64; 3]) -> Self {
+        Self::new(Number::from(c[0]), Number::from(c[1]), Number::from(c[2]))
+    }
+}
+
+impl From<&Color> for [f64; 3] {
+    fn from(c: &Color) -> Self {
+        [f64::from(&c.r), f64::from(&c.g), f64::from(&c.b)]
+    }
+}
+
+impl Color {
+    pub fn add(&mut self, other: &Self) {
+        self.r.add(&other.r);
+        self.g.add(&other.g);
+        self.b.add(&other.b);
+    }
+
+    pub fn scale(&mut self, s: &Number) {
+        self.r.mul(s);
+        self.g.mul(s);
+        self.b.mul(s);
+    }
+
+    // componentwise product, e.g. light times surface albedo.
+    pub fn modulate(&mut self, other: &Self) {
+        self.r.mul(&other.r);
+        self.g.mul(&other.g);
+        self.b.mul(&other.b);
+    }
+
+    // into [0, 1].
+    pub fn clamp(&mut self) {
+        clamp(&mut self.r);
+        clamp(&mut self.g);
+        clamp(&mut self.b);
+    }
+}
+
+impl Color {
+    // linear intensity of a linear color, for dithering.
+    pub fn luminance(&self) -> Number {
+        Number::dot(&LUMA, &[self.r.clone(), self.g.clone(), self.b.clone()])
+    }
+
+    // linear to sRGB, clamping first.
+    pub fn encode(&mut self) {
+        self.r = encode(&self.r);
+        self.g = encode(&self.g);
+        self.b = encode(&self.b);
+    }
+
+    // sRGB to linear, clamping first.
+    pub fn decode(&mut self) {
+        self.r = decode(&self.r);
+        self.g = decode(&self.g);
+        self.b = decode(&self.b);
+    }
+}
+
+pub fn encode(n: &Number) -> Number {
+    lookup(&ENCODE, n)
+}
+
+pub fn decode(n: &Number) -> Number {
+    lookup(&DECODE, n)
+}
+
+fn clamp(n: &mut Number) {
+    if n.is_negative() {
+        *n = Number::ZERO;
+    } else if Number::ONE.lt(n) {
+        *n = Number::ONE;
+    }
+}
+
+fn lookup(table: &[i32; 257], n: &Number) -> Number {
+    let mut n = n.clone();
+    clamp(&mut n);
+    if n == Number::ONE {
+        return Number(Int32::from(table[256]));
+    }
+
+    // the high fraction byte picks the sample, the low one interpolates.
+    let i = n.0.parts[1] as usize;
+    let step = Number(Int32::from(table[i + 1] - table[i]));
+    let frac = Number(Int32::from((n.0.parts[0] as i32) * 256));
+
+    let mut r = Number(Int32::from(table[i]));
+    r.mul_add(&step, &frac);
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    const EPS: f64 = 2.0 / 65536.0;
+
+    fn srgb_encode(x: f64) -> f64 {
+        if x <= 0.0031308 { 12.92 * x } else { 1.055 * x.powf(1.0 / 2.4) - 0.055 }
+    }
+
+    fn srgb_decode(x: f64) -> f64 {
+        if x <= 0.04045 { x / 12.92 } else { ((x + 0.055) / 1.055).powf(2.4) }
+    }
+
+    fn random(rng: &mut impl Rng) -> ([f64; 3], Color) {
+        let c = Color::from([rng.gen_range(0.0..4.0), rng.gen_range(0.0..4.0), rng.gen_range(0.0..4.0)]);
+        (<[f64; 3]>::from(&c), c)
+    }
+
+    fn assert_close(got: &Color, want: [f64; 3], eps: f64) {
+        let got = <[f64; 3]>::from(got);
+        for i in 0..3 {
+            assert!((got[i] - want[i]).abs() <= eps, "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let ((fa, a), (fb, b)) = (random(&mut rng), random(&mut rng));
+
+            let mut c = a.clone();
+            c.add(&b);
+            assert_close(&c, [fa[0] + fb[0], fa[1] + fb[1], fa[2] + fb[2]], 0.0);
+
+            let mut c = a.clone();
+            c.modulate(&b);
+            assert_close(&c, [fa[0] * fb[0], fa[1] * fb[1], fa[2] * fb[2]], EPS);
+
+            let s = f64::from(&Number::from(rng.gen_range(0.0..2.0)));
+            let mut c = a.clone();
+            c.scale(&Number::from(s));
+            assert_close(&c, [fa[0] * s, fa[1] * s, fa[2] * s], EPS);
+        }
+    }
+
+    #[test]
+    fn clamp() {
+        let mut c = Color::from([-0.5, 0.25, 3.0]);
+        c.clamp();
+        assert_eq!(c, Color::from([0.0, 0.25, 1.0]));
+    }
+
+    #[test]
+    fn luminance() {
+        assert_eq!(Color::white().luminance(), Number::ONE);
+        assert_eq!(Color::black().luminance(), Number::ZERO);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let (f, c) = random(&mut rng);
+            let want = 0.2126 * f[0] + 0.7152 * f[1] + 0.0722 * f[2];
+            let got = f64::from(&c.luminance());
+            assert!((got - want).abs() < 8.0 / 65536.0, "got {}, want {}", got, want);
+        }
+    }
+
+    #[test]
+    fn gamma() {
+        assert_eq!(encode(&Number::ZERO), Number::ZERO);
+        assert_eq!(encode(&Number::ONE), Number::ONE);
+        assert_eq!(decode(&Number::ONE), Number::ONE);
+        assert_eq!(encode(&Number::from(-1)), Number::ZERO);
+        assert_eq!(decode(&Number::from(2)), Number::ONE);
+
+        for i in 0..=65536 {
+            let x = i as f64 / 65536.0;
+            let n = Number::from(x);
+
+            let got = f64::from(&decode(&n));
+            assert!((got - srgb_decode(x)).abs() < EPS, "decode {}: got {}, want {}", x, got, srgb_decode(x));
+
+            let eps = if x < 1.0 / 16.0 {
+                1.0 / 512.0
+            } else if x < 0.25 {
+                10.0 / 65536.0
+            } else {
+                EPS
+            };
+            let got = f64::from(&encode(&n));
+            assert!((got - srgb_encode(x)).abs() < eps, "encode {}: got {}, want {}", x, got, srgb_encode(x));
+        }
+
+        let mut c = Color::from([0.1, 0.5, 0.9]);
+        c.encode();
+        c.decode();
+        assert_close(&c, [0.1, 0.5, 0.9], 8.0 / 65536.0);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::aabb::Aabb;
+use crate::disk::{level, within};
+use crate::geometry::{Dir3, Normal3, Point3};
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::poly;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// a capped cone around the y axis: a base of the given radius at y = 0,
+// narrowing to the apex at y = height.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cone {
+    pub radius: Number,
+    pub height: Number,
+    pub material: i16,
+}
+
+impl Cone {
+    pub fn new(radius: Number, height: Number, material: i16) -> Self {
+        Self { radius, height, material }
+    }
+}
+
+impl Cone {
+    // the side is h^2 (x^2 + z^2) = r^2 (h - y)^2 for 0 <= y <= h. scaling
+    // the ray as O = (h ox, h oz, r (h - oy)) and D = (h dx, h dz, -r dy)
+    // makes that |O_xz + t D_xz|^2 = (O_w + t D_w)^2, a quadratic whose
+    // coefficients are dot products, taken exactly. the other nappe, above
+    // the apex, is cut off by the range on y.
+    fn quadratic(&self, start: &Point3, direction: &Dir3) -> Option<[Int64; 3]> {
+        let (h, r) = (&self.height, &self.radius);
+        let (origin, direction) = (start.vec3(), direction.vec3());
+        let scaled = |a: &Number, b: &Number| {
+            let mut p = a.clone();
+            p.try_mul(b).map(|_| p)
+        };
+        let mut w = h.clone();
+        w.try_sub(&origin.y).ok()?;
+        let o = [scaled(h, &origin.x).ok()?, scaled(h, &origin.z).ok()?, scaled(r, &w).ok()?];
+        let mut d = [scaled(h, &direction.x).ok()?, scaled(h, &direction.z).ok()?, scaled(r, &direction.y).ok()?];
+        d[2].neg();
+
+        // the w terms enter with a minus sign
+        let lorentz = |a: &[Number; 3], b: &[Number; 3]| {
+            let mut s = Number::sum_of_products(&a[..2], &b[..2]);
+            s.sub(&Int64::product(&a[2].0, &b[2].0));
+            s
+        };
+        let mut b = lorentz(&o, &d);
+        b.shl(1);
+        Some([lorentz(&d, &d), b, lorentz(&o, &o)])
+    }
+}
+
+impl Shape for Cone {
+    // solved from where the ray passes closest to the middle of the cone,
+    // so the coefficients don't grow with the distance to it. each root is
+    // then solved for again from the point it gives, where it is close to
+    // 0: the first solve rounds it to a step of 2^k ulp, see roots.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (h, r) = (&self.height, &self.radius);
+        let direction = ray.direction.vec3();
+        let mut half = h.clone();
+        half.mul(&Number::HALF);
+        let middle = Point3::new(Number::ZERO, half, Number::ZERO);
+        let to_middle = middle.minus(&ray.origin).into_vec3();
+        let d = [direction.x.clone(), direction.y.clone(), direction.z.clone()];
+        let tc = Number::try_dot_ratio(&[to_middle.x, to_middle.y, to_middle.z], &d, &d, &d).ok()?;
+        let mut start = ray.origin.clone();
+        start.offset(&ray.direction, &tc);
+
+        let mut best: Option<(Number, Normal3)> = None;
+        let mut nearest = t_max.clone();
+        for mut s in roots(self.quadratic(&start, &ray.direction)?) {
+            let mut p = start.clone();
+            p.offset(&ray.direction, &s);
+            let nudge = self.quadratic(&p, &ray.direction).map(roots).and_then(|ds| {
+                ds.into_iter().reduce(|a, b| if b.saturating_abs().lt(&a.saturating_abs()) { b } else { a })
+            });
+            if let Some(ds) = nudge {
+                if s.try_add(&ds).is_ok() {
+                    p.offset(&ray.direction, &ds);
+                }
+            }
+
+            let mut t = tc.clone();
+            if t.try_add(&s).is_err() || !t_min.lt(&t) || !t.lt(&nearest) {
+                continue;
+            }
+            if p.vec3().y.is_negative() || h.lt(&p.vec3().y) {
+                continue;
+            }
+            // the gradient (h^2 x, r^2 (h - y), h^2 z), over h / rho. at the
+            // apex, where rho = 0, it vanishes: straight up there.
+            let p = p.into_vec3();
+            let rho = Number::hypot(&[p.x.clone(), p.z.clone()]);
+            let mut normal = Normal3::new(Number::ZERO, Number::ONE, Number::ZERO);
+            if rho != Number::ZERO {
+                let (mut x, mut z, mut up) = (p.x, p.z, r.clone());
+                x.mul(h);
+                z.mul(h);
+                up.mul(&rho);
+                normal = Normal3::new(x, up, z);
+                normal.normalize();
+            }
+            nearest = t.clone();
+            best = Some((t, normal));
+            break;
+        }
+
+        if let Some((t, x, z)) = level(ray, &Number::ZERO, t_min, &nearest) {
+            if within(&x, &z, r) {
+                best = Some((t, Normal3::new(Number::ZERO, Number::from(-1), Number::ZERO)));
+            }
+        }
+
+        let (t, normal) = best?;
+        Some(Hit::new(ray, t, normal, self.material))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around_y(&self.radius, &Number::ZERO, &self.height))
+    }
+}
+
+// the real roots of a t^2 + b t + c, nearest first. t is scaled as 2^k s,
+// with k as small as brings a or b up to about c, for 16.16 would keep few
+// bits of a otherwise. that makes s about the size of the roots, and
+// scaling further would only round them to a coarser step: a ray nearly
+// parallel to a side has a near 0 but a clean root -c / b.
+fn roots([a, b, c]: [Int64; 3]) -> Vec<Number> {
+    let (mut a, mut b) = (a, b);
+    let mut k = 0;
+    while k < 14
+        && a != Int64::from(0)
+        && !quarter(&magnitude(&c)).lt_unsigned(&magnitude(&a))
+        && !halved(&magnitude(&c)).lt_unsigned(&magnitude(&b))
+    {
+        a.shl(2);
+        b.shl(1);
+        k += 1;
+    }
+    let p = poly::from_wide(&[a, b, c]);
+    let unit = Number::from(1 << k);
+    poly::quadratic(&p[0], &p[1], &p[2]).into_iter().filter_map(|mut s| s.try_mul(&unit).ok().map(|_| s)).collect()
+}
+
+fn magnitude(c: &Int64) -> Int64 {
+    let mut m = c.clone();
+    if m.parts[7] >= 0x80 {
+        m.neg();
+    }
+    m
+}
+
+fn halved(c: &Int64) -> Int64 {
+    let mut q = c.clone();
+    q.shr(1);
+    q
+}
+
+fn quarter(c: &Int64) -> Int64 {
+    let mut q = c.clone();
+    q.shr(2);
+    q
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // 45 degrees: radius 2, height 2
+    fn cone() -> Cone {
+        Cone::new(Number::from(2), Number::from(2), 9)
+    }
+
+    // from somewhere within 20 units, toward a random point of the box
+    // |x|, |z| <= half[0], half[1], y in ys; directions of varying length.
+    fn aimed(rng: &mut impl Rng, half: [f64; 2], ys: [f64; 2]) -> Ray {
+        let target = [rng.gen_range(-half[0]..half[0]), rng.gen_range(ys[0]..ys[1]), rng.gen_range(-half[1]..half[1])];
+        let origin = [rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)];
+        let norm = (0..3).map(|i| (target[i] - origin[i]).powi(2)).sum::<f64>().sqrt();
+        let scale = rng.gen_range(0.25..2.0) / norm;
+        Ray::new(Point3::from(origin), Dir3::from([0, 1, 2].map(|i| (target[i] - origin[i]) * scale)))
+    }
+
+    fn hit(c: &Cone, r: &Ray) -> Option<Hit> {
+        c.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    #[test]
+    fn side_apex_and_base() {
+        let c = cone();
+        let s = 0.5f64.sqrt();
+
+        // the side at y = 1, where the radius is 1
+        let r = Ray::new(Point3::from([-3.0, 1.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert_eq!(h.normal, Normal3::from([-s, s, 0.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 9);
+
+        // from the axis, inside
+        let r = Ray::new(Point3::from([0.0, 1.0, 0.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::ONE);
+        assert!(!h.front_face);
+
+        // straight down onto the apex, and up through the base
+        let r = Ray::new(Point3::from([0.0, 5.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(3));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        let r = Ray::new(Point3::from([0.5, -5.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(5));
+        assert_eq!(h.normal, Normal3::from([0.0, -1.0, 0.0]));
+
+        // above the apex the mirrored nappe isn't there
+        let r = Ray::new(Point3::from([-3.0, 3.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        assert!(hit(&c, &r).is_none());
+        // nor under the base
+        let r = Ray::new(Point3::from([-3.0, -1.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        assert!(hit(&c, &r).is_none());
+
+        // parallel to the far side, so a quadratic with a = 0: the near
+        // side only
+        let r = Ray::new(Point3::from([-3.0, 3.0, 0.0]), Dir3::from([1.0, -1.0, 0.0]));
+        assert_eq!(hit(&c, &r).unwrap().point, Point3::from([-1.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let (mut hits, mut misses) = (0, 0);
+        for _ in 0..1000 {
+            let c = Cone::new(Number::from(rng.gen_range(0.5..10.0)), Number::from(rng.gen_range(0.5..20.0)), 0);
+            let (rad, ht) = (f64::from(&c.radius), f64::from(&c.height));
+            let r = aimed(&mut rng, [1.5 * rad, 1.5 * rad], [-0.25 * ht, 1.25 * ht]);
+
+            let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let at = |t: f64| [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2]];
+            let len = d.iter().map(|c| c * c).sum::<f64>().sqrt();
+
+            // crossings of the side and the base, each with its distance from
+            // the nearest edge (rim or apex) to leave out close calls, and
+            // the sine of the angle it meets the surface at
+            let k = rad / ht;
+            let w = ht - o[1];
+            let a = d[0] * d[0] + d[2] * d[2] - k * k * d[1] * d[1];
+            let b = o[0] * d[0] + o[2] * d[2] + k * k * w * d[1];
+            let cc = o[0] * o[0] + o[2] * o[2] - k * k * w * w;
+            let mut crossings = vec![];
+            let disc = b * b - a * cc;
+            if disc >= 0.0 {
+                for t in [(-b - disc.sqrt()) / a, (-b + disc.sqrt()) / a] {
+                    let p = at(t);
+                    let rho = (p[0] * p[0] + p[2] * p[2]).sqrt();
+                    let n = [ht * p[0], rad * rho, ht * p[2]];
+                    let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                    let n = n.map(|c| c / l);
+                    let sine = (n[0] * d[0] + n[1] * d[1] + n[2] * d[2]).abs() / len;
+                    crossings.push((t, p[1].min(ht - p[1]).min(rho), n, sine));
+                }
+            }
+            let t = -o[1] / d[1];
+            let p = at(t);
+            crossings.push((t, rad - (p[0] * p[0] + p[2] * p[2]).sqrt(), [0.0, -1.0, 0.0], d[1].abs() / len));
+            let want = crossings.iter().filter(|c| c.0 > 0.0 && c.1 >= 0.0).min_by(|a, b| a.0.total_cmp(&b.0));
+
+            // the point found is off the surface by an ulp or so, which moves
+            // it along the ray by that over the sine: leave out rays within
+            // 1/16 of tangent, and near misses, which could go either way
+            let near_miss = disc < 0.0 && (-disc).sqrt() / a.abs().max(1e-9) < 1.0 / 64.0;
+            if near_miss || crossings.iter().any(|c| c.1.abs() < 1.0 / 64.0 || c.0.abs() < 1.0 / 1024.0 || c.3 < 1.0 / 16.0) {
+                continue;
+            }
+            match (hit(&c, &r), want) {
+                (None, None) => misses += 1,
+                (Some(h), Some(&(t, _, n, _))) => {
+                    hits += 1;
+                    assert!((f64::from(&h.t) - t).abs() * len < 1.0 / 512.0, "t: got {}, want {}", f64::from(&h.t), t);
+                    let outward = <[f64; 3]>::from(&h.normal).map(|c| if h.front_face { c } else { -c });
+                    for i in 0..3 {
+                        assert!((outward[i] - n[i]).abs() < 1.0 / 512.0, "normal: got {:?}, want {:?}", outward, n);
+                    }
+                }
+                (got, want) => panic!("got {:?}, want {:?}", got, want),
+            }
+        }
+        assert!(hits > 100 && misses > 100, "{} hits, {} misses", hits, misses);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::aabb::Aabb;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::{Shape, Span};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Op {
+    Union,
+    Intersection,
+    // left minus right
+    Difference,
+}
+
+impl Op {
+    fn keeps(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Op::Union => in_left || in_right,
+            Op::Intersection => in_left && in_right,
+            Op::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// two solids combined, by their spans along each ray. the operands are
+// closed solids, planes as half-spaces, or other Csg nodes.
+pub struct Csg {
+    pub op: Op,
+    pub left: Box<dyn Shape>,
+    pub right: Box<dyn Shape>,
+}
+
+impl Csg {
+    pub fn new(op: Op, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Self { op, left, right }
+    }
+
+    pub fn union(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Self::new(Op::Union, left, right)
+    }
+
+    pub fn intersection(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Self::new(Op::Intersection, left, right)
+    }
+
+    pub fn difference(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Self::new(Op::Difference, left, right)
+    }
+}
+
+impl Shape for Csg {
+    // the first end of the first span: where the ray goes in, or comes out
+    // if it starts inside.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let first = self.spans(ray, t_min, t_max).into_iter().next()?;
+        first.enter.or(first.exit)
+    }
+
+    fn spans(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Vec<Span> {
+        combine(self.op, self.left.spans(ray, t_min, t_max), self.right.spans(ray, t_min, t_max))
+    }
+
+    // a union reaches as far as either operand, so needs both bounded. the
+    // others keep within the left operand, and an intersection within the
+    // right one too, which bounds a solid cut by a plane.
+    fn bounds(&self) -> Option<Aabb> {
+        let (left, right) = (self.left.bounds(), self.right.bounds());
+        match self.op {
+            Op::Union => {
+                let mut bounds = left?;
+                bounds.union(&right?);
+                Some(bounds)
+            }
+            Op::Intersection => match (left, right) {
+                (Some(mut a), Some(b)) => {
+                    a.min.max(&b.min);
+                    a.max.min(&b.max);
+                    Some(a)
+                }
+                (a, b) => a.or(b),
+            },
+            Op::Difference => left,
+        }
+    }
+}
+
+// walks the ends of both span lists in order of t, tracking whether the ray
+// is inside each operand, and keeps the ends where that changes whether it
+// is inside the result. a kept surface faces the way the result goes: the
+// inside of a hole cut by Difference comes out of the right operand as a
+// back face and goes in as a front face, and vice versa.
+fn combine(op: Op, left: Vec<Span>, right: Vec<Span>) -> Vec<Span> {
+    let starts_inside = |spans: &[Span]| spans.first().is_some_and(|s| s.enter.is_none());
+    let (mut in_left, mut in_right) = (starts_inside(&left), starts_inside(&right));
+
+    // (hit, from the left operand, going in)
+    let mut ends: Vec<(Hit, bool, bool)> = Vec::new();
+    for (spans, is_left) in [(left, true), (right, false)] {
+        for span in spans {
+            ends.extend(span.enter.map(|h| (h, is_left, true)));
+            ends.extend(span.exit.map(|h| (h, is_left, false)));
+        }
+    }
+    ends.sort_by(|a, b| {
+        if a.0.t.lt(&b.0.t) {
+            std::cmp::Ordering::Less
+        } else if b.0.t.lt(&a.0.t) {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    });
+
+    let mut inside = op.keeps(in_left, in_right);
+    let mut open: Option<Option<Hit>> = if inside { Some(None) } else { None };
+    let mut spans = Vec::new();
+    for (mut hit, is_left, going_in) in ends {
+        if is_left {
+            in_left = going_in;
+        } else {
+            in_right = going_in;
+        }
+        if op.keeps(in_left, in_right) == inside {
+            continue;
+        }
+
+        inside = !inside;
+        hit.front_face = inside;
+        if inside {
+            open = Some(Some(hit));
+        } else {
+            spans.push(Span { enter: open.take().flatten(), exit: Some(hit) });
+        }
+    }
+    if let Some(enter) = open {
+        spans.push(Span { enter, exit: None });
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::cuboid::Cuboid;
+    use crate::geometry::{Dir3, Normal3, Point3};
+    use crate::plane::Plane;
+    use crate::sphere::Sphere;
+
+    fn sphere(c: [f64; 3], r: f64) -> Box<dyn Shape> {
+        Box::new(Sphere::new(Point3::from(c), Number::from(r), 1))
+    }
+
+    fn cube(lo: [f64; 3], hi: [f64; 3]) -> Box<dyn Shape> {
+        Box::new(Cuboid::new(&Point3::from(lo), &Point3::from(hi), 2))
+    }
+
+    fn ray(o: [f64; 3], d: [f64; 3]) -> Ray {
+        Ray::new(Point3::from(o), Dir3::from(d))
+    }
+
+    // every boundary the ray crosses, as (t, front_face), by asking for the
+    // next hit after each one.
+    fn walk(s: &dyn Shape, r: &Ray) -> Vec<(f64, bool)> {
+        let mut found = Vec::new();
+        let mut from = Number::ZERO;
+        while let Some(h) = s.hit(r, &from, &Number::MAX) {
+            found.push((f64::from(&h.t), h.front_face));
+            from = h.t;
+        }
+        found
+    }
+
+    fn near(got: &[(f64, bool)], want: &[(f64, bool)]) {
+        assert_eq!(got.len(), want.len(), "got {:?}, want {:?}", got, want);
+        for (g, w) in got.iter().zip(want) {
+            assert!((g.0 - w.0).abs() < 1.0 / 4096.0 && g.1 == w.1, "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    #[test]
+    fn sphere_minus_box() {
+        // a unit sphere with the corner x, y, z > 0 cut away
+        let s = Csg::difference(sphere([0.0, 0.0, 0.0], 1.0), cube([0.0, 0.0, 0.0], [2.0, 2.0, 2.0]));
+
+        // down into the notch: the floor of the cut, facing up, front side
+        let r = ray([0.5, 0.5, 5.0], [0.0, 0.0, -1.0]);
+        let h = s.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(5));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 2);
+        let s2 = 0.5f64.sqrt();
+        near(&walk(&s, &r), &[(5.0, true), (5.0 + s2, false)]);
+
+        // along x: the sphere, then out through the wall of the cut
+        let r = ray([-5.0, 0.5, 0.5], [1.0, 0.0, 0.0]);
+        near(&walk(&s, &r), &[(5.0 - s2, true), (5.0, false)]);
+        let h = s.hit(&r, &Number::from(4.5), &Number::MAX).unwrap();
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+        assert!(!h.front_face);
+
+        // clear of the cut, the whole sphere
+        let r = ray([-5.0, -0.5, 0.0], [1.0, 0.0, 0.0]);
+        let c = 0.75f64.sqrt();
+        near(&walk(&s, &r), &[(5.0 - c, true), (5.0 + c, false)]);
+
+        // through the cut only: nothing
+        let r = ray([0.5, 0.5, 5.0], [0.0, 0.0, 1.0]);
+        assert!(s.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+    }
+
+    #[test]
+    fn lens_and_shell() {
+        let lens = Csg::intersection(sphere([-0.5, 0.0, 0.0], 1.0), sphere([0.5, 0.0, 0.0], 1.0));
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&lens, &r), &[(4.5, true), (5.5, false)]);
+        let r = ray([0.0, 5.0, 0.0], [0.0, -1.0, 0.0]);
+        let y = 0.75f64.sqrt();
+        near(&walk(&lens, &r), &[(5.0 - y, true), (5.0 + y, false)]);
+
+        // a hollow sphere: in and out of the shell twice, the inner surface
+        // facing inward
+        let shell = Csg::difference(sphere([0.0, 0.0, 0.0], 2.0), sphere([0.0, 0.0, 0.0], 1.0));
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&shell, &r), &[(3.0, true), (4.0, false), (6.0, true), (7.0, false)]);
+        let h = shell.hit(&r, &Number::from(3), &Number::MAX).unwrap();
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+
+        // from inside the cavity, and from inside the shell itself
+        let r = ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&shell, &r), &[(1.0, true), (2.0, false)]);
+        let r = ray([1.5, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&shell, &r), &[(0.5, false)]);
+    }
+
+    #[test]
+    fn union_and_nesting() {
+        // the inner surfaces of an overlap disappear
+        let pair = Csg::union(sphere([-0.5, 0.0, 0.0], 1.0), sphere([0.5, 0.0, 0.0], 1.0));
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&pair, &r), &[(3.5, true), (6.5, false)]);
+
+        // a box with a hole drilled through it, cut by a half-space
+        let drilled = Csg::difference(cube([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]), cube([-0.25, -0.25, -2.0], [0.25, 0.25, 2.0]));
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&drilled, &r), &[(4.0, true), (4.75, false), (5.25, true), (6.0, false)]);
+        let r = ray([0.0, 0.0, -5.0], [0.0, 0.0, 1.0]);
+        assert!(drilled.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+
+        let floor = Box::new(Plane::new(Point3::from([0.0, 0.0, 0.0]), Normal3::from([1.0, 0.0, 0.0]), 3));
+        let half = Csg::difference(Box::new(drilled), floor);
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        near(&walk(&half, &r), &[(5.25, true), (6.0, false)]);
+        let r = ray([5.0, 0.0, 0.0], [-1.0, 0.0, 0.0]);
+        near(&walk(&half, &r), &[(4.0, true), (4.75, false)]);
+    }
+
+    #[test]
+    fn grazing() {
+        // a ray touching the cutting sphere at one point doesn't open a
+        // hole the rest of the way
+        let s = Csg::difference(cube([-2.0, -2.0, -2.0], [2.0, 2.0, 2.0]), sphere([0.0, 1.0, 0.0], 1.0));
+        let r = ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let got = walk(&s, &r);
+        assert_eq!((got[0], got[got.len() - 1]), ((3.0, true), (7.0, false)), "{:?}", got);
+    }
+
+    #[test]
+    fn random_against_f64() {
+        // sphere minus box, against the spans each covers along the ray
+        let mut rng = StdRng::seed_from_u64(45);
+        let mut hits = 0;
+        for _ in 0..300 {
+            let c: [f64; 3] = [0, 1, 2].map(|_| rng.gen_range(-2.0..2.0));
+            let rad = rng.gen_range(1.0..3.0);
+            let (lo, hi) = ([0, 1, 2].map(|_| rng.gen_range(-3.0..0.0)), [0, 1, 2].map(|_| rng.gen_range(0.0..3.0)));
+            let s = Csg::difference(sphere(c, rad), cube(lo, hi));
+            let origin = [0, 1, 2].map(|_| rng.gen_range(-10.0..10.0));
+            let target = c.map(|x| x + rng.gen_range(-rad..rad));
+            let scale = rng.gen_range(0.05..0.2);
+            let r = ray(origin, [0, 1, 2].map(|i| (target[i] - origin[i]) * scale));
+
+            let (c, rad) = (c.map(|x| f64::from(&Number::from(x))), f64::from(&Number::from(rad)));
+            let (lo, hi) = (lo.map(|x| f64::from(&Number::from(x))), hi.map(|x| f64::from(&Number::from(x))));
+            let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let len = d.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+            // the sphere's span, and the box's from its slabs
+            let oc = [0, 1, 2].map(|i| o[i] - c[i]);
+            let (a, b) = (len * len, (0..3).map(|i| oc[i] * d[i]).sum::<f64>());
+            let disc = b * b - a * ((0..3).map(|i| oc[i] * oc[i]).sum::<f64>() - rad * rad);
+            let ball = [(-b - disc.max(0.0).sqrt()) / a, (-b + disc.max(0.0).sqrt()) / a];
+            let (mut slab, mut faces) = ([f64::NEG_INFINITY, f64::INFINITY], [0, 0]);
+            for i in 0..3 {
+                let (t0, t1) = ((lo[i] - o[i]) / d[i], (hi[i] - o[i]) / d[i]);
+                if slab[0] < t0.min(t1) {
+                    (slab[0], faces[0]) = (t0.min(t1), i);
+                }
+                if t0.max(t1) < slab[1] {
+                    (slab[1], faces[1]) = (t0.max(t1), i);
+                }
+            }
+
+            // leave out rays within 1/16 of tangent to either, where an ulp
+            // off the surface is a long way along the ray, or crossing two
+            // of the four boundaries (or the origin) too close together to
+            // tell apart in 16.16
+            let sines = [disc.abs().sqrt() / len / rad, d[faces[0]].abs() / len, d[faces[1]].abs() / len];
+            let mut ts = [0.0, ball[0], ball[1], slab[0], slab[1]];
+            ts.sort_by(f64::total_cmp);
+            if sines.iter().any(|s| *s < 1.0 / 16.0) || ts.windows(2).any(|w| (w[1] - w[0]) * len < 1.0 / 256.0) {
+                continue;
+            }
+
+            // the sphere's span less the box's, and where the first of what
+            // is left starts or ends after the origin
+            let mut spans = vec![];
+            if disc > 0.0 {
+                if slab[0] < slab[1] {
+                    spans.push([ball[0], ball[1].min(slab[0])]);
+                    spans.push([ball[0].max(slab[1]), ball[1]]);
+                } else {
+                    spans.push(ball);
+                }
+            }
+            let want = spans.iter().filter(|s| s[0] < s[1]).flat_map(|s| [(s[0], true), (s[1], false)]).filter(|e| e.0 > 0.0).min_by(|a, b| a.0.total_cmp(&b.0));
+
+            match (s.hit(&r, &Number::ZERO, &Number::MAX), want) {
+                (None, None) => {}
+                (Some(h), Some((t, front))) => {
+                    hits += 1;
+                    let got = f64::from(&h.t);
+                    assert!((got - t).abs() * len < 1.0 / 1024.0, "got {}, want {}", got, t);
+                    assert_eq!(h.front_face, front, "at t = {}", t);
+                }
+                (got, want) => panic!("got {:?}, want {:?}", got.map(|h| f64::from(&h.t)), want),
+            }
+        }
+        assert!(hits > 100, "{} hits", hits);
+    }
+}
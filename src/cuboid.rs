@@ -0,0 +1,209 @@
+use crate::aabb::Aabb;
+use crate::geometry::{Normal3, Point3};
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::{Shape, Span};
+
+// a solid axis-aligned box, in local space. rotate it with a transform.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cuboid {
+    pub bounds: Aabb,
+    pub material: i16,
+}
+
+impl Cuboid {
+    // the box spanned by two opposite corners, in any order.
+    pub fn new(a: &Point3, b: &Point3, material: i16) -> Self {
+        Self { bounds: Aabb::new(a, b), material }
+    }
+}
+
+type End = (Number, Option<usize>);
+
+impl Cuboid {
+    // the slab test from Aabb::hit, keeping track of which face each end of
+    // the span lies on: (t, axis) going in and coming out. the axis is None
+    // only when the direction is zero.
+    fn slabs(&self, ray: &Ray) -> Option<(End, End)> {
+        let (min, max, origin, direction) = (self.bounds.min.vec3(), self.bounds.max.vec3(), ray.origin.vec3(), ray.direction.vec3());
+        let axes = [
+            (&min.x, &max.x, &origin.x, &direction.x, &ray.inv_direction.x),
+            (&min.y, &max.y, &origin.y, &direction.y, &ray.inv_direction.y),
+            (&min.z, &max.z, &origin.z, &direction.z, &ray.inv_direction.z),
+        ];
+
+        let (mut near, mut far) = ((Number::MIN, None), (Number::MAX, None));
+        for (i, (lo, hi, origin, direction, inv)) in axes.into_iter().enumerate() {
+            if *direction == Number::ZERO {
+                if origin.lt(lo) || hi.lt(origin) {
+                    return None;
+                }
+                continue;
+            }
+
+            let (mut t0, mut t1) = (lo.saturating_sub(origin).saturating_mul(inv), hi.saturating_sub(origin).saturating_mul(inv));
+            if direction.is_negative() {
+                (t0, t1) = (t1, t0);
+            }
+            if near.0.lt(&t0) {
+                near = (t0, Some(i));
+            }
+            if t1.lt(&far.0) {
+                far = (t1, Some(i));
+            }
+        }
+        if far.0.lt(&near.0) {
+            return None;
+        }
+        Some((near, far))
+    }
+
+    // the entry face's normal points back along the ray, the exit face's
+    // along it.
+    fn face(&self, ray: &Ray, t: Number, axis: usize, exit: bool) -> Hit {
+        let d = ray.direction.vec3();
+        let mut n = [Number::ZERO, Number::ZERO, Number::ZERO];
+        n[axis] = if [&d.x, &d.y, &d.z][axis].is_negative() ^ exit { Number::ONE } else { Number::from(-1) };
+        let [x, y, z] = n;
+        Hit::new(ray, t, Normal3::new(x, y, z), self.material)
+    }
+}
+
+impl Shape for Cuboid {
+    // the entry face is hit first; from inside the box, the exit face.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (near, far) = self.slabs(ray)?;
+        match (near, far) {
+            ((t, Some(i)), _) if t_min.lt(&t) && t.lt(t_max) => Some(self.face(ray, t, i, false)),
+            (_, (t, Some(i))) if t_min.lt(&t) && t.lt(t_max) => Some(self.face(ray, t, i, true)),
+            _ => None,
+        }
+    }
+
+    // both ends from the one slab test, even through an edge where they
+    // meet.
+    fn spans(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Vec<Span> {
+        let Some(((near, near_axis), (far, far_axis))) = self.slabs(ray) else { return Vec::new() };
+        if !t_min.lt(&far) || !near.lt(t_max) {
+            return Vec::new();
+        }
+        let enter = near_axis.filter(|_| t_min.lt(&near)).map(|i| self.face(ray, near, i, false));
+        let exit = far_axis.filter(|_| far.lt(t_max)).map(|i| self.face(ray, far, i, true));
+        vec![Span { enter, exit }]
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bounds.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geometry::Dir3;
+
+    fn unit() -> Cuboid {
+        Cuboid::new(&Point3::from([1.0, 1.0, 1.0]), &Point3::from([-1.0, -1.0, -1.0]), 5)
+    }
+
+    fn hit(c: &Cuboid, r: &Ray) -> Option<Hit> {
+        c.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    #[test]
+    fn faces() {
+        let c = unit();
+        let axes = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        for a in axes {
+            for s in [1.0, -1.0] {
+                // from outside along -s a: the face at +s a, facing out
+                let r = Ray::new(Point3::from(a.map(|c| 3.0 * s * c + 0.5 * (1.0 - c))), Dir3::from(a.map(|c| -s * c)));
+                let h = hit(&c, &r).unwrap();
+                assert_eq!(h.t, Number::from(2));
+                assert_eq!(h.normal, Normal3::from(a.map(|c| s * c)));
+                assert!(h.front_face);
+                assert_eq!(h.material, 5);
+
+                // from the center: the same face, from inside
+                let r = Ray::new(Point3::origin(), Dir3::from(a.map(|c| s * c)));
+                let h = hit(&c, &r).unwrap();
+                assert_eq!(h.t, Number::ONE);
+                assert!(!h.front_face);
+                assert_eq!(h.normal, Normal3::from(a.map(|c| -s * c)));
+            }
+        }
+
+        // past, behind and parallel to the box
+        let r = Ray::new(Point3::from([0.0, 2.0, -5.0]), Dir3::from([0.0, 0.0, 1.0]));
+        assert!(hit(&c, &r).is_none());
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::from([0.0, 0.0, -1.0]));
+        assert!(hit(&c, &r).is_none());
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::from([0.0, 0.0, 1.0]));
+        assert!(c.hit(&r, &Number::ZERO, &Number::from(4)).is_none());
+        assert_eq!(c.hit(&r, &Number::from(4), &Number::MAX).unwrap().t, Number::from(6));
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let mut p = || Point3::from([rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)]);
+            let c = Cuboid::new(&p(), &p(), 0);
+            let r = Ray::new(p(), Dir3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]));
+
+            let (lo, hi) = (<[f64; 3]>::from(&c.bounds.min), <[f64; 3]>::from(&c.bounds.max));
+            let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let (mut near, mut far) = ((f64::MIN, 0), (f64::MAX, 0));
+            let (mut entries, mut exits) = ([0.0; 3], [0.0; 3]);
+            for i in 0..3 {
+                let (t0, t1) = ((lo[i] - o[i]) / d[i], (hi[i] - o[i]) / d[i]);
+                let (t0, t1) = (t0.min(t1), t0.max(t1));
+                (entries[i], exits[i]) = (t0, t1);
+                if t0 > near.0 {
+                    near = (t0, i);
+                }
+                if t1 < far.0 {
+                    far = (t1, i);
+                }
+            }
+            let want = if far.0 < near.0 {
+                None
+            } else if near.0 > 0.0 {
+                Some(near)
+            } else if far.0 > 0.0 {
+                Some(far)
+            } else {
+                None
+            };
+
+            // rays through an edge, or barely meeting the box, could go
+            // either way: in or out, and which face's normal they get
+            let eps = 1.0 / 1024.0;
+            entries.sort_by(f64::total_cmp);
+            exits.sort_by(f64::total_cmp);
+            if (far.0 - near.0).abs() < eps || near.0.abs() < eps || far.0.abs() < eps {
+                continue;
+            }
+            if entries[2] - entries[1] < eps || exits[1] - exits[0] < eps {
+                continue;
+            }
+            match (hit(&c, &r), want) {
+                (None, None) => {}
+                (Some(h), Some((t, axis))) => {
+                    hits += 1;
+                    assert!((f64::from(&h.t) - t).abs() < eps, "got {}, want {}", f64::from(&h.t), t);
+                    let n = <[f64; 3]>::from(&h.normal);
+                    assert_eq!(n[axis].abs(), 1.0, "normal {:?} not on axis {}", n, axis);
+                    // facing the ray
+                    assert!(n[axis] * d[axis] < 0.0);
+                }
+                (got, want) => panic!("got {:?}, want {:?}", got, want),
+            }
+        }
+        assert!(hits > 50, "{} hits", hits);
+    }
+}
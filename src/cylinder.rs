@@ -0,0 +1,192 @@
+use crate::aabb::Aabb;
+use crate::disk::{level, within};
+use crate::geometry::Normal3;
+use crate::number::Number;
+use crate::poly::crossings;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// a capped cylinder around the y axis, from y = min to y = max.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cylinder {
+    pub radius: Number,
+    pub min: Number,
+    pub max: Number,
+    pub material: i16,
+}
+
+impl Cylinder {
+    pub fn new(radius: Number, min: Number, max: Number, material: i16) -> Self {
+        if max.lt(&min) {
+            return Self { radius, min: max, max: min, material };
+        }
+        Self { radius, min, max, material }
+    }
+}
+
+impl Shape for Cylinder {
+    // the nearest of the side and the two caps. the rims belong to both.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (origin, direction) = (ray.origin.vec3(), ray.direction.vec3());
+        let mut best: Option<(Number, Normal3)> = None;
+        let mut nearest = t_max.clone();
+
+        if let Some((near, far)) = crossings(&[origin.x.clone(), origin.z.clone()], &[direction.x.clone(), direction.z.clone()], &self.radius) {
+            for t in [near, far] {
+                if !t_min.lt(&t) || !t.lt(&nearest) {
+                    continue;
+                }
+                let mut y = origin.y.clone();
+                if y.try_mul_add(&direction.y, &t).is_err() || y.lt(&self.min) || self.max.lt(&y) {
+                    continue;
+                }
+                let p = ray.at(&t).into_vec3();
+                let (mut x, mut z) = (p.x, p.z);
+                x.div(&self.radius);
+                z.div(&self.radius);
+                nearest = t.clone();
+                best = Some((t, Normal3::new(x, Number::ZERO, z)));
+                break;
+            }
+        }
+
+        for (y, up) in [(&self.min, false), (&self.max, true)] {
+            if let Some((t, x, z)) = level(ray, y, t_min, &nearest) {
+                if within(&x, &z, &self.radius) {
+                    let normal = Normal3::new(Number::ZERO, if up { Number::ONE } else { Number::from(-1) }, Number::ZERO);
+                    nearest = t.clone();
+                    best = Some((t, normal));
+                }
+            }
+        }
+
+        let (t, normal) = best?;
+        Some(Hit::new(ray, t, normal, self.material))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around_y(&self.radius, &self.min, &self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geometry::{Dir3, Point3};
+
+    fn unit() -> Cylinder {
+        Cylinder::new(Number::ONE, Number::from(2), Number::from(-1), 8)
+    }
+
+    // from somewhere within 20 units, toward a random point of the box
+    // |x|, |z| <= half[0], half[1], y in ys; directions of varying length.
+    fn aimed(rng: &mut impl Rng, half: [f64; 2], ys: [f64; 2]) -> Ray {
+        let target = [rng.gen_range(-half[0]..half[0]), rng.gen_range(ys[0]..ys[1]), rng.gen_range(-half[1]..half[1])];
+        let origin = [rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)];
+        let norm = (0..3).map(|i| (target[i] - origin[i]).powi(2)).sum::<f64>().sqrt();
+        let scale = rng.gen_range(0.25..2.0) / norm;
+        Ray::new(Point3::from(origin), Dir3::from([0, 1, 2].map(|i| (target[i] - origin[i]) * scale)))
+    }
+
+    fn hit(c: &Cylinder, r: &Ray) -> Option<Hit> {
+        c.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    #[test]
+    fn side_and_caps() {
+        let c = unit();
+        assert_eq!((c.min.clone(), c.max.clone()), (Number::from(-1), Number::from(2)));
+
+        // the side, from outside and from the axis
+        let r = Ray::new(Point3::from([-3.0, 0.5, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 8);
+        let r = Ray::new(Point3::from([0.0, 0.5, 0.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::ONE);
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+
+        // the caps, from above and below, and from inside
+        let r = Ray::new(Point3::from([0.5, 5.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(3));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        let r = Ray::new(Point3::from([0.5, -5.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(4));
+        assert_eq!(h.normal, Normal3::from([0.0, -1.0, 0.0]));
+        let r = Ray::new(Point3::from([0.5, 0.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        let h = hit(&c, &r).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert!(!h.front_face);
+
+        // over the top, and through the rim
+        let r = Ray::new(Point3::from([-3.0, 2.5, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        assert!(hit(&c, &r).is_none());
+        let r = Ray::new(Point3::from([-3.0, 4.0, 0.0]), Dir3::from([1.0, -1.0, 0.0]));
+        assert_eq!(hit(&c, &r).unwrap().point, Point3::from([-1.0, 2.0, 0.0]));
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let (mut hits, mut misses) = (0, 0);
+        for _ in 0..1000 {
+            let (y0, y1): (f64, f64) = (rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0));
+            let c = Cylinder::new(Number::from(rng.gen_range(0.5..10.0)), Number::from(y0), Number::from(y1), 0);
+            let (rad, lo, hi) = (f64::from(&c.radius), f64::from(&c.min), f64::from(&c.max));
+            let r = aimed(&mut rng, [1.5 * rad, 1.5 * rad], [lo - 1.0, hi + 1.0]);
+            let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let at = |t: f64| [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2]];
+            let len = d.iter().map(|c| c * c).sum::<f64>().sqrt();
+
+            // every crossing of the side or a cap plane, with how far it is
+            // from an edge of the solid to judge whether it's a close call,
+            // and the sine of the angle it meets the surface at
+            let mut crossings = vec![];
+            let (a, b, cc) = (d[0] * d[0] + d[2] * d[2], o[0] * d[0] + o[2] * d[2], o[0] * o[0] + o[2] * o[2] - rad * rad);
+            if b * b - a * cc >= 0.0 {
+                for t in [(-b - (b * b - a * cc).sqrt()) / a, (-b + (b * b - a * cc).sqrt()) / a] {
+                    let y = at(t)[1];
+                    let n = [at(t)[0] / rad, 0.0, at(t)[2] / rad];
+                    crossings.push((t, (y - lo).min(hi - y), n, (n[0] * d[0] + n[2] * d[2]).abs() / len));
+                }
+            }
+            for (y, n) in [(lo, -1.0), (hi, 1.0)] {
+                let t = (y - o[1]) / d[1];
+                let p = at(t);
+                crossings.push((t, rad - (p[0] * p[0] + p[2] * p[2]).sqrt(), [0.0, n, 0.0], d[1].abs() / len));
+            }
+            let want = crossings.iter().filter(|c| c.0 > 0.0 && c.1 >= 0.0).min_by(|a, b| a.0.total_cmp(&b.0));
+
+            let close = crossings.iter().any(|c| c.1.abs() < 1.0 / 256.0 || c.0.abs() < 1.0 / 1024.0) || (b * b - a * cc >= 0.0 && (b * b - a * cc).sqrt() < 1.0 / 256.0);
+            if close {
+                continue;
+            }
+            match (hit(&c, &r), want) {
+                (None, None) => misses += 1,
+                (Some(h), Some(&(t, _, n, sine))) => {
+                    hits += 1;
+                    // the point found is off the surface by a few ulp,
+                    // which moves it along the ray by that over the sine, and
+                    // turns the normal by that over the radius
+                    let along = 4.0 / 65536.0 / sine;
+                    assert!((f64::from(&h.t) - t).abs() * len < 1.0 / 1024.0 + along, "t: got {}, want {}", f64::from(&h.t), t);
+                    let outward = <[f64; 3]>::from(&h.normal).map(|c| if h.front_face { c } else { -c });
+                    for i in 0..3 {
+                        assert!((outward[i] - n[i]).abs() < 1.0 / 1024.0 + along / rad, "normal: got {:?}, want {:?}", outward, n);
+                    }
+                }
+                (got, want) => panic!("got {:?}, want {:?}", got, want),
+            }
+        }
+        assert!(hits > 100 && misses > 100, "{} hits, {} misses", hits, misses);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::aabb::Aabb;
+use crate::geometry::Normal3;
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// a disk in the y = 0 plane, centered on the origin, facing +y.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Disk {
+    pub radius: Number,
+    pub material: i16,
+}
+
+impl Disk {
+    pub fn new(radius: Number, material: i16) -> Self {
+        Self { radius, material }
+    }
+}
+
+impl Shape for Disk {
+    // (u, v) are the local x and z of the hit.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (t, x, z) = level(ray, &Number::ZERO, t_min, t_max)?;
+        if !within(&x, &z, &self.radius) {
+            return None;
+        }
+
+        let mut hit = Hit::new(ray, t, Normal3::new(Number::ZERO, Number::ONE, Number::ZERO), self.material);
+        (hit.u, hit.v) = (x, z);
+        Some(hit)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around_y(&self.radius, &Number::ZERO, &Number::ZERO))
+    }
+}
+
+// where the ray crosses the plane at height y, as (t, x, z), if it does so
+// within (t_min, t_max). x and z are fused from t, so they are off by no
+// more than the rounding of t itself.
+pub fn level(ray: &Ray, y: &Number, t_min: &Number, t_max: &Number) -> Option<(Number, Number, Number)> {
+    let (origin, direction) = (ray.origin.vec3(), ray.direction.vec3());
+    let mut t = y.clone();
+    t.try_sub(&origin.y).ok()?;
+    t.try_div(&direction.y).ok()?;
+    if !t_min.lt(&t) || !t.lt(t_max) {
+        return None;
+    }
+
+    let (mut x, mut z) = (origin.x.clone(), origin.z.clone());
+    x.try_mul_add(&direction.x, &t).ok()?;
+    z.try_mul_add(&direction.z, &t).ok()?;
+    Some((t, x, z))
+}
+
+// x^2 + z^2 <= r^2, exactly.
+pub fn within(x: &Number, z: &Number, r: &Number) -> bool {
+    let d = Number::sum_of_products(&[x.clone(), z.clone()], &[x.clone(), z.clone()]);
+    !Int64::product(&r.0, &r.0).lt_unsigned(&d)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::geometry::{Dir3, Point3};
+
+    #[test]
+    fn hit() {
+        let d = Disk::new(Number::from(2), 6);
+        let r = Ray::new(Point3::from([1.0, 3.0, -1.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = d.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(3));
+        assert_eq!(h.point, Point3::from([1.0, 0.0, -1.0]));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 6);
+        assert_eq!((h.u, h.v), (Number::from(1), Number::from(-1)));
+
+        // from below
+        let r = Ray::new(Point3::from([0.0, -1.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        let h = d.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, -1.0, 0.0]));
+
+        // the rim is on the disk, an ulp past it isn't
+        let r = Ray::new(Point3::from([2.0, 1.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        assert!(d.hit(&r, &Number::ZERO, &Number::MAX).is_some());
+        let r = Ray::new(Point3::from([2.0 + 1.0 / 65536.0, 1.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        assert!(d.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+
+        // parallel, and in the plane
+        for y in [1.0, 0.0] {
+            let r = Ray::new(Point3::from([-5.0, y, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+            assert!(d.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+        }
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = rand::thread_rng();
+        let (mut hits, mut misses) = (0, 0);
+        for _ in 0..1000 {
+            let d = Disk::new(Number::from(rng.gen_range(0.5..20.0)), 0);
+            let r = Ray::new(
+                Point3::from([rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)]),
+                Dir3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]),
+            );
+
+            let (o, dir) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let t = -o[1] / dir[1];
+            let (x, z) = (o[0] + t * dir[0], o[2] + t * dir[2]);
+            let rim = (x * x + z * z).sqrt() - f64::from(&d.radius);
+
+            // rays through the rim, or nearly in the plane, could go either way
+            if rim.abs() < 1.0 / 256.0 || !(1.0 / 1024.0..32767.0).contains(&t.abs()) {
+                continue;
+            }
+            match d.hit(&r, &Number::ZERO, &Number::MAX) {
+                Some(h) => {
+                    hits += 1;
+                    assert!(t > 0.0 && rim < 0.0, "hit at t = {}, {} past the rim", t, rim);
+                    assert!((f64::from(&h.t) - t).abs() < 1.0 / 16384.0 * t.max(1.0), "got {}, want {}", f64::from(&h.t), t);
+                }
+                None => {
+                    misses += 1;
+                    assert!(t < 0.0 || rim > 0.0, "missed at t = {}, {} inside the rim", t, -rim);
+                }
+            }
+        }
+        assert!(hits > 50 && misses > 50, "{} hits, {} misses", hits, misses);
+    }
+}
@@ -0,0 +1,94 @@
+use std::cell::Cell;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithError {
+    DivideByZero,
+    NegativeSqrt,
+    Overflow,
+    OutOfRange,
+}
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArithError::DivideByZero => write!(f, "divide by zero"),
+            ArithError::NegativeSqrt => write!(f, "square root of negative number"),
+            ArithError::Overflow => write!(f, "arithmetic overflow"),
+            ArithError::OutOfRange => write!(f, "digit out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ArithError {}
+
+// What the infallible forms (`div`, `sqrt`, `div_u4`, ...) do when their
+// `try_` counterpart would have returned an error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    // panic, except on overflow which wraps around as it always has.
+    Panic,
+    // clamp to the nearest representable value and carry on.
+    Saturate,
+    // saturate, and keep the error around until `take_error` is called.
+    Record,
+}
+
+thread_local! {
+    static POLICY: Cell<Policy> = const { Cell::new(Policy::Panic) };
+    static LAST_ERROR: Cell<Option<ArithError>> = const { Cell::new(None) };
+}
+
+pub fn policy() -> Policy {
+    POLICY.with(|p| p.get())
+}
+
+pub fn set_policy(policy: Policy) {
+    POLICY.with(|p| p.set(policy));
+}
+
+pub fn take_error() -> Option<ArithError> {
+    LAST_ERROR.with(|e| e.take())
+}
+
+// Reports `err` under the current policy. Callers fall back to a saturated
+// result when this returns.
+pub(crate) fn raise(err: ArithError) {
+    match policy() {
+        Policy::Panic => panic!("{}", err),
+        Policy::Saturate => {}
+        Policy::Record => LAST_ERROR.with(|e| e.set(Some(err))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_defaults_to_panic() {
+        assert_eq!(policy(), Policy::Panic);
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn raise_panics() {
+        raise(ArithError::DivideByZero);
+    }
+
+    #[test]
+    fn raise_saturates() {
+        set_policy(Policy::Saturate);
+        raise(ArithError::Overflow);
+        assert_eq!(take_error(), None);
+    }
+
+    #[test]
+    fn raise_records() {
+        set_policy(Policy::Record);
+        raise(ArithError::NegativeSqrt);
+        raise(ArithError::OutOfRange);
+        assert_eq!(take_error(), Some(ArithError::OutOfRange));
+        assert_eq!(take_error(), None);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::error::ArithError;
+use crate::number::Number;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+// Vec3 with a meaning attached. Only the operations that make sense between
+// them are defined: point - point is a direction, point + direction is a
+// point, and each kind transforms its own way (points take the translation,
+// directions don't, normals go by the inverse transpose). The Vec3 inside
+// is only reachable within the crate, for the arithmetic underneath.
+
+/// ```
+/// use raytracer_jack_proto::geometry::{Dir3, Normal3, Point3};
+/// use raytracer_jack_proto::number::Number;
+/// use raytracer_jack_proto::ray::{Hit, Ray};
+/// let mut p = Point3::origin();
+/// p.add(&Dir3::from([1.0, 0.0, 0.0]));
+/// let ray = Ray::new(p, Dir3::from([1.0, 0.0, 0.0]));
+/// Hit::new(&ray, Number::ONE, Normal3::from([-1.0, 0.0, 0.0]), 0);
+/// ```
+///
+/// a point plus a point isn't anything:
+///
+/// ```compile_fail
+/// use raytracer_jack_proto::geometry::Point3;
+/// let mut p = Point3::origin();
+/// p.add(&Point3::origin());
+/// ```
+///
+/// nor is a direction a normal:
+///
+/// ```compile_fail
+/// use raytracer_jack_proto::geometry::{Dir3, Point3};
+/// use raytracer_jack_proto::number::Number;
+/// use raytracer_jack_proto::ray::{Hit, Ray};
+/// let ray = Ray::new(Point3::origin(), Dir3::from([1.0, 0.0, 0.0]));
+/// Hit::new(&ray, Number::ONE, Dir3::from([-1.0, 0.0, 0.0]), 0);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Point3(Vec3);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dir3(Vec3);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Normal3(Vec3);
+
+impl Point3 {
+    pub fn new(x: Number, y: Number, z: Number) -> Self {
+        Self(Vec3::new(x, y, z))
+    }
+
+    pub fn origin() -> Self {
+        Self(Vec3::zero())
+    }
+
+    pub(crate) fn vec3(&self) -> &Vec3 {
+        &self.0
+    }
+
+    pub(crate) fn into_vec3(self) -> Vec3 {
+        self.0
+    }
+}
+
+impl Dir3 {
+    pub fn new(x: Number, y: Number, z: Number) -> Self {
+        Self(Vec3::new(x, y, z))
+    }
+
+    pub fn zero() -> Self {
+        Self(Vec3::zero())
+    }
+
+    // a normal taken as a direction, e.g. to reflect along it.
+    pub fn along(n: Normal3) -> Self {
+        Self(n.0)
+    }
+
+    pub(crate) fn vec3(&self) -> &Vec3 {
+        &self.0
+    }
+
+    pub(crate) fn into_vec3(self) -> Vec3 {
+        self.0
+    }
+}
+
+impl Normal3 {
+    pub fn new(x: Number, y: Number, z: Number) -> Self {
+        Self(Vec3::new(x, y, z))
+    }
+
+    // a direction that is known to be perpendicular to a surface, e.g. the
+    // cross product of two edges.
+    pub fn perpendicular(d: Dir3) -> Self {
+        Self(d.0)
+    }
+
+    pub(crate) fn vec3(&self) -> &Vec3 {
+        &self.0
+    }
+}
+
+// host-side, like From<f64> for Number.
+impl From<[f64; 3]> for Point3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self(Vec3::from(v))
+    }
+}
+
+impl From<[f64; 3]> for Dir3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self(Vec3::from(v))
+    }
+}
+
+impl From<[f64; 3]> for Normal3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self(Vec3::from(v))
+    }
+}
+
+impl From<&Point3> for [f64; 3] {
+    fn from(p: &Point3) -> Self {
+        <[f64; 3]>::from(&p.0)
+    }
+}
+
+impl From<&Dir3> for [f64; 3] {
+    fn from(d: &Dir3) -> Self {
+        <[f64; 3]>::from(&d.0)
+    }
+}
+
+impl From<&Normal3> for [f64; 3] {
+    fn from(n: &Normal3) -> Self {
+        <[f64; 3]>::from(&n.0)
+    }
+}
+
+impl Point3 {
+    pub fn add(&mut self, d: &Dir3) {
+        self.0.add(&d.0);
+    }
+
+    pub fn sub(&mut self, d: &Dir3) {
+        self.0.sub(&d.0);
+    }
+
+    // self + d * t, rounded once per component.
+    pub fn offset(&mut self, d: &Dir3, t: &Number) {
+        self.0.x.mul_add(&d.0.x, t);
+        self.0.y.mul_add(&d.0.y, t);
+        self.0.z.mul_add(&d.0.z, t);
+    }
+
+    // as offset, but an error, leaving self as it was, if a component
+    // leaves 16.16.
+    pub fn try_offset(&mut self, d: &Dir3, t: &Number) -> Result<(), ArithError> {
+        let mut p = self.0.clone();
+        p.x.try_mul_add(&d.0.x, t)?;
+        p.y.try_mul_add(&d.0.y, t)?;
+        p.z.try_mul_add(&d.0.z, t)?;
+        self.0 = p;
+        Ok(())
+    }
+
+    // self - other.
+    pub fn minus(&self, other: &Self) -> Dir3 {
+        let mut d = self.0.clone();
+        d.sub(&other.0);
+        Dir3(d)
+    }
+
+    pub fn min(&mut self, other: &Self) {
+        self.0.min(&other.0);
+    }
+
+    pub fn max(&mut self, other: &Self) {
+        self.0.max(&other.0);
+    }
+
+    pub fn transform(&mut self, t: &Transform) {
+        self.0 = t.transform_point(&self.0);
+    }
+}
+
+impl Dir3 {
+    pub fn add(&mut self, other: &Self) {
+        self.0.add(&other.0);
+    }
+
+    pub fn sub(&mut self, other: &Self) {
+        self.0.sub(&other.0);
+    }
+
+    pub fn scale(&mut self, s: &Number) {
+        self.0.scale(s);
+    }
+
+    pub fn neg(&mut self) {
+        self.0.neg();
+    }
+
+    pub fn dot(&self, other: &Self) -> Number {
+        self.0.dot(&other.0)
+    }
+
+    pub fn cross(&mut self, other: &Self) {
+        self.0.cross(&other.0);
+    }
+
+    pub fn length(&self) -> Number {
+        self.0.length()
+    }
+
+    pub fn normalize(&mut self) {
+        self.0.normalize();
+    }
+
+    pub fn transform(&mut self, t: &Transform) {
+        self.0 = t.transform_direction(&self.0);
+    }
+}
+
+impl Normal3 {
+    pub fn neg(&mut self) {
+        self.0.neg();
+    }
+
+    pub fn dot(&self, d: &Dir3) -> Number {
+        self.0.dot(&d.0)
+    }
+
+    pub fn normalize(&mut self) {
+        self.0.normalize();
+    }
+
+    // by the inverse transpose; not renormalized.
+    pub fn transform(&mut self, t: &Transform) {
+        self.0 = t.transform_normal(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_arithmetic() {
+        let mut p = Point3::from([1.0, 2.0, 3.0]);
+        let q = Point3::from([4.0, 0.0, -1.0]);
+        let d = q.minus(&p);
+        assert_eq!(d, Dir3::from([3.0, -2.0, -4.0]));
+
+        p.add(&d);
+        assert_eq!(p, q);
+        p.sub(&d);
+        assert_eq!(p, Point3::from([1.0, 2.0, 3.0]));
+
+        p.offset(&Dir3::from([0.5, 0.0, -1.0]), &Number::from(4));
+        assert_eq!(p, Point3::from([3.0, 2.0, -1.0]));
+
+        // past 16.16 in y: an error, and p as it was
+        assert!(p.try_offset(&Dir3::from([1.0, 16384.0, 0.0]), &Number::from(2)).is_err());
+        assert_eq!(p, Point3::from([3.0, 2.0, -1.0]));
+        assert!(p.try_offset(&Dir3::from([1.0, 1.0, 0.0]), &Number::from(2)).is_ok());
+        assert_eq!(p, Point3::from([5.0, 4.0, -1.0]));
+    }
+
+    #[test]
+    fn transforms() {
+        // a shear: x += y. translation moves points only.
+        let mut t = Transform::from_rows([
+            [Number::ONE, Number::ONE, Number::ZERO, Number::from(5)],
+            [Number::ZERO, Number::ONE, Number::ZERO, Number::ZERO],
+            [Number::ZERO, Number::ZERO, Number::ONE, Number::ZERO],
+        ]);
+
+        let mut p = Point3::from([0.0, 1.0, 0.0]);
+        p.transform(&t);
+        assert_eq!(p, Point3::from([6.0, 1.0, 0.0]));
+
+        let mut d = Dir3::from([0.0, 1.0, 0.0]);
+        d.transform(&t);
+        assert_eq!(d, Dir3::from([1.0, 1.0, 0.0]));
+
+        // the plane y = x has normal (1, -1, 0); sheared it becomes y = x / 2,
+        // whose normal is (1, -2, 0).
+        let mut n = Normal3::from([1.0, -1.0, 0.0]);
+        n.transform(&t);
+        assert_eq!(n, Normal3::from([1.0, -2.0, 0.0]));
+        let mut along = Dir3::from([1.0, 1.0, 0.0]);
+        along.transform(&t);
+        assert_eq!(n.dot(&along), Number::ZERO);
+
+        t.inverse();
+        p.transform(&t);
+        assert_eq!(p, Point3::from([0.0, 1.0, 0.0]));
+    }
+}
@@ -0,0 +1,499 @@
+use std::fmt;
+
+use crate::aabb::Aabb;
+use crate::geometry::{Normal3, Point3};
+use crate::int32::Int32;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+use crate::triangle::Triangle;
+
+// slack on the per-cell height test, so rounding in the ray's height at the
+// cell's edges can't skip a hit along its top or bottom.
+const MARGIN: Number = Number(Int32 { parts: [0, 1, 0, 0] });
+
+#[derive(Debug)]
+pub enum PgmError {
+    Io(std::io::Error),
+    // not a P2 or P5 file, or a header field that isn't a number
+    Malformed(&'static str),
+    Truncated,
+    // under 2 x 2 samples, which is no cells at all
+    TooSmall,
+}
+
+impl fmt::Display for PgmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgmError::Io(err) => write!(f, "reading pgm: {}", err),
+            PgmError::Malformed(what) => write!(f, "malformed pgm: {}", what),
+            PgmError::Truncated => write!(f, "pgm ends before its last sample"),
+            PgmError::TooSmall => write!(f, "pgm has fewer than 2 x 2 samples"),
+        }
+    }
+}
+
+impl std::error::Error for PgmError {}
+
+// terrain over a grid of heights, in local space: sample (i, j) sits at
+// x = i, z = j, one unit apart, so a 512 x 256 grid spans 511 x 255 and
+// leaves room in 16.16 to scale it up with a transform. each cell is cut
+// into two triangles along its (i, j) - (i + 1, j + 1) diagonal.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Heightfield {
+    pub width: usize,
+    pub depth: usize,
+    // row by row: sample (i, j) at j * width + i
+    pub heights: Vec<Number>,
+    pub bounds: Aabb,
+    pub material: i16,
+}
+
+impl Heightfield {
+    pub fn new(width: usize, depth: usize, heights: Vec<Number>, material: i16) -> Self {
+        assert!(width >= 2 && depth >= 2, "heightfield needs at least 2 x 2 samples");
+        assert!(width <= 0x7FFF && depth <= 0x7FFF, "heightfield too large for 16.16");
+        assert_eq!(heights.len(), width * depth, "heightfield sample count");
+
+        let (mut lo, mut hi) = (heights[0].clone(), heights[0].clone());
+        for h in &heights {
+            if h.lt(&lo) {
+                lo = h.clone();
+            }
+            if hi.lt(h) {
+                hi = h.clone();
+            }
+        }
+        let far = Point3::new(Number::from((width - 1) as i16), hi, Number::from((depth - 1) as i16));
+        let bounds = Aabb::new(&Point3::new(Number::ZERO, lo, Number::ZERO), &far);
+        Self { width, depth, heights, bounds, material }
+    }
+
+    // 8-bit samples, each step high.
+    pub fn from_bytes(width: usize, depth: usize, samples: &[u8], step: &Number, material: i16) -> Self {
+        let heights = samples
+            .iter()
+            .map(|s| {
+                let mut h = Number::from(*s as i16);
+                h.mul(step);
+                h
+            })
+            .collect();
+        Self::new(width, depth, heights, material)
+    }
+
+    // a binary (P5) or plain (P2) graymap, its maxval mapped to height.
+    pub fn from_pgm(bytes: &[u8], height: &Number, material: i16) -> Result<Self, PgmError> {
+        let (width, depth, maxval, samples) = parse_pgm(bytes)?;
+        // sample / maxval in 16.16 terms: both raw, so the scales cancel
+        let den = Number::sum_of_products(&[Number::ONE], &[Number(Int32::from(maxval as i32))]);
+        let mut heights = Vec::with_capacity(samples.len());
+        for s in samples {
+            let num = Number::sum_of_products(std::slice::from_ref(height), &[Number(Int32::from(s as i32))]);
+            heights.push(Number::try_ratio(&num, &den).map_err(|_| PgmError::Malformed("height out of range"))?);
+        }
+        Ok(Self::new(width, depth, heights, material))
+    }
+
+    pub fn load(path: &str, height: &Number, material: i16) -> Result<Self, PgmError> {
+        let bytes = std::fs::read(path).map_err(PgmError::Io)?;
+        Self::from_pgm(&bytes, height, material)
+    }
+}
+
+impl Heightfield {
+    fn height(&self, i: usize, j: usize) -> &Number {
+        &self.heights[j * self.width + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        Point3::new(Number::from(i as i16), self.height(i, j).clone(), Number::from(j as i16))
+    }
+
+    // from the slopes to the neighbouring samples: central differences
+    // inside the grid, one-sided along its edges.
+    fn vertex_normal(&self, i: usize, j: usize) -> Normal3 {
+        let slope = |a: &Number, b: &Number, wide: bool| {
+            let mut s = a.clone();
+            s.sub(b);
+            if wide {
+                s.mul(&Number::HALF);
+            }
+            s
+        };
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.depth - 1));
+        let mut n = Normal3::new(
+            slope(self.height(i0, j), self.height(i1, j), i1 - i0 == 2),
+            Number::ONE,
+            slope(self.height(i, j0), self.height(i, j1), j1 - j0 == 2),
+        );
+        n.normalize();
+        n
+    }
+
+    // the cell's two triangles, counter-clockwise seen from above.
+    fn triangles(&self, i: usize, j: usize) -> [[(usize, usize); 3]; 2] {
+        [[(i, j), (i, j + 1), (i + 1, j + 1)], [(i, j), (i + 1, j + 1), (i + 1, j)]]
+    }
+
+    fn cell_hit(&self, ray: &Ray, i: usize, j: usize, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let mut best: Option<(Hit, [(usize, usize); 3])> = None;
+        for corners in self.triangles(i, j) {
+            let far = best.as_ref().map_or(t_max, |(h, _)| &h.t);
+            let triangle = Triangle::new(corners.map(|(i, j)| self.vertex(i, j)), self.material);
+            if let Some(hit) = triangle.hit(ray, t_min, far) {
+                best = Some((hit, corners));
+            }
+        }
+
+        // again on the winner, now with vertex normals to shade by. the same
+        // arithmetic on the same ray finds the same t.
+        let (_, corners) = best?;
+        let smooth = Triangle::smooth(
+            corners.map(|(i, j)| self.vertex(i, j)),
+            corners.map(|(i, j)| self.vertex_normal(i, j)),
+            self.material,
+        );
+        let mut hit = smooth.hit(ray, t_min, t_max)?;
+        // u, v across the whole grid, for texturing
+        hit.u = hit.point.vec3().x.clone();
+        hit.u.div(&Number::from((self.width - 1) as i16));
+        hit.v = hit.point.vec3().z.clone();
+        hit.v.div(&Number::from((self.depth - 1) as i16));
+        Some(hit)
+    }
+
+    // whether the ray's height over [t0, t1] meets the cell's heights.
+    fn spans_cell(&self, ray: &Ray, i: usize, j: usize, t0: &Number, t1: &Number) -> bool {
+        let corners = [self.height(i, j), self.height(i + 1, j), self.height(i, j + 1), self.height(i + 1, j + 1)];
+        let (mut lo, mut hi) = (corners[0].clone(), corners[0].clone());
+        for h in corners {
+            if h.lt(&lo) {
+                lo = h.clone();
+            }
+            if hi.lt(h) {
+                hi = h.clone();
+            }
+        }
+        let y = |t: &Number| ray.origin.vec3().y.saturating_add(&ray.direction.vec3().y.saturating_mul(t));
+        let (y0, y1) = (y(t0), y(t1));
+        let (lo, hi) = (lo.saturating_sub(&MARGIN), hi.saturating_add(&MARGIN));
+        let above = hi.lt(&y0) && hi.lt(&y1);
+        let below = y0.lt(&lo) && y1.lt(&lo);
+        !above && !below
+    }
+}
+
+impl Shape for Heightfield {
+    // a 2d DDA over the cells the ray crosses in x and z, nearest first, so
+    // the first cell with a hit has the nearest one.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let span = self.bounds.hit(ray, t_min, t_max)?;
+        let (origin, direction, inv) = (ray.origin.vec3(), ray.direction.vec3(), &ray.inv_direction);
+
+        let start = ray.at(&span.lo);
+        let cell = |n: &Number, cells: usize| (i32::from(n.0.clone()) >> 16).clamp(0, cells as i32 - 1);
+        let (mut i, mut j) = (cell(&start.vec3().x, self.width - 1), cell(&start.vec3().z, self.depth - 1));
+
+        // the t where the ray leaves cell k along one axis
+        let next = |k: i32, o: &Number, d: &Number, inv: &Number| {
+            if *d == Number::ZERO {
+                return Number::MAX;
+            }
+            let edge = Number::from((if d.is_negative() { k } else { k + 1 }) as i16);
+            edge.saturating_sub(o).saturating_mul(inv)
+        };
+
+        let mut enter = span.lo.clone();
+        loop {
+            let (tx, tz) = (next(i, &origin.x, &direction.x, &inv.x), next(j, &origin.z, &direction.z, &inv.z));
+            let mut exit = if tx.lt(&tz) { tx.clone() } else { tz.clone() };
+            if span.hi.lt(&exit) {
+                exit = span.hi.clone();
+            }
+
+            if self.spans_cell(ray, i as usize, j as usize, &enter, &exit) {
+                if let Some(hit) = self.cell_hit(ray, i as usize, j as usize, t_min, t_max) {
+                    return Some(hit);
+                }
+            }
+
+            if !exit.lt(&span.hi) {
+                return None;
+            }
+            if tx.lt(&tz) {
+                i += if direction.x.is_negative() { -1 } else { 1 };
+            } else {
+                j += if direction.z.is_negative() { -1 } else { 1 };
+            }
+            if i < 0 || j < 0 || i >= self.width as i32 - 1 || j >= self.depth as i32 - 1 {
+                return None;
+            }
+            enter = exit;
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bounds.clone())
+    }
+}
+
+// the header is magic, width, height and maxval, separated by whitespace
+// and # comments; then one whitespace byte and the samples, in binary (one
+// byte each, or two big-endian past maxval 255) for P5 or as text for P2.
+fn parse_pgm(bytes: &[u8]) -> Result<(usize, usize, u16, Vec<u16>), PgmError> {
+    let binary = match bytes.get(..2) {
+        Some(b"P5") => true,
+        Some(b"P2") => false,
+        _ => return Err(PgmError::Malformed("not a P2 or P5 graymap")),
+    };
+
+    let mut at = 2;
+    let mut fields = [0usize; 3];
+    for field in fields.iter_mut() {
+        *field = number(bytes, &mut at)?;
+    }
+    let [width, depth, maxval] = fields;
+    if maxval == 0 || maxval > 0xFFFF {
+        return Err(PgmError::Malformed("maxval out of range"));
+    }
+    if width < 2 || depth < 2 {
+        return Err(PgmError::TooSmall);
+    }
+    if width > 0x7FFF || depth > 0x7FFF {
+        return Err(PgmError::Malformed("too many samples"));
+    }
+
+    let count = width * depth;
+    let mut samples = Vec::with_capacity(count);
+    if binary {
+        // the single whitespace byte ending the header
+        at += 1;
+        let size = if maxval > 0xFF { 2 } else { 1 };
+        let body = bytes.get(at..at + count * size).ok_or(PgmError::Truncated)?;
+        for s in body.chunks(size) {
+            samples.push(s.iter().fold(0u16, |acc, b| (acc << 8) | *b as u16));
+        }
+    } else {
+        for _ in 0..count {
+            let sample = number(bytes, &mut at).map_err(|err| match err {
+                PgmError::Malformed(_) if at >= bytes.len() => PgmError::Truncated,
+                err => err,
+            })?;
+            // checked before it is narrowed, or 65536 would pass as 0
+            if sample > maxval {
+                return Err(PgmError::Malformed("sample above maxval"));
+            }
+            samples.push(sample as u16);
+        }
+    }
+    if samples.iter().any(|s| *s as usize > maxval) {
+        return Err(PgmError::Malformed("sample above maxval"));
+    }
+    Ok((width, depth, maxval as u16, samples))
+}
+
+// the next decimal number, past whitespace and comments.
+fn number(bytes: &[u8], at: &mut usize) -> Result<usize, PgmError> {
+    loop {
+        match bytes.get(*at) {
+            Some(b'#') => {
+                while !matches!(bytes.get(*at), None | Some(b'\n')) {
+                    *at += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => *at += 1,
+            _ => break,
+        }
+    }
+    let start = *at;
+    let mut n = 0usize;
+    while let Some(c) = bytes.get(*at).filter(|c| c.is_ascii_digit()) {
+        n = n.saturating_mul(10).saturating_add((c - b'0') as usize);
+        *at += 1;
+    }
+    if *at == start {
+        return Err(PgmError::Malformed("expected a number"));
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geometry::Dir3;
+
+    fn hit(f: &Heightfield, r: &Ray) -> Option<Hit> {
+        f.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    // every triangle in f64, nearest hit: what the DDA has to match.
+    fn brute_force(f: &Heightfield, o: &[f64; 3], d: &[f64; 3]) -> Option<f64> {
+        let mut best: Option<f64> = None;
+        for j in 0..f.depth - 1 {
+            for i in 0..f.width - 1 {
+                for corners in f.triangles(i, j) {
+                    let v = corners.map(|(i, j)| [i as f64, f64::from(f.height(i, j)), j as f64]);
+                    if let Some(t) = triangle(o, d, &v) {
+                        best = Some(best.map_or(t, |b: f64| b.min(t)));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn slope() {
+        // y = x / 2 + z / 4 over 8 x 6 samples
+        let heights = (0..6).flat_map(|j| (0..8).map(move |i| Number::from(i as f64 / 2.0 + j as f64 / 4.0))).collect();
+        let f = Heightfield::new(8, 6, heights, 7);
+        assert_eq!(f.bounds.max, Point3::from([7.0, 4.75, 5.0]));
+
+        let r = Ray::new(Point3::from([3.0, 10.0, 2.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = hit(&f, &r).unwrap();
+        assert_eq!(h.t, Number::from(8));
+        assert!(h.front_face);
+        assert_eq!(h.material, 7);
+        assert!((f64::from(&h.u) - 3.0 / 7.0).abs() < 1.0 / 65536.0 && (f64::from(&h.v) - 0.4).abs() < 1.0 / 65536.0);
+        let want = [-0.5, 1.0, -0.25].map(|c| c / 1.3125f64.sqrt());
+        let n = <[f64; 3]>::from(&h.normal);
+        assert!((0..3).all(|i| (n[i] - want[i]).abs() < 1.0 / 1024.0), "{:?}", n);
+
+        // from below, along the grid; then past it, and short of it
+        let r = Ray::new(Point3::from([10.0, 1.0, 2.5]), Dir3::from([-1.0, 0.0, 0.0]));
+        let h = hit(&f, &r).unwrap();
+        assert!((f64::from(&h.t) - 9.25).abs() < 1.0 / 4096.0);
+        assert!(!h.front_face);
+        let r = Ray::new(Point3::from([-2.0, 9.0, 2.5]), Dir3::from([1.0, 0.0, 0.0]));
+        assert!(hit(&f, &r).is_none());
+        let r = Ray::new(Point3::from([3.0, 10.0, 2.0]), Dir3::from([0.0, -1.0, 0.0]));
+        assert!(f.hit(&r, &Number::ZERO, &Number::from(7)).is_none());
+    }
+
+    #[test]
+    fn random_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let mut hits = 0;
+        for _ in 0..20 {
+            let (w, d) = (rng.gen_range(2..12), rng.gen_range(2..12));
+            let samples: Vec<u8> = (0..w * d).map(|_| rng.gen()).collect();
+            let f = Heightfield::from_bytes(w, d, &samples, &Number::from(1.0 / 32.0), 0);
+            for _ in 0..20 {
+                let o = [rng.gen_range(-4.0..16.0), rng.gen_range(-2.0..12.0), rng.gen_range(-4.0..16.0)];
+                let at = [rng.gen_range(0.0..w as f64), rng.gen_range(0.0..8.0), rng.gen_range(0.0..d as f64)];
+                let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+                let (o, dir) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+                match (hit(&f, &r), brute_force(&f, &o, &dir)) {
+                    (Some(h), Some(t)) => {
+                        hits += 1;
+                        assert!((f64::from(&h.t) - t).abs() < 1.0 / 1024.0, "{:?}: {} vs {}", r, f64::from(&h.t), t);
+                    }
+                    (None, None) => {}
+                    (got, want) => panic!("{:?}: {:?} vs {:?}", r, got.map(|h| h.t), want),
+                }
+            }
+        }
+        assert!(hits > 50, "{} hits", hits);
+    }
+
+    #[test]
+    fn pgm() {
+        let plain = b"P2\n# a comment\n3 2\n# another\n10\n0 5 10\n10 5 0\n";
+        let f = Heightfield::from_pgm(plain, &Number::from(4), 1).unwrap();
+        assert_eq!((f.width, f.depth), (3, 2));
+        assert_eq!(f.heights, [0.0, 2.0, 4.0, 4.0, 2.0, 0.0].map(Number::from).to_vec());
+
+        let mut binary = b"P5 2 2 255\n".to_vec();
+        binary.extend([0, 51, 255, 102]);
+        let f = Heightfield::from_pgm(&binary, &Number::from(5), 1).unwrap();
+        assert_eq!(f.heights, [0.0, 1.0, 5.0, 2.0].map(Number::from).to_vec());
+
+        let mut wide = b"P5 2 2 65535\n".to_vec();
+        wide.extend([0, 0, 0x80, 0x00, 0xFF, 0xFF, 0x40, 0x00]);
+        let f = Heightfield::from_pgm(&wide, &Number::from(2), 1).unwrap();
+        let want = [0.0, 65536.0 / 65535.0, 2.0, 32768.0 / 65535.0];
+        assert!(f.heights.iter().zip(want).all(|(h, w)| (f64::from(h) - w).abs() < 1.0 / 65536.0), "{:?}", f.heights);
+
+        let err = |bytes: &[u8]| Heightfield::from_pgm(bytes, &Number::ONE, 0).unwrap_err();
+        assert!(matches!(err(b"P6 2 2 255\n"), PgmError::Malformed(_)));
+        assert!(matches!(err(b"P2 2 x 255\n"), PgmError::Malformed(_)));
+        assert!(matches!(err(b"P2 2 2 9\n1 2 3"), PgmError::Truncated));
+        assert!(matches!(err(b"P5 2 2 255\n\x01\x02\x03"), PgmError::Truncated));
+        assert!(matches!(err(b"P2 1 5 9\n1 2 3 4 5"), PgmError::TooSmall));
+        assert!(matches!(err(b"P2 2 2 9\n1 2 3 10"), PgmError::Malformed(_)));
+        assert!(matches!(err(b"P2 2 2 9\n1 2 3 65536"), PgmError::Malformed(_)));
+        assert!(matches!(Heightfield::load("/nonexistent.pgm", &Number::ONE, 0), Err(PgmError::Io(_))));
+    }
+
+    // a pinhole over a 512 x 256 field, one ray per 16 x 16 block of a 512 x
+    // 256 image, against the same two triangles per cell walked in f64.
+    #[test]
+    fn full_size() {
+        let (w, d) = (512, 256);
+        let height = |i: usize, j: usize| 8.0 + 6.0 * (i as f64 / 37.0).sin() * (j as f64 / 23.0).cos();
+        let heights = (0..d).flat_map(|j| (0..w).map(move |i| Number::from(height(i, j)))).collect();
+        let f = Heightfield::new(w, d, heights, 0);
+        let y = |i: usize, j: usize| f64::from(f.height(i, j));
+
+        let eye = [255.5, 30.0, -20.0];
+        let mut hits = 0;
+        for py in (8..256).step_by(16) {
+            for px in (8..512).step_by(16) {
+                let dir = [(px as f64 - 256.0) / 256.0, -0.15 - (py as f64 - 128.0) / 512.0, 1.0];
+                let r = Ray::new(Point3::from(eye), Dir3::from(dir));
+                let (o, dir) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+
+                // every hit over the cells under the ray's path, sampled
+                // finely enough in t to visit each of them
+                let mut all = Vec::new();
+                let mut t = 0.0;
+                while t < 1200.0 {
+                    let (x, z) = (o[0] + t * dir[0], o[2] + t * dir[2]);
+                    for (i, j) in [(x.floor(), z.floor()), (x.floor() - 1.0, z.floor()), (x.floor(), z.floor() - 1.0)] {
+                        if i < 0.0 || j < 0.0 || i >= (w - 1) as f64 || j >= (d - 1) as f64 {
+                            continue;
+                        }
+                        for corners in f.triangles(i as usize, j as usize) {
+                            let v = corners.map(|(i, j)| [i as f64, y(i, j), j as f64]);
+                            all.extend(triangle(&o, &dir, &v));
+                        }
+                    }
+                    t += 0.25;
+                }
+                let want = all.into_iter().reduce(f64::min);
+                match (hit(&f, &r), want) {
+                    (Some(h), Some(t)) => {
+                        hits += 1;
+                        assert!((f64::from(&h.t) - t).abs() < t / 256.0, "pixel {} {}: {} vs {}", px, py, f64::from(&h.t), t);
+                    }
+                    (None, None) => {}
+                    (got, want) => panic!("pixel {} {}: {:?} vs {:?}", px, py, got.map(|h| h.t), want),
+                }
+            }
+        }
+        assert!(hits > 300, "{} hits", hits);
+    }
+
+    fn triangle(o: &[f64; 3], d: &[f64; 3], v: &[[f64; 3]; 3]) -> Option<f64> {
+        let sub = |a: &[f64; 3], b: &[f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+        let cross = |a: &[f64; 3], b: &[f64; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+        let dot = |a: &[f64; 3], b: &[f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let (e1, e2) = (sub(&v[1], &v[0]), sub(&v[2], &v[0]));
+        let p = cross(d, &e2);
+        let det = dot(&e1, &p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let s = sub(o, &v[0]);
+        let u = dot(&s, &p) / det;
+        let q = cross(&s, &e1);
+        let v = dot(d, &q) / det;
+        let t = dot(&e2, &q) / det;
+        (u >= 0.0 && v >= 0.0 && u + v <= 1.0 && t > 0.0).then_some(t)
+    }
+}
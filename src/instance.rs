@@ -0,0 +1,216 @@
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::geometry::Point3;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::{Shape, Span};
+use crate::transform::Transform;
+
+// a shape placed by a transform from its own space into the scene. the
+// shape is shared, so one mesh can stand in many places for the cost of a
+// transform each; an Instance can hold another Instance.
+pub struct Instance {
+    pub shape: Rc<dyn Shape>,
+    pub transform: Transform,
+}
+
+impl Instance {
+    pub fn new(shape: Rc<dyn Shape>, transform: Transform) -> Self {
+        Self { shape, transform }
+    }
+}
+
+impl Instance {
+    // the ray in object space. the direction is carried over unnormalized,
+    // so t means the same on both sides and t_min, t_max pass straight
+    // through.
+    fn local(&self, ray: &Ray) -> Ray {
+        let (mut origin, mut direction) = (ray.origin.clone(), ray.direction.clone());
+        let mut inv = self.transform.clone();
+        inv.inverse();
+        origin.transform(&inv);
+        direction.transform(&inv);
+        Ray::new(origin, direction)
+    }
+
+    // back to world space: the point from the world ray, the normal by the
+    // inverse transpose. that keeps n . d, so the normal still faces against
+    // the ray and front_face carries over, mirror transforms included.
+    fn world(&self, ray: &Ray, hit: Hit) -> Hit {
+        let mut hit = hit;
+        hit.point = ray.at(&hit.t);
+        hit.normal.transform(&self.transform);
+        hit.normal.normalize();
+        hit
+    }
+}
+
+impl Shape for Instance {
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let hit = self.shape.hit(&self.local(ray), t_min, t_max)?;
+        Some(self.world(ray, hit))
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> bool {
+        self.shape.occluded(&self.local(ray), t_min, t_max)
+    }
+
+    // the eight corners of the shape's box, carried into world space.
+    fn bounds(&self) -> Option<Aabb> {
+        let local = self.shape.bounds()?;
+        let (lo, hi) = (local.min.vec3(), local.max.vec3());
+        let mut bounds = Aabb::empty();
+        for corner in 0..8 {
+            let pick = |bit: usize, lo: &Number, hi: &Number| if corner >> bit & 1 == 1 { hi.clone() } else { lo.clone() };
+            let mut p = Point3::new(pick(0, &lo.x, &hi.x), pick(1, &lo.y, &hi.y), pick(2, &lo.z, &hi.z));
+            p.transform(&self.transform);
+            bounds.expand(&p);
+        }
+        Some(bounds)
+    }
+
+    fn spans(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Vec<Span> {
+        let spans = self.shape.spans(&self.local(ray), t_min, t_max);
+        spans
+            .into_iter()
+            .map(|s| Span { enter: s.enter.map(|h| self.world(ray, h)), exit: s.exit.map(|h| self.world(ray, h)) })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::csg::Csg;
+    use crate::cuboid::Cuboid;
+    use crate::geometry::{Dir3, Normal3, Point3};
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    fn unit_sphere() -> Rc<dyn Shape> {
+        Rc::new(Sphere::new(Point3::origin(), Number::ONE, 4))
+    }
+
+    fn hit(s: &dyn Shape, r: &Ray) -> Option<Hit> {
+        s.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    #[test]
+    fn translated_and_shared() {
+        let shape = unit_sphere();
+        let a = Instance::new(shape.clone(), Transform::translate(&Vec3::from([0.0, 0.0, -5.0])));
+        let b = Instance::new(shape.clone(), Transform::translate(&Vec3::from([3.0, 0.0, -5.0])));
+        assert_eq!(Rc::strong_count(&shape), 3);
+
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, -1.0]));
+        let h = hit(&a, &r).unwrap();
+        assert_eq!(h.t, Number::from(4));
+        assert_eq!(h.point, Point3::from([0.0, 0.0, -4.0]));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 4);
+        assert!(hit(&b, &r).is_none());
+
+        let r = Ray::new(Point3::from([3.0, 0.0, 0.0]), Dir3::from([0.0, 0.0, -1.0]));
+        assert_eq!(hit(&b, &r).unwrap().point, Point3::from([3.0, 0.0, -4.0]));
+    }
+
+    #[test]
+    fn scaled_and_rotated() {
+        // an ellipsoid with semi-axes 2, 1, 1: t is the same in both spaces
+        // even though the local direction isn't unit length
+        let e = Instance::new(unit_sphere(), Transform::scale(&Vec3::from([2.0, 1.0, 1.0])));
+        let r = Ray::new(Point3::from([-5.0, 0.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let h = hit(&e, &r).unwrap();
+        assert_eq!(h.t, Number::from(3));
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+
+        // off-axis, the normal is the gradient x / 4, y, z, not the radial
+        // direction
+        let r = Ray::new(Point3::from([1.0, 5.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = hit(&e, &r).unwrap();
+        let y = 0.75f64.sqrt();
+        assert!((f64::from(&h.t) - (5.0 - y)).abs() < 1.0 / 4096.0);
+        let (gx, gy) = (0.25, y);
+        let l = (gx * gx + gy * gy).sqrt();
+        let n = <[f64; 3]>::from(&h.normal);
+        assert!((n[0] - gx / l).abs() < 1.0 / 4096.0 && (n[1] - gy / l).abs() < 1.0 / 4096.0 && n[2] == 0.0, "{:?}", n);
+
+        // a unit cube turned 90 degrees about y: its +x face now faces -z
+        let cube: Rc<dyn Shape> = Rc::new(Cuboid::new(&Point3::from([0.0, -0.5, -0.5]), &Point3::from([1.0, 0.5, 0.5]), 0));
+        let quarter = Number::from(std::f64::consts::FRAC_PI_2);
+        let turned = Instance::new(cube, Transform::rotate_y(&quarter));
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = hit(&turned, &r).unwrap();
+        assert!((f64::from(&h.t) - 4.0).abs() < 1.0 / 4096.0, "{:?}", h.t);
+        let n = <[f64; 3]>::from(&h.normal);
+        assert!((n[2] + 1.0).abs() < 1.0 / 4096.0, "{:?}", n);
+    }
+
+    #[test]
+    fn mirrored() {
+        // a reflection turns the sphere inside out as a transform, but its
+        // outside still faces out
+        let m = Instance::new(unit_sphere(), Transform::scale(&Vec3::from([-1.0, 1.0, 1.0])));
+        let r = Ray::new(Point3::from([-5.0, 0.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let h = hit(&m, &r).unwrap();
+        assert_eq!(h.t, Number::from(4));
+        assert!(h.front_face);
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn nested() {
+        // an instance of an instance matches one instance with the product
+        let (mut outer, inner) = (Transform::rotate_z(&Number::from(0.5)), Transform::translate(&Vec3::from([2.0, 0.0, 0.0])));
+        let nested = Instance::new(Rc::new(Instance::new(unit_sphere(), inner.clone())), outer.clone());
+        outer.mul(&inner);
+        let flat = Instance::new(unit_sphere(), outer);
+        let center = <[f64; 3]>::from(&flat.transform.transform_point(&Vec3::from([0.0, 0.0, 0.0])));
+
+        // aimed near the sphere, wherever the transforms put it
+        let mut rng = StdRng::seed_from_u64(46);
+        let mut hits = 0;
+        for _ in 0..200 {
+            let o = [0, 1, 2].map(|_| rng.gen_range(-5.0..5.0));
+            let at = [0, 1, 2].map(|i| center[i] + rng.gen_range(-1.5..1.5));
+            let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+            match (hit(&nested, &r), hit(&flat, &r)) {
+                (Some(a), Some(b)) => {
+                    hits += 1;
+                    assert!((f64::from(&a.t) - f64::from(&b.t)).abs() < 1.0 / 1024.0, "{:?} vs {:?}", a.t, b.t);
+                    assert_eq!(a.front_face, b.front_face);
+                }
+                (None, None) => {}
+                // only on a graze
+                (a, b) => {
+                    let t = a.or(b).unwrap();
+                    let n = <[f64; 3]>::from(&t.normal);
+                    let d = <[f64; 3]>::from(&r.direction);
+                    let cos = (n[0] * d[0] + n[1] * d[1] + n[2] * d[2]) / (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                    assert!(cos.abs() < 1.0 / 64.0, "disagree away from a graze: {:?}", t);
+                }
+            }
+        }
+        assert!(hits > 50, "{} hits", hits);
+    }
+
+    #[test]
+    fn in_csg() {
+        // two instances of one sphere, intersected into a lens
+        let shape = unit_sphere();
+        let left = Instance::new(shape.clone(), Transform::translate(&Vec3::from([-0.5, 0.0, 0.0])));
+        let right = Instance::new(shape, Transform::translate(&Vec3::from([0.5, 0.0, 0.0])));
+        let lens = Csg::intersection(Box::new(left), Box::new(right));
+        let r = Ray::new(Point3::from([-5.0, 0.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let spans = lens.spans(&r, &Number::ZERO, &Number::MAX);
+        assert_eq!(spans.len(), 1);
+        let (enter, exit) = (spans[0].enter.clone().unwrap(), spans[0].exit.clone().unwrap());
+        assert_eq!((enter.t, exit.t), (Number::from(4.5), Number::from(5.5)));
+        assert_eq!(enter.point, Point3::from([-0.5, 0.0, 0.0]));
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq)]
+use crate::error::{policy, raise, ArithError, Policy};
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Int32 {
     pub parts: [i16; 4],
 }
@@ -165,12 +167,46 @@ impl Int32 {
     }
 }
 
+impl Int32 {
+    fn saturated(negative: bool) -> Self {
+        Self::from(if negative { i32::MIN } else { i32::MAX })
+    }
+}
+
 impl Int32 {
     pub fn div(&mut self, other: &Self) {
         if other.parts == [0; 4] {
-            panic!("divide by zero");
+            raise(ArithError::DivideByZero);
+            if self.parts != [0; 4] {
+                *self = Self::saturated(self.parts[3] >= 0x80);
+            }
+            return;
         }
 
+        let negative = (self.parts[3] >= 0x80) ^ (other.parts[3] >= 0x80);
+        if self.div_wrapping(other) && policy() != Policy::Panic {
+            raise(ArithError::Overflow);
+            *self = Self::saturated(negative);
+        }
+    }
+
+    pub fn try_div(&mut self, other: &Self) -> Result<(), ArithError> {
+        if other.parts == [0; 4] {
+            return Err(ArithError::DivideByZero);
+        }
+
+        let mut q = Self { parts: self.parts };
+        if q.div_wrapping(other) {
+            return Err(ArithError::Overflow);
+        }
+
+        *self = q;
+        Ok(())
+    }
+
+    // divides by a non-zero divisor, wrapping on overflow (i32::MIN / -1).
+    // returns whether it overflowed.
+    fn div_wrapping(&mut self, other: &Self) -> bool {
         let negative = (self.parts[3] >= 0x80) ^ (other.parts[3] >= 0x80);
 
         if self.parts[3] >= 0x80 {
@@ -257,9 +293,12 @@ impl Int32 {
             q[6] + q[7] * 16,
         ];
 
+        let overflow = !negative && self.parts[3] >= 0x80;
         if negative {
             self.neg();
         }
+
+        overflow
     }
 }
 
@@ -311,10 +350,28 @@ fn nlz(n: i16) -> i16 {
 }
 
 pub fn div_u4(u: [i16; 16], v: [i16; 8]) -> [i16; 16] {
+    match try_div_u4(u, v) {
+        Ok(q) => q,
+        Err(err) => {
+            raise(err);
+            if err == ArithError::DivideByZero { [0x0F; 16] } else { [0; 16] }
+        }
+    }
+}
+
+pub fn try_div_u4(u: [i16; 16], v: [i16; 8]) -> Result<[i16; 16], ArithError> {
     if v == [0; 8] {
-        panic!()
+        return Err(ArithError::DivideByZero);
     }
 
+    if u.iter().chain(v.iter()).any(|d| !(0..16).contains(d)) {
+        return Err(ArithError::OutOfRange);
+    }
+
+    Ok(div_u4_unchecked(u, v))
+}
+
+fn div_u4_unchecked(u: [i16; 16], v: [i16; 8]) -> [i16; 16] {
     let base = 16;
     let mut n = 8;
     for i in v.iter().rev() {
@@ -392,27 +449,99 @@ pub fn div_u4(u: [i16; 16], v: [i16; 8]) -> [i16; 16] {
     q
 }
 
+impl Int32 {
+    pub fn shr(&mut self, sft: i16) {
+        for _ in 0..sft {
+            self.parts[0] = self.parts[0] / 2 + (self.parts[1] & 1) * 0x80;
+            self.parts[1] = self.parts[1] / 2 + (self.parts[2] & 1) * 0x80;
+            self.parts[2] = self.parts[2] / 2 + (self.parts[3] & 1) * 0x80;
+            self.parts[3] /= 2;
+        }
+    }
+
+    // arithmetic shift right: rounds towards -inf, keeping the sign.
+    pub fn sar(&mut self, sft: i16) {
+        let fill = if self.parts[3] >= 0x80 { 0x80 } else { 0 };
+        for _ in 0..sft {
+            self.shr(1);
+            self.parts[3] += fill;
+        }
+    }
+
+    pub fn shl(&mut self, sft: i16) {
+        for _ in 0..sft {
+            self.parts[3] = (self.parts[3] * 2 + self.parts[2] / 0x80) & 0xFF;
+            self.parts[2] = (self.parts[2] * 2 + self.parts[1] / 0x80) & 0xFF;
+            self.parts[1] = (self.parts[1] * 2 + self.parts[0] / 0x80) & 0xFF;
+            self.parts[0] = (self.parts[0] * 2) & 0xFF;
+        }
+    }
+
+    pub fn xor(&mut self, other: &Self) {
+        for i in 0..4 {
+            self.parts[i] = (self.parts[i] | other.parts[i]) & !(self.parts[i] & other.parts[i]);
+        }
+    }
+
+    pub fn lt(&self, other: &Self) -> bool {
+        let (neg, other_neg) = (self.parts[3] >= 0x80, other.parts[3] >= 0x80);
+        if neg != other_neg {
+            return neg;
+        }
+        self.lt_unsigned(other)
+    }
+
+    pub fn lt_unsigned(&self, other: &Self) -> bool {
+        for i in (0..4).rev() {
+            if self.parts[i] != other.parts[i] {
+                return self.parts[i] < other.parts[i];
+            }
+        }
+        false
+    }
+}
+
 impl Int32 {
     pub fn sqrt(&mut self) {
+        self.sqrt_rem();
+    }
+
+    pub fn try_sqrt(&mut self) -> Result<(), ArithError> {
         if self.parts[3] >= 0x80 {
-            panic!()
+            return Err(ArithError::NegativeSqrt);
         }
 
-        if self.parts == [0, 0, 0, 0] {
+        self.sqrt_rem();
+        Ok(())
+    }
+
+    // digit-by-digit square root: 16 rounds of compare, subtract and shift,
+    // leaving floor(sqrt(n)) in self and returning n - floor(sqrt(n))^2.
+    pub fn sqrt_rem(&mut self) -> Self {
+        if self.parts[3] >= 0x80 {
+            raise(ArithError::NegativeSqrt);
             *self = Self::from(0);
-            return;
+            return Self::from(0);
         }
 
-        let mut guess = Self::from(5);
-        for _ in 0..20 {
-            let mut inv = Self { parts: self.parts };
-            inv.div(&guess);
+        let mut rem = Self { parts: self.parts };
+        let mut root = Self::from(0);
+        let mut bit = Self::from(0x40000000);
+        for _ in 0..16 {
+            let mut trial = Self { parts: root.parts };
+            trial.add(&bit);
+
+            root.shr(1);
+            if !rem.lt_unsigned(&trial) {
+                rem.sub(&trial);
+                root.add(&bit);
+            }
 
-            guess.add(&inv);
-            guess.div(&Self::from(2));
+            bit.shr(2);
         }
 
-        *self = guess;
+        *self = root;
+        rem
     }
 }
 
@@ -420,6 +549,8 @@ impl Int32 {
 mod tests {
     use rand::Rng;
 
+    use crate::error::{set_policy, take_error};
+
     use super::*;
 
     #[test]
@@ -725,4 +856,245 @@ mod tests {
             assert_eq!(n, ((a as f64).sqrt() as i32).into(), "sqrt: {}", a);
         }
     }
+
+    #[test]
+    fn shr() {
+        let mut n = Int32::from(0x40000000);
+        n.shr(2);
+        assert_eq!(n, 0x10000000.into());
+
+        let mut n = Int32::from(-1);
+        n.shr(1);
+        assert_eq!(n, i32::MAX.into());
+
+        for _ in 0..100 {
+            let (a, s) = (rand::random::<i32>(), rand::thread_rng().gen_range(0..32));
+            let mut n = Int32::from(a);
+            n.shr(s);
+            assert_eq!(n, ((a as u32 >> s) as i32).into(), "shift right: {} >> {}", a, s);
+        }
+    }
+
+    #[test]
+    fn sar() {
+        let mut n = Int32::from(-1);
+        n.sar(5);
+        assert_eq!(n, (-1).into());
+
+        for _ in 0..100 {
+            let (a, s) = (rand::random::<i32>(), rand::thread_rng().gen_range(0..32));
+            let mut n = Int32::from(a);
+            n.sar(s);
+            assert_eq!(n, (a >> s).into(), "shift right: {} >> {}", a, s);
+        }
+    }
+
+    #[test]
+    fn shl() {
+        let mut n = Int32::from(1);
+        n.shl(31);
+        assert_eq!(n, i32::MIN.into());
+
+        for _ in 0..100 {
+            let (a, s) = (rand::random::<i32>(), rand::thread_rng().gen_range(0..32));
+            let mut n = Int32::from(a);
+            n.shl(s);
+            assert_eq!(n, (a << s).into(), "shift left: {} << {}", a, s);
+        }
+    }
+
+    #[test]
+    fn xor() {
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i32>(), rand::random::<i32>());
+            let mut n = Int32::from(a);
+            n.xor(&Int32::from(b));
+            assert_eq!(n, (a ^ b).into(), "xor: {} ^ {}", a, b);
+        }
+    }
+
+    #[test]
+    fn lt() {
+        assert!(Int32::from(-1).lt(&Int32::from(0)));
+        assert!(!Int32::from(0).lt(&Int32::from(-1)));
+        assert!(!Int32::from(3).lt(&Int32::from(3)));
+        assert!(Int32::from(0).lt_unsigned(&Int32::from(-1)));
+
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i32>(), rand::random::<i32>());
+            assert_eq!(Int32::from(a).lt(&Int32::from(b)), a < b, "{} < {}", a, b);
+            assert_eq!(Int32::from(a).lt_unsigned(&Int32::from(b)), (a as u32) < (b as u32), "{} < {}", a, b);
+        }
+    }
+
+    #[test]
+    fn sqrt_rem() {
+        let mut n = Int32::from(0);
+        assert_eq!(n.sqrt_rem(), 0.into());
+        assert_eq!(n, 0.into());
+
+        let mut n = Int32::from(i32::MAX);
+        assert_eq!(n.sqrt_rem(), (i32::MAX - 46340 * 46340).into());
+        assert_eq!(n, 46340.into());
+
+        for k in [1, 2, 3, 255, 256, 4095, 4096, 46339, 46340] {
+            for a in [k * k - 1, k * k, k * k + 1] {
+                let mut n = Int32::from(a);
+                let rem = n.sqrt_rem();
+                let want = if a < k * k { k - 1 } else { k };
+                assert_eq!(n, want.into(), "sqrt: {}", a);
+                assert_eq!(rem, (a - want * want).into(), "sqrt remainder: {}", a);
+            }
+        }
+
+        for _ in 0..100 {
+            let a = rand::random::<i32>() & 0x7FFFFFFF;
+            let mut n = Int32::from(a);
+            let rem = n.sqrt_rem();
+            let r = i32::from(n) as i64;
+            assert!(r * r <= a as i64 && (r + 1) * (r + 1) > a as i64, "sqrt: {}", a);
+            assert_eq!(rem, (a - (r * r) as i32).into(), "sqrt remainder: {}", a);
+        }
+    }
+
+    #[test]
+    fn try_div() {
+        let mut n = Int32::from(330519);
+        assert_eq!(n.try_div(&Int32::from(781)), Ok(()));
+        assert_eq!(n, 423.into());
+
+        let mut n = Int32::from(330519);
+        assert_eq!(n.try_div(&Int32::from(0)), Err(ArithError::DivideByZero));
+        assert_eq!(n, 330519.into());
+
+        let mut n = Int32::from(i32::MIN);
+        assert_eq!(n.try_div(&Int32::from(-1)), Err(ArithError::Overflow));
+        assert_eq!(n, i32::MIN.into());
+
+        let mut n = Int32::from(i32::MIN);
+        assert_eq!(n.try_div(&Int32::from(1)), Ok(()));
+        assert_eq!(n, i32::MIN.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn div_by_zero_panics() {
+        let mut n = Int32::from(1);
+        n.div(&Int32::from(0));
+    }
+
+    #[test]
+    fn div_overflow_wraps_under_panic() {
+        let mut n = Int32::from(i32::MIN);
+        n.div(&Int32::from(-1));
+        assert_eq!(n, i32::MIN.into());
+    }
+
+    #[test]
+    fn div_saturates() {
+        set_policy(Policy::Saturate);
+
+        let mut n = Int32::from(5);
+        n.div(&Int32::from(0));
+        assert_eq!(n, i32::MAX.into());
+
+        let mut n = Int32::from(-5);
+        n.div(&Int32::from(0));
+        assert_eq!(n, i32::MIN.into());
+
+        let mut n = Int32::from(0);
+        n.div(&Int32::from(0));
+        assert_eq!(n, 0.into());
+
+        let mut n = Int32::from(i32::MIN);
+        n.div(&Int32::from(-1));
+        assert_eq!(n, i32::MAX.into());
+
+        assert_eq!(take_error(), None);
+    }
+
+    #[test]
+    fn div_records() {
+        set_policy(Policy::Record);
+
+        let mut n = Int32::from(5);
+        n.div(&Int32::from(0));
+        assert_eq!(n, i32::MAX.into());
+        assert_eq!(take_error(), Some(ArithError::DivideByZero));
+
+        let mut n = Int32::from(i32::MIN);
+        n.div(&Int32::from(-1));
+        assert_eq!(n, i32::MAX.into());
+        assert_eq!(take_error(), Some(ArithError::Overflow));
+
+        let mut n = Int32::from(6);
+        n.div(&Int32::from(3));
+        assert_eq!(n, 2.into());
+        assert_eq!(take_error(), None);
+    }
+
+    #[test]
+    fn try_div_u4() {
+        assert_eq!(
+            super::try_div_u4([6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], [3, 0, 0, 0, 0, 0, 0, 0]),
+            Ok([2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            super::try_div_u4([6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], [0; 8]),
+            Err(ArithError::DivideByZero)
+        );
+        assert_eq!(
+            super::try_div_u4([16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], [3, 0, 0, 0, 0, 0, 0, 0]),
+            Err(ArithError::OutOfRange)
+        );
+        assert_eq!(
+            super::try_div_u4([6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], [-1, 0, 0, 0, 0, 0, 0, 0]),
+            Err(ArithError::OutOfRange)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn div_u4_by_zero_panics() {
+        div_u4([1; 16], [0; 8]);
+    }
+
+    #[test]
+    fn div_u4_records() {
+        set_policy(Policy::Record);
+
+        assert_eq!(div_u4([1; 16], [0; 8]), [0x0F; 16]);
+        assert_eq!(take_error(), Some(ArithError::DivideByZero));
+
+        assert_eq!(div_u4([0x10; 16], [1; 8]), [0; 16]);
+        assert_eq!(take_error(), Some(ArithError::OutOfRange));
+    }
+
+    #[test]
+    fn try_sqrt() {
+        let mut n = Int32::from(17);
+        assert_eq!(n.try_sqrt(), Ok(()));
+        assert_eq!(n, 4.into());
+
+        let mut n = Int32::from(-17);
+        assert_eq!(n.try_sqrt(), Err(ArithError::NegativeSqrt));
+        assert_eq!(n, (-17).into());
+    }
+
+    #[test]
+    #[should_panic(expected = "square root of negative number")]
+    fn sqrt_negative_panics() {
+        let mut n = Int32::from(-1);
+        n.sqrt();
+    }
+
+    #[test]
+    fn sqrt_negative_records() {
+        set_policy(Policy::Record);
+
+        let mut n = Int32::from(-1);
+        assert_eq!(n.sqrt_rem(), 0.into());
+        assert_eq!(n, 0.into());
+        assert_eq!(take_error(), Some(ArithError::NegativeSqrt));
+    }
 }
@@ -0,0 +1,259 @@
+use crate::int32::Int32;
+
+// Wide register for accumulating full Int32 products before rounding.
+// Same layout as Int32: little-endian bytes held in i16s.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Int64 {
+    pub parts: [i16; 8],
+}
+
+impl From<&Int32> for Int64 {
+    fn from(n: &Int32) -> Self {
+        let ext = if n.parts[3] >= 0x80 { 0xFF } else { 0 };
+        Int64 {
+            parts: [n.parts[0], n.parts[1], n.parts[2], n.parts[3], ext, ext, ext, ext],
+        }
+    }
+}
+
+impl From<i64> for Int64 {
+    fn from(n: i64) -> Self {
+        let mut parts = [0; 8];
+        for (i, p) in parts.iter_mut().enumerate() {
+            *p = ((n >> (i * 8)) & 0xFF) as i16;
+        }
+        Int64 { parts }
+    }
+}
+
+impl From<Int64> for i64 {
+    fn from(n: Int64) -> Self {
+        n.parts.iter().rev().fold(0, |acc, p| acc << 8 | *p as i64)
+    }
+}
+
+impl Int64 {
+    pub fn neg(&mut self) {
+        for i in 0..8 {
+            self.parts[i] = !self.parts[i] & 0xFF;
+        }
+        self.add(&Int64 { parts: [1, 0, 0, 0, 0, 0, 0, 0] });
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        let mut carry = 0;
+        for i in 0..8 {
+            self.parts[i] += other.parts[i] + carry;
+            carry = 0;
+            if self.parts[i] > 0xFF {
+                self.parts[i] -= 0x100;
+                carry = 1;
+            }
+        }
+    }
+}
+
+impl Int64 {
+    pub fn sub(&mut self, other: &Self) {
+        let mut other = other.clone();
+        other.neg();
+        self.add(&other);
+    }
+
+    pub fn shl(&mut self, sft: i16) {
+        for _ in 0..sft {
+            for i in (1..8).rev() {
+                self.parts[i] = (self.parts[i] * 2 + self.parts[i - 1] / 0x80) & 0xFF;
+            }
+            self.parts[0] = (self.parts[0] * 2) & 0xFF;
+        }
+    }
+
+    pub fn shr(&mut self, sft: i16) {
+        for _ in 0..sft {
+            for i in 0..7 {
+                self.parts[i] = self.parts[i] / 2 + (self.parts[i + 1] & 1) * 0x80;
+            }
+            self.parts[7] /= 2;
+        }
+    }
+
+    pub fn lt_unsigned(&self, other: &Self) -> bool {
+        for i in (0..8).rev() {
+            if self.parts[i] != other.parts[i] {
+                return self.parts[i] < other.parts[i];
+            }
+        }
+        false
+    }
+}
+
+impl Int64 {
+    // digit-by-digit square root of the value read as unsigned, like
+    // Int32::sqrt_rem.
+    pub fn sqrt_rem(&mut self) -> Self {
+        let mut rem = self.clone();
+        let mut root = Self { parts: [0; 8] };
+        let mut bit = Self { parts: [0, 0, 0, 0, 0, 0, 0, 0x40] };
+        for _ in 0..32 {
+            let mut trial = root.clone();
+            trial.add(&bit);
+
+            root.shr(1);
+            if !rem.lt_unsigned(&trial) {
+                rem.sub(&trial);
+                root.add(&bit);
+            }
+
+            bit.shr(2);
+        }
+
+        *self = root;
+        rem
+    }
+}
+
+impl Int64 {
+    // the exact signed product of two Int32s.
+    pub fn product(a: &Int32, b: &Int32) -> Self {
+        let negative = (a.parts[3] >= 0x80) ^ (b.parts[3] >= 0x80);
+
+        let mut a = a.clone();
+        if a.parts[3] >= 0x80 {
+            a.neg();
+        }
+        let mut b = b.clone();
+        if b.parts[3] >= 0x80 {
+            b.neg();
+        }
+
+        let mut a_chunked = [0; 8];
+        let mut b_chunked = [0; 8];
+        for i in 0..4 {
+            a_chunked[2 * i] = a.parts[i] & 0x0F;
+            a_chunked[2 * i + 1] = a.parts[i] / 16;
+            b_chunked[2 * i] = b.parts[i] & 0x0F;
+            b_chunked[2 * i + 1] = b.parts[i] / 16;
+        }
+
+        let mut mul = [0; 16];
+        for (i, lhs) in a_chunked.iter().enumerate() {
+            for (j, rhs) in b_chunked.iter().enumerate() {
+                mul[i + j] += rhs * lhs;
+            }
+        }
+
+        let mut r = Self { parts: [0; 8] };
+        let mut carry = 0;
+        for i in 0..8 {
+            let byte = carry + mul[2 * i] + mul[2 * i + 1] * 16;
+            r.parts[i] = byte & 0xFF;
+            carry = byte / 256;
+        }
+
+        if negative {
+            r.neg();
+        }
+
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from() {
+        assert_eq!(Int64::from(&Int32::from(-1)), Int64::from(-1));
+        assert_eq!(Int64::from(&Int32::from(i32::MIN)), Int64::from(i32::MIN as i64));
+        assert_eq!(i64::from(Int64::from(i64::MIN)), i64::MIN);
+    }
+
+    #[test]
+    fn neg() {
+        let mut n = Int64::from(0);
+        n.neg();
+        assert_eq!(n, Int64::from(0));
+
+        for _ in 0..100 {
+            let a = rand::random::<i64>();
+            let mut n = Int64::from(a);
+            n.neg();
+            assert_eq!(n, Int64::from(a.wrapping_neg()), "negate: -{}", a);
+        }
+    }
+
+    #[test]
+    fn add() {
+        let mut n = Int64::from(0xFFFFFFFF);
+        n.add(&Int64::from(1));
+        assert_eq!(n, Int64::from(0x100000000));
+
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i64>(), rand::random::<i64>());
+            let mut n = Int64::from(a);
+            n.add(&Int64::from(b));
+            assert_eq!(n, Int64::from(a.wrapping_add(b)), "add: {} + {}", a, b);
+        }
+    }
+
+    #[test]
+    fn product() {
+        let m = Int32::from(i32::MIN);
+        assert_eq!(Int64::product(&m, &m), Int64::from(1 << 62));
+        assert_eq!(Int64::product(&m, &Int32::from(i32::MAX)), Int64::from(i32::MIN as i64 * i32::MAX as i64));
+
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i32>(), rand::random::<i32>());
+            assert_eq!(
+                Int64::product(&Int32::from(a), &Int32::from(b)),
+                Int64::from(a as i64 * b as i64),
+                "multiply: {} * {}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn shifts() {
+        for _ in 0..100 {
+            let (a, s) = (rand::random::<i64>(), rand::random::<u8>() % 64);
+            let mut n = Int64::from(a);
+            n.shl(s as i16);
+            assert_eq!(n, Int64::from(a << s), "shift left: {} << {}", a, s);
+
+            let mut n = Int64::from(a);
+            n.shr(s as i16);
+            assert_eq!(n, Int64::from((a as u64 >> s) as i64), "shift right: {} >> {}", a, s);
+        }
+    }
+
+    #[test]
+    fn sub() {
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i64>(), rand::random::<i64>());
+            let mut n = Int64::from(a);
+            n.sub(&Int64::from(b));
+            assert_eq!(n, Int64::from(a.wrapping_sub(b)), "subtract: {} - {}", a, b);
+            assert_eq!(Int64::from(a).lt_unsigned(&Int64::from(b)), (a as u64) < (b as u64));
+        }
+    }
+
+    #[test]
+    fn sqrt_rem() {
+        let mut n = Int64::from(-1);
+        assert_eq!(n.sqrt_rem(), Int64::from((u64::MAX - 0xFFFFFFFF * 0xFFFFFFFF) as i64));
+        assert_eq!(n, Int64::from(0xFFFFFFFF));
+
+        for _ in 0..100 {
+            let a = rand::random::<u64>() >> (rand::random::<u8>() % 64);
+            let mut n = Int64::from(a as i64);
+            let rem = n.sqrt_rem();
+            let r = i64::from(n) as u128;
+            assert!(r * r <= a as u128 && (r + 1) * (r + 1) > a as u128, "sqrt: {}", a);
+            assert_eq!(rem, Int64::from((a as u128 - r * r) as i64), "sqrt remainder: {}", a);
+        }
+    }
+}
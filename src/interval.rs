@@ -0,0 +1,309 @@
+use crate::error::{raise, ArithError};
+use crate::number::Number;
+
+// A closed range [lo, hi] that always contains the exact real result of the
+// operations applied to it. Bounds that would leave the 16.16 range stick to
+// Number::MIN / Number::MAX, which is what may_overflow looks for.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Interval {
+    pub lo: Number,
+    pub hi: Number,
+}
+
+impl Interval {
+    pub fn new(lo: &Number, hi: &Number) -> Self {
+        if hi.lt(lo) {
+            Self { lo: hi.clone(), hi: lo.clone() }
+        } else {
+            Self { lo: lo.clone(), hi: hi.clone() }
+        }
+    }
+
+    pub fn point(n: &Number) -> Self {
+        Self { lo: n.clone(), hi: n.clone() }
+    }
+
+    pub fn everything() -> Self {
+        Self { lo: Number::MIN, hi: Number::MAX }
+    }
+}
+
+impl Interval {
+    pub fn contains(&self, n: &Number) -> bool {
+        !n.lt(&self.lo) && !self.hi.lt(n)
+    }
+
+    pub fn contains_interval(&self, other: &Self) -> bool {
+        !other.lo.lt(&self.lo) && !self.hi.lt(&other.hi)
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !other.hi.lt(&self.lo) && !self.hi.lt(&other.lo)
+    }
+
+    // narrows self to the common part. returns false, leaving self untouched,
+    // if there is none.
+    pub fn intersect(&mut self, other: &Self) -> bool {
+        if !self.overlaps(other) {
+            return false;
+        }
+
+        if self.lo.lt(&other.lo) {
+            self.lo = other.lo.clone();
+        }
+        if other.hi.lt(&self.hi) {
+            self.hi = other.hi.clone();
+        }
+        true
+    }
+
+    pub fn may_overflow(&self) -> bool {
+        self.lo == Number::MIN || self.hi == Number::MAX
+    }
+}
+
+impl Interval {
+    pub fn add(&mut self, other: &Self) {
+        self.lo = self.lo.saturating_add(&other.lo);
+        self.hi = self.hi.saturating_add(&other.hi);
+    }
+
+    pub fn sub(&mut self, other: &Self) {
+        self.lo = self.lo.saturating_sub(&other.hi);
+        self.hi = self.hi.saturating_sub(&other.lo);
+    }
+
+    pub fn neg(&mut self) {
+        let lo = self.lo.clone();
+        self.lo = self.hi.saturating_neg();
+        self.hi = lo.saturating_neg();
+    }
+
+    // mul rounds towards -inf, so the exact product of two bounds lies in
+    // [p, p + ulp).
+    pub fn mul(&mut self, other: &Self) {
+        let products = [
+            self.lo.saturating_mul(&other.lo),
+            self.lo.saturating_mul(&other.hi),
+            self.hi.saturating_mul(&other.lo),
+            self.hi.saturating_mul(&other.hi),
+        ];
+
+        self.lo = lowest(&products);
+        self.hi = highest(&products);
+        widen_up(&mut self.hi);
+    }
+
+    pub fn square(&mut self) {
+        let (lo, hi) = (self.lo.saturating_mul(&self.lo), self.hi.saturating_mul(&self.hi));
+        let top = if lo.lt(&hi) { hi.clone() } else { lo.clone() };
+
+        self.lo = if self.contains(&Number::ZERO) {
+            Number::ZERO
+        } else if lo.lt(&hi) {
+            lo
+        } else {
+            hi
+        };
+        self.hi = top;
+        widen_up(&mut self.hi);
+    }
+
+    // div truncates towards zero, so the exact quotient lies within an ulp
+    // either side. a divisor that straddles zero gives everything.
+    pub fn div(&mut self, other: &Self) {
+        if other.contains(&Number::ZERO) {
+            *self = Self::everything();
+            return;
+        }
+
+        let quotients = [
+            self.lo.saturating_div(&other.lo),
+            self.lo.saturating_div(&other.hi),
+            self.hi.saturating_div(&other.lo),
+            self.hi.saturating_div(&other.hi),
+        ];
+
+        self.lo = lowest(&quotients);
+        self.hi = highest(&quotients);
+        widen_down(&mut self.lo);
+        widen_up(&mut self.hi);
+    }
+
+    // the negative part is dropped; an interval with no non-negative part is
+    // reported as NegativeSqrt and becomes [0, 0].
+    pub fn sqrt(&mut self) {
+        if self.hi.is_negative() {
+            raise(ArithError::NegativeSqrt);
+            *self = Self::point(&Number::ZERO);
+            return;
+        }
+
+        if self.lo.is_negative() {
+            self.lo = Number::ZERO;
+        }
+
+        self.lo.sqrt();
+        self.hi.sqrt();
+        widen_up(&mut self.hi);
+    }
+}
+
+fn widen_up(n: &mut Number) {
+    *n = n.saturating_add(&Number::ULP);
+}
+
+fn widen_down(n: &mut Number) {
+    *n = n.saturating_sub(&Number::ULP);
+}
+
+fn lowest(ns: &[Number]) -> Number {
+    ns.iter().cloned().reduce(Number::min).unwrap()
+}
+
+fn highest(ns: &[Number]) -> Number {
+    ns.iter().cloned().reduce(Number::max).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn random_interval(scale: f64) -> Interval {
+        let mut rng = rand::thread_rng();
+        Interval::new(
+            &Number::from(rng.gen_range(-scale..scale)),
+            &Number::from(rng.gen_range(-scale..scale)),
+        )
+    }
+
+    // a few points spread across the interval, including both bounds.
+    fn samples(i: &Interval) -> Vec<f64> {
+        let (lo, hi) = (f64::from(&i.lo), f64::from(&i.hi));
+        let mut points = vec![lo, hi];
+        for _ in 0..8 {
+            let t = rand::thread_rng().gen_range(0.0..1.0);
+            points.push(f64::from(&Number::from(lo + (hi - lo) * t)));
+        }
+        points
+    }
+
+    fn assert_encloses(i: &Interval, want: f64, what: &str) {
+        let (lo, hi) = (f64::from(&i.lo), f64::from(&i.hi));
+        assert!(lo <= want && want <= hi, "{}: {} not in [{}, {}]", what, want, lo, hi);
+    }
+
+    #[test]
+    fn new_orders_bounds() {
+        let i = Interval::new(&Number::from(3), &Number::from(-2));
+        assert_eq!(i.lo, Number::from(-2));
+        assert_eq!(i.hi, Number::from(3));
+    }
+
+    #[test]
+    fn queries() {
+        let i = Interval::new(&Number::from(-2), &Number::from(3));
+        assert!(i.contains(&Number::from(-2)));
+        assert!(i.contains(&Number::from(3)));
+        assert!(!i.contains(&Number::from(4)));
+        assert!(i.contains_interval(&Interval::new(&Number::ZERO, &Number::ONE)));
+        assert!(!i.contains_interval(&Interval::new(&Number::ZERO, &Number::from(5))));
+
+        let mut j = Interval::new(&Number::from(1), &Number::from(5));
+        assert!(i.overlaps(&j));
+        assert!(j.intersect(&i));
+        assert_eq!(j, Interval::new(&Number::from(1), &Number::from(3)));
+
+        let mut k = Interval::new(&Number::from(4), &Number::from(5));
+        assert!(!k.overlaps(&i));
+        assert!(!k.intersect(&i));
+        assert_eq!(k, Interval::new(&Number::from(4), &Number::from(5)));
+    }
+
+    #[test]
+    fn add_sub() {
+        for _ in 0..100 {
+            let (a, b) = (random_interval(1000.0), random_interval(1000.0));
+            let mut sum = a.clone();
+            sum.add(&b);
+            let mut diff = a.clone();
+            diff.sub(&b);
+            for x in samples(&a) {
+                for y in samples(&b) {
+                    assert_encloses(&sum, x + y, "add");
+                    assert_encloses(&diff, x - y, "sub");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mul_div() {
+        for _ in 0..100 {
+            let (a, b) = (random_interval(100.0), random_interval(100.0));
+            let mut prod = a.clone();
+            prod.mul(&b);
+            let mut quot = a.clone();
+            quot.div(&b);
+            for x in samples(&a) {
+                for y in samples(&b) {
+                    assert_encloses(&prod, x * y, "mul");
+                    if y != 0.0 && (x / y).abs() < 30000.0 {
+                        assert_encloses(&quot, x / y, "div");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_sqrt() {
+        for _ in 0..100 {
+            let a = random_interval(150.0);
+            let mut sq = a.clone();
+            sq.square();
+            let mut root = a.clone();
+            if !a.hi.is_negative() {
+                root.sqrt();
+            }
+            for x in samples(&a) {
+                assert_encloses(&sq, x * x, "square");
+                if x >= 0.0 {
+                    assert_encloses(&root, x.sqrt(), "sqrt");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_is_tighter_than_mul() {
+        let a = Interval::new(&Number::from(-2), &Number::from(3));
+        let mut sq = a.clone();
+        sq.square();
+        let mut prod = a.clone();
+        prod.mul(&a);
+        assert_eq!(sq.lo, Number::ZERO);
+        assert_eq!(prod.lo, Number::from(-6));
+    }
+
+    #[test]
+    fn overflow_saturates() {
+        let mut a = Interval::new(&Number::from(100), &Number::from(200));
+        assert!(!a.may_overflow());
+        a.mul(&Interval::new(&Number::from(100), &Number::from(200)));
+        assert!(a.may_overflow());
+        assert_eq!(a.hi, Number::MAX);
+        assert_eq!(a.lo, Number::from(10000));
+
+        let mut a = Interval::new(&Number::from(-30000), &Number::from(1));
+        a.sub(&Interval::new(&Number::from(5000), &Number::from(6000)));
+        assert_eq!(a.lo, Number::MIN);
+        assert_eq!(a.hi, Number::from(-4999));
+
+        let mut a = Interval::new(&Number::ONE, &Number::from(2));
+        a.div(&Interval::new(&Number::from(-1), &Number::ONE));
+        assert_eq!(a, Interval::everything());
+    }
+}
@@ -0,0 +1,32 @@
+pub mod aabb;
+pub mod bvh;
+pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod error;
+pub mod geometry;
+pub mod heightfield;
+pub mod instance;
+pub mod int32;
+pub mod int64;
+pub mod interval;
+pub mod mesh;
+pub mod number;
+pub mod obj;
+pub mod plane;
+pub mod poly;
+pub mod quat;
+pub mod random;
+pub mod ray;
+pub mod rect;
+pub mod sdf;
+pub mod shape;
+pub mod sphere;
+pub mod torus;
+pub mod transform;
+pub mod triangle;
+pub mod trig;
+pub mod vec3;
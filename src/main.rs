@@ -1,6 +1,3 @@
-mod int32;
-mod number;
-
 fn main() {
     println!("Hello, world!");
 }
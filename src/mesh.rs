@@ -0,0 +1,159 @@
+use crate::aabb::Aabb;
+use crate::bvh::{Bvh, Split};
+use crate::geometry::{Normal3, Point3};
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+use crate::triangle::Triangle;
+
+// a triangle by index into the mesh's buffers.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Face {
+    pub vertices: [usize; 3],
+    // per-corner normals for smooth shading; the face normal otherwise.
+    pub normals: Option<[usize; 3]>,
+}
+
+// triangles sharing one vertex buffer and one normal buffer, as they come
+// out of a modelling tool. vertices shared by several faces are stored once.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    pub normals: Vec<Normal3>,
+    pub faces: Vec<Face>,
+    pub material: i16,
+    // over the faces, rebuilt by new
+    pub bvh: Bvh,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Point3>, normals: Vec<Normal3>, faces: Vec<Face>, material: i16) -> Self {
+        for face in &faces {
+            assert!(face.vertices.iter().all(|i| *i < vertices.len()), "mesh face vertex out of range");
+            assert!(face.normals.iter().flatten().all(|i| *i < normals.len()), "mesh face normal out of range");
+        }
+        let bounds: Vec<Aabb> = faces
+            .iter()
+            .map(|face| {
+                let mut b = Aabb::empty();
+                face.vertices.iter().for_each(|i| b.expand(&vertices[*i]));
+                b
+            })
+            .collect();
+        let bvh = Bvh::new(&bounds, Split::Sah);
+        Self { vertices, normals, faces, material, bvh }
+    }
+}
+
+impl Mesh {
+    pub fn triangle(&self, face: usize) -> Triangle {
+        let face = &self.faces[face];
+        let vertices = face.vertices.map(|i| self.vertices[i].clone());
+        match face.normals {
+            Some(normals) => Triangle::smooth(vertices, normals.map(|i| self.normals[i].clone()), self.material),
+            None => Triangle::new(vertices, self.material),
+        }
+    }
+}
+
+impl Shape for Mesh {
+    // the faces the bvh lets through, narrowing t_max to the nearest hit so far.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        self.bvh.hit(ray, t_min, t_max, |i, far| self.triangle(i).hit(ray, t_min, far))
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> bool {
+        self.bvh.occluded(ray, t_min, t_max, |i, far| self.triangle(i).hit(ray, t_min, far).is_some())
+    }
+
+    // unused vertices play no part, so this is the faces' box
+    fn bounds(&self) -> Option<Aabb> {
+        self.bvh.nodes.first().map(|n| n.bounds.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::cuboid::Cuboid;
+    use crate::geometry::Dir3;
+
+    // the cube [-1, 1]^3: 8 shared vertices, 12 faces wound outwards.
+    fn cube() -> Mesh {
+        let vertices = (0..8).map(|i| Point3::from([0, 1, 2].map(|b| if i >> b & 1 == 1 { 1.0 } else { -1.0 }))).collect();
+        let quads = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+        let faces = quads
+            .iter()
+            .flat_map(|q| [[q[0], q[1], q[2]], [q[0], q[2], q[3]]])
+            .map(|vertices| Face { vertices, normals: None })
+            .collect();
+        Mesh::new(vertices, Vec::new(), faces, 6)
+    }
+
+    #[test]
+    fn cube_against_cuboid() {
+        let (mesh, solid) = (cube(), Cuboid::new(&Point3::from([-1.0, -1.0, -1.0]), &Point3::from([1.0, 1.0, 1.0]), 6));
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.bounds().unwrap(), solid.bounds);
+
+        let mut rng = StdRng::seed_from_u64(49);
+        let mut hits = 0;
+        for _ in 0..300 {
+            let o = [0, 1, 2].map(|_| rng.gen_range(-4.0..4.0));
+            let at = [0, 1, 2].map(|_| rng.gen_range(-1.5..1.5));
+            let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+            let (got, want) = (mesh.hit(&r, &Number::ZERO, &Number::MAX), solid.hit(&r, &Number::ZERO, &Number::MAX));
+            match (got, want) {
+                (Some(a), Some(b)) => {
+                    hits += 1;
+                    assert!((f64::from(&a.t) - f64::from(&b.t)).abs() < 1.0 / 4096.0, "{:?} vs {:?}", a.t, b.t);
+                    assert_eq!(a.front_face, b.front_face);
+                    let (na, nb) = (<[f64; 3]>::from(&a.normal), <[f64; 3]>::from(&b.normal));
+                    assert!((0..3).all(|i| (na[i] - nb[i]).abs() < 1.0 / 4096.0), "{:?} vs {:?}", na, nb);
+                    assert_eq!(a.material, 6);
+                }
+                (None, None) => {}
+                // through an edge or corner, one may just miss
+                (a, b) => {
+                    let p = <[f64; 3]>::from(&a.or(b).unwrap().point);
+                    assert!(p.iter().filter(|c| (c.abs() - 1.0).abs() < 1.0 / 1024.0).count() >= 2, "disagree at {:?}", p);
+                }
+            }
+        }
+        assert!(hits > 100, "{} hits", hits);
+    }
+
+    #[test]
+    fn smooth_normals() {
+        // a square at y = 0 whose corner normals lean out like a dome's
+        let vertices = [[-1.0, 0.0, -1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 0.0, -1.0]].map(Point3::from).to_vec();
+        let normals = [[-1.0, 1.0, -1.0], [-1.0, 1.0, 1.0], [1.0, 1.0, 1.0], [1.0, 1.0, -1.0]]
+            .map(|n| {
+                let mut n = Normal3::from(n);
+                n.normalize();
+                n
+            })
+            .to_vec();
+        let faces = vec![
+            Face { vertices: [0, 1, 2], normals: Some([0, 1, 2]) },
+            Face { vertices: [0, 2, 3], normals: Some([0, 2, 3]) },
+        ];
+        let mesh = Mesh::new(vertices, normals, faces, 0);
+
+        let down = |x: f64, z: f64| mesh.hit(&Ray::new(Point3::from([x, 5.0, z]), Dir3::from([0.0, -1.0, 0.0])), &Number::ZERO, &Number::MAX).unwrap();
+        let h = down(0.0, 0.0);
+        assert_eq!(h.t, Number::from(5));
+        let n = <[f64; 3]>::from(&h.normal);
+        assert!(n[0].abs() < 1.0 / 1024.0 && (n[1] - 1.0).abs() < 1.0 / 1024.0 && n[2].abs() < 1.0 / 1024.0, "{:?}", n);
+        // towards a corner, towards its normal
+        let n = <[f64; 3]>::from(&down(0.9, 0.9).normal);
+        assert!(n[0] > 0.5 && n[2] > 0.5, "{:?}", n);
+        // from below, turned to face the ray
+        let h = mesh.hit(&Ray::new(Point3::from([0.0, -5.0, 0.0]), Dir3::from([0.0, 1.0, 0.0])), &Number::ZERO, &Number::MAX).unwrap();
+        assert!(!h.front_face);
+        assert!(h.normal.vec3().y.is_negative());
+    }
+}
@@ -1,12 +1,23 @@
 use lazy_static::lazy_static;
+use crate::error::{policy, raise, ArithError, Policy};
 use crate::int32::{Int32, div_u4};
+use crate::int64::Int64;
 
 lazy_static! {
     static ref SCALE_FACTOR: Int32 = Int32::from(0xFFFF);
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Number(Int32);
+#[derive(Debug, PartialEq, Clone)]
+pub struct Number(pub Int32);
+
+impl Number {
+    pub const ZERO: Number = Number(Int32 { parts: [0, 0, 0, 0] });
+    pub const ONE: Number = Number(Int32 { parts: [0, 0, 1, 0] });
+    pub const ULP: Number = Number(Int32 { parts: [1, 0, 0, 0] });
+    pub const HALF: Number = Number(Int32 { parts: [0, 0x80, 0, 0] });
+    pub const MAX: Number = Number(Int32 { parts: [0xFF, 0xFF, 0xFF, 0x7F] });
+    pub const MIN: Number = Number(Int32 { parts: [0, 0, 0, 0x80] });
+}
 
 impl From<i16> for Number {
     fn from(n: i16) -> Self {
@@ -15,6 +26,20 @@ impl From<i16> for Number {
     }
 }
 
+// host-side conversions for scene setup and tests; rounds to the nearest
+// 1/65536 and clamps to the representable range.
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Self(Int32::from((n * 65536.0).round().clamp(i32::MIN as f64, i32::MAX as f64) as i32))
+    }
+}
+
+impl From<&Number> for f64 {
+    fn from(n: &Number) -> Self {
+        i32::from(n.0.clone()) as f64 / 65536.0
+    }
+}
+
 impl Number {
     pub fn add(&mut self, other: &Self) {
         self.0.add(&other.0);
@@ -23,6 +48,88 @@ impl Number {
     pub fn sub(&mut self, other: &Self) {
         self.0.sub(&other.0);
     }
+
+    pub fn neg(&mut self) {
+        self.0.neg();
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.parts[3] >= 0x80
+    }
+
+    pub fn lt(&self, other: &Self) -> bool {
+        self.0.lt(&other.0)
+    }
+}
+
+impl Number {
+    pub fn try_add(&mut self, other: &Self) -> Result<(), ArithError> {
+        let mut r = self.clone();
+        r.add(other);
+        if self.is_negative() == other.is_negative() && r.is_negative() != self.is_negative() {
+            return Err(ArithError::Overflow);
+        }
+
+        *self = r;
+        Ok(())
+    }
+
+    pub fn try_sub(&mut self, other: &Self) -> Result<(), ArithError> {
+        let mut r = self.clone();
+        r.sub(other);
+        if self.is_negative() != other.is_negative() && r.is_negative() != self.is_negative() {
+            return Err(ArithError::Overflow);
+        }
+
+        *self = r;
+        Ok(())
+    }
+
+    // checks the product of the magnitudes piece by piece before falling back
+    // to mul, so the result rounds exactly like mul does.
+    pub fn try_mul(&mut self, other: &Self) -> Result<(), ArithError> {
+        let negative = self.is_negative() ^ other.is_negative();
+
+        let mut a = self.0.clone();
+        if a.parts[3] >= 0x80 {
+            a.neg();
+        }
+        let mut b = other.0.clone();
+        if b.parts[3] >= 0x80 {
+            b.neg();
+        }
+
+        let (ah, al) = (Int32 { parts: [a.parts[2], a.parts[3], 0, 0] }, Int32 { parts: [a.parts[0], a.parts[1], 0, 0] });
+        let (bh, bl) = (Int32 { parts: [b.parts[2], b.parts[3], 0, 0] }, Int32 { parts: [b.parts[0], b.parts[1], 0, 0] });
+
+        let mut hh = ah.clone();
+        hh.mul(&bh);
+        if Int32::from(0x8000).lt(&hh) {
+            return Err(ArithError::Overflow);
+        }
+
+        let mut hl = ah;
+        hl.mul(&bl);
+        let mut lh = al.clone();
+        lh.mul(&bh);
+        let mut ll = al;
+        ll.mul(&bl);
+
+        let limit = Int32::from(i32::MIN);
+        let mut r = Int32 { parts: [0, 0, hh.parts[0], hh.parts[1]] };
+        for term in [hl, lh, Int32 { parts: [ll.parts[2], ll.parts[3], 0, 0] }] {
+            r.add(&term);
+            if limit.lt_unsigned(&r) {
+                return Err(ArithError::Overflow);
+            }
+        }
+        if r == limit && !negative {
+            return Err(ArithError::Overflow);
+        }
+
+        self.mul(other);
+        Ok(())
+    }
 }
 
 impl Number {
@@ -72,12 +179,258 @@ impl Number {
     }
 }
 
+// fused forms: products are summed exactly in an Int64 (as 32.32 fixed
+// point) and rounded down to 16.16 once, instead of once per mul.
+impl Number {
+    pub fn mul_add(&mut self, a: &Self, b: &Self) {
+        let mut acc = self.widened();
+        acc.add(&Int64::product(&a.0, &b.0));
+        *self = Self::narrowed(&acc);
+    }
+
+    pub fn try_mul_add(&mut self, a: &Self, b: &Self) -> Result<(), ArithError> {
+        let mut acc = self.widened();
+        acc.add(&Int64::product(&a.0, &b.0));
+        *self = Self::try_narrowed(&acc)?;
+        Ok(())
+    }
+
+    pub fn dot(a: &[Self], b: &[Self]) -> Self {
+        Self::narrowed(&Self::sum_of_products(a, b))
+    }
+
+    pub fn try_dot(a: &[Self], b: &[Self]) -> Result<Self, ArithError> {
+        Self::try_narrowed(&Self::sum_of_products(a, b))
+    }
+
+    // the euclidean norm of up to three components: the squares are summed
+    // exactly and the root taken on the wide sum, so nothing overflows until
+    // the result itself doesn't fit.
+    pub fn hypot(xs: &[Self]) -> Self {
+        match Self::try_hypot(xs) {
+            Ok(n) => n,
+            Err(err) => {
+                raise(err);
+                Self::MAX
+            }
+        }
+    }
+
+    pub fn try_hypot(xs: &[Self]) -> Result<Self, ArithError> {
+        assert!(xs.len() <= 3, "hypot of more than three components");
+
+        let mut root = Self::sum_of_products(xs, xs);
+        root.sqrt_rem();
+        if root.parts[4..] != [0; 4] || root.parts[3] >= 0x80 {
+            return Err(ArithError::Overflow);
+        }
+
+        Ok(Self(Int32 { parts: [root.parts[0], root.parts[1], root.parts[2], root.parts[3]] }))
+    }
+
+    // (a . b) / (c . d) with neither dot product rounded first, for ray
+    // parameters like -(oc . d) / (d . d) whose terms leave 16.16.
+    pub fn try_dot_ratio(a: &[Self], b: &[Self], c: &[Self], d: &[Self]) -> Result<Self, ArithError> {
+        Self::try_ratio(&Self::sum_of_products(a, b), &Self::sum_of_products(c, d))
+    }
+
+    // num / den for two wide values in the same fixed point scale, truncated
+    // towards zero like div. binary long division: 15 integer bits, then 16
+    // fraction bits.
+    pub fn try_ratio(num: &Int64, den: &Int64) -> Result<Self, ArithError> {
+        if den.parts == [0; 8] {
+            return Err(ArithError::DivideByZero);
+        }
+
+        let negative = (num.parts[7] >= 0x80) ^ (den.parts[7] >= 0x80);
+        let (mut rem, mut den) = (num.clone(), den.clone());
+        if rem.parts[7] >= 0x80 {
+            rem.neg();
+        }
+        if den.parts[7] >= 0x80 {
+            den.neg();
+        }
+
+        let mut q = Int32::from(0);
+        for i in (0..15).rev() {
+            let mut step = den.clone();
+            step.shl(i);
+            let mut back = step.clone();
+            back.shr(i);
+            q.shl(1);
+            if back == den && !rem.lt_unsigned(&step) {
+                rem.sub(&step);
+                q.add(&Int32::from(1));
+            }
+        }
+        if !rem.lt_unsigned(&den) {
+            return Err(ArithError::Overflow);
+        }
+
+        for _ in 0..16 {
+            rem.shl(1);
+            q.shl(1);
+            if !rem.lt_unsigned(&den) {
+                rem.sub(&den);
+                q.add(&Int32::from(1));
+            }
+        }
+
+        if negative {
+            q.neg();
+        }
+        Ok(Self(q))
+    }
+
+    // a . b exactly, in 32.32.
+    pub fn sum_of_products(a: &[Self], b: &[Self]) -> Int64 {
+        assert_eq!(a.len(), b.len(), "dot product of different lengths");
+
+        let mut acc = Int64 { parts: [0; 8] };
+        for (x, y) in a.iter().zip(b) {
+            acc.add(&Int64::product(&x.0, &y.0));
+        }
+        acc
+    }
+
+    fn widened(&self) -> Int64 {
+        let ext = if self.is_negative() { 0xFF } else { 0 };
+        let p = self.0.parts;
+        Int64 { parts: [0, 0, p[0], p[1], p[2], p[3], ext, ext] }
+    }
+
+    fn try_narrowed(acc: &Int64) -> Result<Self, ArithError> {
+        let ext = if acc.parts[5] >= 0x80 { 0xFF } else { 0 };
+        if acc.parts[6] != ext || acc.parts[7] != ext {
+            return Err(ArithError::Overflow);
+        }
+
+        Ok(Self(Int32 { parts: [acc.parts[2], acc.parts[3], acc.parts[4], acc.parts[5]] }))
+    }
+
+    fn narrowed(acc: &Int64) -> Self {
+        match Self::try_narrowed(acc) {
+            Ok(n) => n,
+            Err(err) if policy() != Policy::Panic => {
+                raise(err);
+                Self::saturated(acc.parts[7] >= 0x80)
+            }
+            Err(_) => Self(Int32 { parts: [acc.parts[2], acc.parts[3], acc.parts[4], acc.parts[5]] }),
+        }
+    }
+}
+
+impl Number {
+    fn saturated(negative: bool) -> Self {
+        if negative { Self::MIN } else { Self::MAX }
+    }
+}
+
+// saturating forms, whatever the error policy: a result out of range clamps
+// to MIN or MAX. unlike add and the rest they return the result instead of
+// updating self, so that they nest.
+impl Number {
+    pub fn saturating_add(&self, other: &Self) -> Self {
+        let mut r = self.clone();
+        if r.try_add(other).is_err() {
+            return Self::saturated(other.is_negative());
+        }
+        r
+    }
+
+    pub fn saturating_sub(&self, other: &Self) -> Self {
+        let mut r = self.clone();
+        if r.try_sub(other).is_err() {
+            return Self::saturated(!other.is_negative());
+        }
+        r
+    }
+
+    pub fn saturating_mul(&self, other: &Self) -> Self {
+        let mut r = self.clone();
+        if r.try_mul(other).is_err() {
+            return Self::saturated(self.is_negative() ^ other.is_negative());
+        }
+        r
+    }
+
+    // dividing by zero saturates by the dividend's sign.
+    pub fn saturating_div(&self, other: &Self) -> Self {
+        let mut r = self.clone();
+        if r.try_div(other).is_err() {
+            return Self::saturated(self.is_negative() ^ other.is_negative());
+        }
+        r
+    }
+
+    pub fn saturating_mul_add(&self, a: &Self, b: &Self) -> Self {
+        let mut acc = self.widened();
+        acc.add(&Int64::product(&a.0, &b.0));
+        Self::try_narrowed(&acc).unwrap_or_else(|_| Self::saturated(acc.parts[7] >= 0x80))
+    }
+
+    pub fn saturating_dot(a: &[Self], b: &[Self]) -> Self {
+        let acc = Self::sum_of_products(a, b);
+        Self::try_narrowed(&acc).unwrap_or_else(|_| Self::saturated(acc.parts[7] >= 0x80))
+    }
+
+    // -MIN is MAX.
+    pub fn saturating_neg(&self) -> Self {
+        if *self == Self::MIN {
+            return Self::MAX;
+        }
+        let mut r = self.clone();
+        r.neg();
+        r
+    }
+
+    pub fn saturating_abs(&self) -> Self {
+        if self.is_negative() { self.saturating_neg() } else { self.clone() }
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        if other.lt(&self) { other } else { self }
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.lt(&other) { other } else { self }
+    }
+}
+
 impl Number {
     pub fn div(&mut self, other: &Self) {
         if other.0.parts == [0; 4] {
-            panic!("divide by zero");
+            raise(ArithError::DivideByZero);
+            if self.0.parts != [0; 4] {
+                *self = Self::saturated(self.0.parts[3] >= 0x80);
+            }
+            return;
+        }
+
+        let negative = (self.0.parts[3] >= 0x80) ^ (other.0.parts[3] >= 0x80);
+        if self.div_wrapping(other) && policy() != Policy::Panic {
+            raise(ArithError::Overflow);
+            *self = Self::saturated(negative);
+        }
+    }
+
+    pub fn try_div(&mut self, other: &Self) -> Result<(), ArithError> {
+        if other.0.parts == [0; 4] {
+            return Err(ArithError::DivideByZero);
         }
 
+        let mut q = Self(Int32 { parts: self.0.parts });
+        if q.div_wrapping(other) {
+            return Err(ArithError::Overflow);
+        }
+
+        *self = q;
+        Ok(())
+    }
+
+    // divides by a non-zero divisor, keeping the low 32 bits of the quotient.
+    // returns whether the quotient didn't fit in 16.16.
+    fn div_wrapping(&mut self, other: &Self) -> bool {
         let negative = (self.0.parts[3] >= 0x80) ^ (other.0.parts[3] >= 0x80);
 
         if self.0.parts[3] >= 0x80 {
@@ -118,13 +471,64 @@ impl Number {
             q[6] + q[7] * 16,
         ];
 
+        let overflow = q[8..].iter().any(|d| *d != 0)
+            || (self.0.parts[3] >= 0x80 && !(negative && self.0.parts == [0, 0, 0, 0x80]));
         if negative {
             self.0.neg();
         }
+
+        overflow
+    }
+}
+
+impl Number {
+    pub fn sqrt(&mut self) {
+        if self.is_negative() {
+            raise(ArithError::NegativeSqrt);
+            *self = Self::ZERO;
+            return;
+        }
+
+        self.sqrt_unchecked();
+    }
+
+    pub fn try_sqrt(&mut self) -> Result<(), ArithError> {
+        if self.is_negative() {
+            return Err(ArithError::NegativeSqrt);
+        }
+
+        self.sqrt_unchecked();
+        Ok(())
+    }
+
+    // sqrt(raw / 2^16) * 2^16 = sqrt(raw * 2^16): take the integer root of raw,
+    // then carry the digit-by-digit method on for 8 more bits of root.
+    fn sqrt_unchecked(&mut self) {
+        let mut root = self.0.clone();
+        let mut rem = root.sqrt_rem();
+        for _ in 0..8 {
+            rem.shl(2);
+            let mut trial = root.clone();
+            trial.shl(2);
+            trial.add(&Int32::from(1));
+
+            root.shl(1);
+            if !rem.lt(&trial) {
+                rem.sub(&trial);
+                root.add(&Int32::from(1));
+            }
+        }
+
+        self.0 = root;
     }
 }
 
+#[cfg(test)]
 mod tests {
+    use rand::Rng;
+
+    use crate::error::{set_policy, take_error};
+
     use super::*;
 
     #[test]
@@ -172,4 +576,324 @@ mod tests {
             assert_eq!(n, Number(Int32::from(want)), "divide: {} / {}", a, b);
         }
     }
+
+    #[test]
+    fn try_div() {
+        let mut n = Number::from(3);
+        assert_eq!(n.try_div(&Number::from(2)), Ok(()));
+        assert_eq!(n, Number(Int32::from(0x18000)));
+
+        let mut n = Number::from(3);
+        assert_eq!(n.try_div(&Number::from(0)), Err(ArithError::DivideByZero));
+        assert_eq!(n, Number::from(3));
+
+        let mut n = Number::from(30000);
+        assert_eq!(n.try_div(&Number(Int32::from(0x8000))), Err(ArithError::Overflow));
+        assert_eq!(n, Number::from(30000));
+
+        let mut n = Number::from(-16384);
+        assert_eq!(n.try_div(&Number(Int32::from(0x8000))), Ok(()));
+        assert_eq!(n, Number::from(i16::MIN));
+
+        let mut n = Number::from(16384);
+        assert_eq!(n.try_div(&Number(Int32::from(0x8000))), Err(ArithError::Overflow));
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn div_by_zero_panics() {
+        let mut n = Number::from(1);
+        n.div(&Number::from(0));
+    }
+
+    #[test]
+    fn div_saturates() {
+        set_policy(Policy::Saturate);
+
+        let mut n = Number::from(1);
+        n.div(&Number::from(0));
+        assert_eq!(n, Number(Int32::from(i32::MAX)));
+
+        let mut n = Number::from(-30000);
+        n.div(&Number(Int32::from(0x8000)));
+        assert_eq!(n, Number(Int32::from(i32::MIN)));
+
+        assert_eq!(take_error(), None);
+    }
+
+    #[test]
+    fn div_records() {
+        set_policy(Policy::Record);
+
+        let mut n = Number::from(-1);
+        n.div(&Number::from(0));
+        assert_eq!(n, Number(Int32::from(i32::MIN)));
+        assert_eq!(take_error(), Some(ArithError::DivideByZero));
+
+        let mut n = Number::from(30000);
+        n.div(&Number(Int32::from(0x8000)));
+        assert_eq!(n, Number(Int32::from(i32::MAX)));
+        assert_eq!(take_error(), Some(ArithError::Overflow));
+    }
+
+    #[test]
+    fn from_f64() {
+        assert_eq!(Number::from(1.5), Number(Int32::from(0x18000)));
+        assert_eq!(Number::from(-0.25), Number(Int32::from(-0x4000)));
+        assert_eq!(Number::from(1e9), Number::MAX);
+        assert_eq!(f64::from(&Number(Int32::from(-0x18000))), -1.5);
+    }
+
+    #[test]
+    fn lt() {
+        assert!(Number::from(-1).lt(&Number::ULP));
+        assert!(!Number::ONE.lt(&Number::ONE));
+        assert!(Number::MIN.lt(&Number::MAX));
+    }
+
+    #[test]
+    fn saturating() {
+        assert_eq!(Number::from(20000).saturating_add(&Number::from(20000)), Number::MAX);
+        assert_eq!(Number::from(-20000).saturating_add(&Number::from(-20000)), Number::MIN);
+        assert_eq!(Number::from(-20000).saturating_sub(&Number::from(20000)), Number::MIN);
+        assert_eq!(Number::from(300).saturating_mul(&Number::from(-300)), Number::MIN);
+        assert_eq!(Number::from(-1).saturating_div(&Number::ZERO), Number::MIN);
+        assert_eq!(Number::ONE.saturating_mul_add(&Number::from(300), &Number::from(300)), Number::MAX);
+        assert_eq!(Number::saturating_dot(&[Number::from(300)], &[Number::from(-300)]), Number::MIN);
+        assert_eq!(Number::MIN.saturating_neg(), Number::MAX);
+        assert_eq!(Number::MIN.saturating_abs(), Number::MAX);
+        assert_eq!(Number::from(-2).saturating_abs(), Number::from(2));
+        assert_eq!(Number::HALF, Number::from(0.5));
+        assert_eq!(Number::ONE.min(Number::HALF), Number::HALF);
+        assert_eq!(Number::ONE.max(Number::HALF), Number::ONE);
+
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i32>(), rand::random::<i32>());
+            let (x, y) = (Number(Int32::from(a)), Number(Int32::from(b)));
+            assert_eq!(x.saturating_add(&y), Number(Int32::from(a.saturating_add(b))), "{} + {}", a, b);
+            assert_eq!(x.saturating_sub(&y), Number(Int32::from(a.saturating_sub(b))), "{} - {}", a, b);
+            let want = ((a as i64 * b as i64) >> 16).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+            assert_eq!(x.saturating_mul(&y), Number(Int32::from(want)), "{} * {}", a, b);
+        }
+    }
+
+    #[test]
+    fn try_add() {
+        let mut n = Number::from(20000);
+        assert_eq!(n.try_add(&Number::from(12767)), Ok(()));
+        assert_eq!(n.try_add(&Number::ONE), Err(ArithError::Overflow));
+        assert_eq!(n, Number::from(32767));
+
+        let mut n = Number::MIN;
+        assert_eq!(n.try_sub(&Number::ULP), Err(ArithError::Overflow));
+        assert_eq!(n.try_add(&Number::ULP), Ok(()));
+
+        for _ in 0..100 {
+            let (a, b) = (rand::random::<i32>(), rand::random::<i32>());
+            let mut n = Number(Int32::from(a));
+            match a.checked_add(b) {
+                Some(want) => {
+                    assert_eq!(n.try_add(&Number(Int32::from(b))), Ok(()));
+                    assert_eq!(n, Number(Int32::from(want)));
+                }
+                None => assert_eq!(n.try_add(&Number(Int32::from(b))), Err(ArithError::Overflow)),
+            }
+
+            let mut n = Number(Int32::from(a));
+            match a.checked_sub(b) {
+                Some(want) => {
+                    assert_eq!(n.try_sub(&Number(Int32::from(b))), Ok(()));
+                    assert_eq!(n, Number(Int32::from(want)));
+                }
+                None => assert_eq!(n.try_sub(&Number(Int32::from(b))), Err(ArithError::Overflow)),
+            }
+        }
+    }
+
+    #[test]
+    fn try_mul() {
+        let mut n = Number::from(181);
+        assert_eq!(n.try_mul(&Number::from(181)), Ok(()));
+        assert_eq!(n, Number::from(32761));
+
+        let mut n = Number::from(182);
+        assert_eq!(n.try_mul(&Number::from(182)), Err(ArithError::Overflow));
+        assert_eq!(n, Number::from(182));
+
+        let mut n = Number::from(-16384);
+        assert_eq!(n.try_mul(&Number::from(2)), Ok(()));
+        assert_eq!(n, Number::MIN);
+
+        let mut n = Number::from(16384);
+        assert_eq!(n.try_mul(&Number::from(2)), Err(ArithError::Overflow));
+
+        for _ in 0..1000 {
+            let (a, b) = (rand::random::<i32>() >> (rand::random::<u8>() % 32), rand::random::<i32>() >> (rand::random::<u8>() % 32));
+            let mut n = Number(Int32::from(a));
+            let want = (a as i64 * b as i64) >> 16;
+            if i32::try_from(want).is_ok() {
+                assert_eq!(n.try_mul(&Number(Int32::from(b))), Ok(()), "multiply: {} * {}", a, b);
+                assert_eq!(n, Number(Int32::from(want as i32)));
+            } else {
+                assert_eq!(n.try_mul(&Number(Int32::from(b))), Err(ArithError::Overflow), "multiply: {} * {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt() {
+        let mut n = Number::from(4);
+        n.sqrt();
+        assert_eq!(n, Number::from(2));
+
+        let mut n = Number::from(2);
+        n.sqrt();
+        assert_eq!(n, Number(Int32::from((2f64.sqrt() * 65536.0) as i32)));
+
+        let mut n = Number::MAX;
+        n.sqrt();
+        assert_eq!(n, Number(Int32::from(((i32::MAX as f64) * 65536.0).sqrt() as i32)));
+
+        for _ in 0..100 {
+            let a = rand::random::<i32>() & 0x7FFFFFFF;
+            let mut n = Number(Int32::from(a));
+            n.sqrt();
+            let r = i32::from(n.0) as i64;
+            let wide = (a as i64) << 16;
+            assert!(r * r <= wide && (r + 1) * (r + 1) > wide, "sqrt: {}", a);
+        }
+    }
+
+    #[test]
+    fn try_sqrt() {
+        let mut n = Number::from(-4);
+        assert_eq!(n.try_sqrt(), Err(ArithError::NegativeSqrt));
+        assert_eq!(n, Number::from(-4));
+
+        set_policy(Policy::Record);
+        n.sqrt();
+        assert_eq!(n, Number::ZERO);
+        assert_eq!(take_error(), Some(ArithError::NegativeSqrt));
+    }
+
+    #[test]
+    fn mul_add() {
+        let mut n = Number::ONE;
+        n.mul_add(&Number::from(2), &Number::from(3));
+        assert_eq!(n, Number::from(7));
+
+        for _ in 0..100 {
+            let (c, a, b) = (rand::random::<i32>() >> 8, rand::random::<i32>() >> 4, rand::random::<i32>() >> 8);
+            let mut n = Number(Int32::from(c));
+            n.mul_add(&Number(Int32::from(a)), &Number(Int32::from(b)));
+            let want = (((c as i64) << 16) + a as i64 * b as i64) >> 16;
+            assert_eq!(n, Number(Int32::from(want as i32)), "{} + {} * {}", c, a, b);
+        }
+    }
+
+    #[test]
+    fn dot_rounds_once() {
+        let mut fused_worse = 0;
+        let mut chained_worse = 0;
+        for _ in 0..1000 {
+            let a: Vec<i32> = (0..3).map(|_| rand::random::<i32>() >> 9).collect();
+            let b: Vec<i32> = (0..3).map(|_| rand::random::<i32>() >> 9).collect();
+            let na: Vec<Number> = a.iter().map(|x| Number(Int32::from(*x))).collect();
+            let nb: Vec<Number> = b.iter().map(|x| Number(Int32::from(*x))).collect();
+            let exact: i64 = a.iter().zip(&b).map(|(x, y)| *x as i64 * *y as i64).sum();
+
+            let fused = Number::dot(&na, &nb);
+            assert_eq!(fused, Number(Int32::from((exact >> 16) as i32)));
+
+            let mut chained = Number::ZERO;
+            for (x, y) in na.iter().zip(&nb) {
+                let mut p = x.clone();
+                p.mul(y);
+                chained.add(&p);
+            }
+
+            let exact = exact as f64 / 4294967296.0;
+            let fused_err = (f64::from(&fused) - exact).abs();
+            let chained_err = (f64::from(&chained) - exact).abs();
+            assert!(fused_err < 1.0 / 65536.0);
+            if fused_err > chained_err {
+                fused_worse += 1;
+            } else if chained_err > fused_err {
+                chained_worse += 1;
+            }
+        }
+        assert!(chained_worse > fused_worse * 2, "chained worse {} times, fused worse {} times", chained_worse, fused_worse);
+    }
+
+    #[test]
+    fn dot_survives_intermediate_overflow() {
+        let a = [Number::from(200), Number::from(-200)];
+        let b = [Number::from(200), Number::from(199)];
+
+        let mut first = Number::from(200);
+        assert_eq!(first.try_mul(&Number::from(200)), Err(ArithError::Overflow));
+        assert_eq!(Number::dot(&a, &b), Number::from(200));
+        assert_eq!(Number::try_dot(&a, &b), Ok(Number::from(200)));
+    }
+
+    #[test]
+    fn dot_overflow() {
+        let a = [Number::from(200), Number::from(200)];
+        assert_eq!(Number::try_dot(&a, &a), Err(ArithError::Overflow));
+
+        let mut n = Number::from(30000);
+        assert_eq!(n.try_mul_add(&Number::from(100), &Number::from(100)), Err(ArithError::Overflow));
+        assert_eq!(n, Number::from(30000));
+
+        set_policy(Policy::Record);
+        assert_eq!(Number::dot(&a, &a), Number::MAX);
+        assert_eq!(take_error(), Some(ArithError::Overflow));
+    }
+
+    #[test]
+    fn hypot() {
+        assert_eq!(Number::hypot(&[Number::from(3), Number::from(-4)]), Number::from(5));
+        assert_eq!(Number::hypot(&[Number::ULP, Number::ULP, Number::ULP]), Number::ULP);
+        assert_eq!(Number::try_hypot(&[Number::MIN]), Err(ArithError::Overflow));
+        assert_eq!(Number::try_hypot(&[Number::MAX, Number::MAX, Number::MAX]), Err(ArithError::Overflow));
+        assert_eq!(Number::try_hypot(&[Number::MAX, Number::ZERO, Number::ZERO]), Ok(Number::MAX));
+
+        for _ in 0..100 {
+            let xs: Vec<i32> = (0..3).map(|_| rand::random::<i32>() >> 2).collect();
+            let ns: Vec<Number> = xs.iter().map(|x| Number(Int32::from(*x))).collect();
+            let sum: u128 = xs.iter().map(|x| (*x as i128 * *x as i128) as u128).sum();
+            let r = i32::from(Number::hypot(&ns).0) as u128;
+            assert!(r * r <= sum && (r + 1) * (r + 1) > sum, "hypot: {:?}", xs);
+        }
+    }
+
+    #[test]
+    fn ratio() {
+        let w = |x: i64| Int64::from(x << 16);
+        assert_eq!(Number::try_ratio(&w(9 << 16), &w(4 << 16)), Ok(Number::from(2.25)));
+        assert_eq!(Number::try_ratio(&w(-1 << 16), &w(3 << 16)), Ok(Number(Int32::from(-21845))));
+        assert_eq!(Number::try_ratio(&w(1), &w(0)), Err(ArithError::DivideByZero));
+        assert_eq!(Number::try_ratio(&w(1 << 32), &w(1)), Err(ArithError::Overflow));
+    }
+
+    #[test]
+    fn dot_ratio() {
+        let n = |x: i16| Number::from(x);
+        // (300 * 300) / (200 * 200), with both products out of range
+        assert_eq!(Number::try_dot_ratio(&[n(300)], &[n(300)], &[n(200)], &[n(200)]), Ok(Number::from(2.25)));
+        assert_eq!(Number::try_dot_ratio(&[n(-3), n(1)], &[n(1), n(1)], &[n(1)], &[n(4)]), Ok(Number::from(-0.5)));
+        assert_eq!(Number::try_dot_ratio(&[n(1)], &[n(1)], &[n(0)], &[n(1)]), Err(ArithError::DivideByZero));
+        assert_eq!(Number::try_dot_ratio(&[n(30000)], &[n(30000)], &[Number::ULP], &[Number::ULP]), Err(ArithError::Overflow));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let v: Vec<Number> = (0..4).map(|_| Number::from(rng.gen_range(-400.0..400.0))).collect();
+            let f: Vec<f64> = v.iter().map(f64::from).collect();
+            let want = (f[0] * f[1]) / (f[2] * f[3]);
+            match Number::try_dot_ratio(&v[0..1], &v[1..2], &v[2..3], &v[3..4]) {
+                Ok(q) => assert!((f64::from(&q) - want).abs() <= 1.0 / 65536.0, "{:?}: got {}, want {}", f, f64::from(&q), want),
+                Err(_) => assert!(want.abs() >= 32767.0, "{:?}: want {}", f, want),
+            }
+        }
+    }
 }
@@ -0,0 +1,274 @@
+use std::fmt;
+
+use crate::aabb::Aabb;
+use crate::geometry::{Normal3, Point3};
+use crate::mesh::{Face, Mesh};
+
+// the largest magnitude 16.16 holds on both sides; MIN is one ulp further.
+const LIMIT: f64 = 32767.0 + 65535.0 / 65536.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ObjError {
+    // a record that doesn't parse, by line number from 1
+    Malformed { line: usize, what: &'static str },
+    // a face index past the vertices or normals read so far, or zero
+    BadIndex { line: usize },
+    // coordinates 16.16 can't hold; rescale first
+    OutOfRange { count: usize },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Malformed { line, what } => write!(f, "obj line {}: {}", line, what),
+            ObjError::BadIndex { line } => write!(f, "obj line {}: face index out of range", line),
+            ObjError::OutOfRange { count } => write!(f, "obj has {} coordinates outside 16.16", count),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// a Wavefront OBJ file's geometry as read, in f64, so that models in
+// millimetres or kilometres can be measured and rescaled before they are
+// squeezed into 16.16. only v, vn and f records are used; texture
+// coordinates, groups, materials and the rest are skipped.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Obj {
+    pub positions: Vec<[f64; 3]>,
+    pub normals: Vec<[f64; 3]>,
+    // already fanned into triangles
+    pub faces: Vec<Face>,
+}
+
+// what parse found: the extent of the positions, and how many coordinates
+// lie outside 16.16, where Number::from would clamp them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjReport {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    pub out_of_range: usize,
+}
+
+impl Obj {
+    pub fn parse(text: &str) -> Result<Self, ObjError> {
+        let mut obj = Obj { positions: Vec::new(), normals: Vec::new(), faces: Vec::new() };
+        for (n, record) in text.lines().enumerate() {
+            let line = n + 1;
+            // anything after a # is a comment
+            let mut fields = record.split('#').next().unwrap_or("").split_whitespace();
+            match fields.next() {
+                Some("v") => obj.positions.push(triple(&mut fields, line)?),
+                Some("vn") => obj.normals.push(triple(&mut fields, line)?),
+                Some("f") => {
+                    let corners = fields.map(|c| obj.corner(c, line)).collect::<Result<Vec<_>, _>>()?;
+                    if corners.len() < 3 {
+                        return Err(ObjError::Malformed { line, what: "face with fewer than 3 corners" });
+                    }
+                    // a fan around the first corner, which keeps the winding;
+                    // fine for the convex polygons modelling tools write
+                    for k in 1..corners.len() - 1 {
+                        let [a, b, c] = [corners[0], corners[k], corners[k + 1]];
+                        let normals = match (a.1, b.1, c.1) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        };
+                        obj.faces.push(Face { vertices: [a.0, b.0, c.0], normals });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(obj)
+    }
+
+    // v, v/vt, v//vn or v/vt/vn, 1-based, or negative to count back from
+    // the last one read.
+    fn corner(&self, corner: &str, line: usize) -> Result<(usize, Option<usize>), ObjError> {
+        let mut parts = corner.split('/');
+        let vertex = index(parts.next(), self.positions.len(), line)?;
+        let normal = match parts.nth(1) {
+            Some("") | None => None,
+            n => Some(index(n, self.normals.len(), line)?),
+        };
+        Ok((vertex, normal))
+    }
+
+    pub fn report(&self) -> ObjReport {
+        let (mut min, mut max) = ([f64::MAX; 3], [f64::MIN; 3]);
+        let mut out_of_range = 0;
+        for p in &self.positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+                out_of_range += !(-32768.0..=LIMIT).contains(&p[i]) as usize;
+            }
+        }
+        ObjReport { min, max, out_of_range }
+    }
+
+    // moved and scaled, the same in every axis, to sit centred in target as
+    // large as it fits. normals keep their directions under a uniform scale.
+    pub fn rescaled(&self, target: &Aabb) -> Self {
+        let ObjReport { min, max, .. } = self.report();
+        let (lo, hi) = (<[f64; 3]>::from(&target.min), <[f64; 3]>::from(&target.max));
+        let scale = (0..3)
+            .filter(|i| max[*i] > min[*i])
+            .map(|i| (hi[i] - lo[i]) / (max[i] - min[i]))
+            .fold(f64::MAX, f64::min);
+        let scale = if scale == f64::MAX { 1.0 } else { scale };
+        let (from, to) = ([0, 1, 2].map(|i| (min[i] + max[i]) / 2.0), [0, 1, 2].map(|i| (lo[i] + hi[i]) / 2.0));
+
+        let mut obj = self.clone();
+        for p in &mut obj.positions {
+            *p = [0, 1, 2].map(|i| to[i] + (p[i] - from[i]) * scale);
+        }
+        obj
+    }
+
+    pub fn to_mesh(&self, material: i16) -> Result<Mesh, ObjError> {
+        let report = self.report();
+        if report.out_of_range > 0 {
+            return Err(ObjError::OutOfRange { count: report.out_of_range });
+        }
+        let vertices = self.positions.iter().map(|p| Point3::from(*p)).collect();
+        let normals = self
+            .normals
+            .iter()
+            .map(|n| {
+                // normalized in f64 first, so long ones don't clamp
+                let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                let l = if l == 0.0 { 1.0 } else { l };
+                Normal3::from(n.map(|c| c / l))
+            })
+            .collect();
+        Ok(Mesh::new(vertices, normals, self.faces.clone(), material))
+    }
+}
+
+fn triple<'a>(fields: &mut impl Iterator<Item = &'a str>, line: usize) -> Result<[f64; 3], ObjError> {
+    let mut t = [0.0f64; 3];
+    for c in t.iter_mut() {
+        let field = fields.next().ok_or(ObjError::Malformed { line, what: "fewer than 3 coordinates" })?;
+        *c = field.parse().map_err(|_| ObjError::Malformed { line, what: "coordinate is not a number" })?;
+        if !c.is_finite() {
+            return Err(ObjError::Malformed { line, what: "coordinate is not finite" });
+        }
+    }
+    Ok(t)
+}
+
+fn index(field: Option<&str>, count: usize, line: usize) -> Result<usize, ObjError> {
+    let field = field.filter(|f| !f.is_empty()).ok_or(ObjError::Malformed { line, what: "face corner without a vertex" })?;
+    let i: i64 = field.parse().map_err(|_| ObjError::Malformed { line, what: "face index is not a number" })?;
+    let i = if i < 0 { count as i64 + i } else { i - 1 };
+    if i < 0 || i >= count as i64 {
+        return Err(ObjError::BadIndex { line });
+    }
+    Ok(i as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Dir3;
+    use crate::number::Number;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+
+    const SQUARE: &str = "# a unit square and a pentagon
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+vn 0 0 2 # not unit length
+g square
+f 1//1 2//1 3//2 4//2
+usemtl red
+f -4/1 -3/1 -2/1  # negative, with texture coordinates
+v 2 0 0
+v 3 0 0
+v 3.5 1 0
+v 2.5 2 0
+v 1.5 1 0
+s off
+f 5/1/1 6 7/1/1 8 9
+";
+
+    #[test]
+    fn parse() {
+        let obj = Obj::parse(SQUARE).unwrap();
+        assert_eq!(obj.positions.len(), 9);
+        assert_eq!(obj.positions[6], [3.5, 1.0, 0.0]);
+        assert_eq!(obj.normals, vec![[0.0, 0.0, 1.0], [0.0, 0.0, 2.0]]);
+        // a quad fans into 2 triangles, a pentagon into 3
+        assert_eq!(
+            obj.faces,
+            vec![
+                Face { vertices: [0, 1, 2], normals: Some([0, 0, 1]) },
+                Face { vertices: [0, 2, 3], normals: Some([0, 1, 1]) },
+                Face { vertices: [0, 1, 2], normals: None },
+                Face { vertices: [4, 5, 6], normals: None },
+                Face { vertices: [4, 6, 7], normals: None },
+                Face { vertices: [4, 7, 8], normals: None },
+            ]
+        );
+
+        let mesh = obj.to_mesh(2).unwrap();
+        assert_eq!(mesh.normals[1], Normal3::from([0.0, 0.0, 1.0]));
+        let r = Ray::new(Point3::from([2.5, 1.0, 5.0]), Dir3::from([0.0, 0.0, -1.0]));
+        let h = mesh.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(5));
+        assert!(h.front_face);
+        assert_eq!(h.material, 2);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| Obj::parse(text).unwrap_err();
+        assert_eq!(err("v 1 2\n"), ObjError::Malformed { line: 1, what: "fewer than 3 coordinates" });
+        assert_eq!(err("v 0 0 0\nv 1 x 0\n"), ObjError::Malformed { line: 2, what: "coordinate is not a number" });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nf 1 2\n"), ObjError::Malformed { line: 3, what: "face with fewer than 3 corners" });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nf 1 2 3\n"), ObjError::BadIndex { line: 3 });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 0 1 2\n"), ObjError::BadIndex { line: 4 });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 -4\n"), ObjError::BadIndex { line: 4 });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1//1 2 3\n"), ObjError::BadIndex { line: 4 });
+        assert_eq!(err("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 /3\n"), ObjError::Malformed { line: 4, what: "face corner without a vertex" });
+        assert_eq!(err("v inf 0 0\n"), ObjError::Malformed { line: 1, what: "coordinate is not finite" });
+    }
+
+    #[test]
+    fn range_and_rescale() {
+        // a building in millimetres: 60 m tall, off to one side
+        let text = "v 100000 0 -5000\nv 110000 0 -5000\nv 105000 60000 -5000\nv 105000 0 5000\nf 1 2 3\nf 1 4 2\n";
+        let obj = Obj::parse(text).unwrap();
+        let report = obj.report();
+        assert_eq!(report.min, [100000.0, 0.0, -5000.0]);
+        assert_eq!(report.max, [110000.0, 60000.0, 5000.0]);
+        // all four x's and the apex's y
+        assert_eq!(report.out_of_range, 5);
+        assert_eq!(obj.to_mesh(0), Err(ObjError::OutOfRange { count: 5 }));
+
+        // into a 4 x 4 x 4 box at the origin: y is the long side
+        let target = Aabb::new(&Point3::from([-2.0, -2.0, -2.0]), &Point3::from([2.0, 2.0, 2.0]));
+        let fitted = obj.rescaled(&target);
+        let report = fitted.report();
+        assert_eq!(report.out_of_range, 0);
+        let want = ([-1.0 / 3.0, -2.0, -1.0 / 3.0], [1.0 / 3.0, 2.0, 1.0 / 3.0]);
+        for i in 0..3 {
+            assert!((report.min[i] - want.0[i]).abs() < 1e-9 && (report.max[i] - want.1[i]).abs() < 1e-9, "{:?}", report);
+        }
+        let mesh = fitted.to_mesh(0).unwrap();
+        assert_eq!(mesh.faces, obj.faces);
+        let b = mesh.bounds().unwrap();
+        assert!(target.min.vec3().y == b.min.vec3().y && target.max.vec3().y == b.max.vec3().y, "{:?}", b);
+
+        // the edge of the range still fits
+        let obj = Obj::parse("v -32768 32767.5 0\n").unwrap();
+        assert_eq!(obj.report().out_of_range, 0);
+        let obj = Obj::parse("v -32768.5 32768 0\n").unwrap();
+        assert_eq!(obj.report().out_of_range, 2);
+    }
+}
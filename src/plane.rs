@@ -0,0 +1,265 @@
+use crate::error::ArithError;
+use crate::geometry::{Dir3, Normal3, Point3};
+use crate::int32::Int32;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// 0.9 in 16.16
+const STEEP: Number = Number(Int32 { parts: [0x66, 0xE6, 0, 0] });
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Plane {
+    pub point: Point3,
+    pub normal: Normal3,
+    // unit axes in the plane for (u, v), perpendicular to each other and to
+    // the normal. a floor (normal +y) gets u along +x and v along +z.
+    pub u_axis: Dir3,
+    pub v_axis: Dir3,
+    pub material: i16,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Normal3, material: i16) -> Self {
+        let mut normal = normal;
+        normal.normalize();
+
+        // any direction not close to the normal will do to start the basis.
+        let mut z = normal.vec3().z.clone();
+        if z.is_negative() {
+            z.neg();
+        }
+        let helper = if z.lt(&STEEP) {
+            Dir3::new(Number::ZERO, Number::ZERO, Number::ONE)
+        } else {
+            Dir3::new(Number::ZERO, Number::ONE, Number::ZERO)
+        };
+
+        let mut u_axis = Dir3::along(normal.clone());
+        u_axis.cross(&helper);
+        u_axis.normalize();
+        let mut v_axis = u_axis.clone();
+        v_axis.cross(&Dir3::along(normal.clone()));
+        v_axis.normalize();
+
+        Self { point, normal, u_axis, v_axis, material }
+    }
+
+    // the plane n . p = offset.
+    pub fn from_offset(normal: Normal3, offset: &Number, material: i16) -> Self {
+        let mut normal = normal;
+        normal.normalize();
+        let mut point = Point3::origin();
+        point.offset(&Dir3::along(normal.clone()), offset);
+        Self::new(point, normal, material)
+    }
+}
+
+impl Plane {
+    // coordinates of p along u_axis and v_axis, from self.point.
+    pub fn uv(&self, p: &Point3) -> (Number, Number) {
+        let d = p.minus(&self.point);
+        (d.dot(&self.u_axis), d.dot(&self.v_axis))
+    }
+
+    // as uv, but an error if either leaves 16.16. p - self.point is taken
+    // wide, so it may be further across than 16.16 holds.
+    pub fn try_uv(&self, p: &Point3) -> Result<(Number, Number), ArithError> {
+        let (p, q) = (p.vec3(), self.point.vec3());
+        let xs = [p.x.clone(), p.y.clone(), p.z.clone(), q.x.clone(), q.y.clone(), q.z.clone()];
+        let along = |a: &Dir3| {
+            let a = a.vec3();
+            let mut back = [a.x.clone(), a.y.clone(), a.z.clone()];
+            back.iter_mut().for_each(Number::neg);
+            let [x, y, z] = back;
+            Number::try_dot(&xs, &[a.x.clone(), a.y.clone(), a.z.clone(), x, y, z])
+        };
+        Ok((along(&self.u_axis)?, along(&self.v_axis)?))
+    }
+}
+
+impl Shape for Plane {
+    // t = n . (point - origin) / n . d, with both dot products kept wide and
+    // point - origin taken inside the first, as n . point - n . origin, so
+    // it may be further across than 16.16 holds. a ray parallel to the plane
+    // never meets it, even one lying in it, and one that meets it further
+    // off than 16.16 holds, in the point or in u and v, misses it.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let n = self.normal.vec3();
+        let n = [n.x.clone(), n.y.clone(), n.z.clone()];
+        let mut back = n.clone();
+        back.iter_mut().for_each(Number::neg);
+        let (p, o) = (self.point.vec3(), ray.origin.vec3());
+        let d = ray.direction.vec3();
+
+        let [x, y, z] = back;
+        let t = Number::try_dot_ratio(
+            &[n[0].clone(), n[1].clone(), n[2].clone(), x, y, z],
+            &[p.x.clone(), p.y.clone(), p.z.clone(), o.x.clone(), o.y.clone(), o.z.clone()],
+            &n,
+            &[d.x.clone(), d.y.clone(), d.z.clone()],
+        )
+        .ok()?;
+        if !t_min.lt(&t) || !t.lt(t_max) {
+            return None;
+        }
+
+        let mut point = ray.origin.clone();
+        point.try_offset(&ray.direction, &t).ok()?;
+        let (u, v) = self.try_uv(&point).ok()?;
+        let mut hit = Hit::new(ray, t, self.normal.clone(), self.material);
+        (hit.u, hit.v) = (u, v);
+        Some(hit)
+    }
+}
+
+// which colour of a checkerboard of unit squares (u, v) falls on. scale u and
+// v first for other square sizes.
+pub fn checker(u: &Number, v: &Number) -> bool {
+    // the integer part's low bit, floored, so squares don't double up
+    // across zero.
+    (u.0.parts[2] & 1) != (v.0.parts[2] & 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn floor() -> Plane {
+        Plane::new(Point3::from([0.0, -1.0, 0.0]), Normal3::from([0.0, 1.0, 0.0]), 2)
+    }
+
+    #[test]
+    fn construction() {
+        let p = floor();
+        assert_eq!(p.u_axis, Dir3::from([1.0, 0.0, 0.0]));
+        assert_eq!(p.v_axis, Dir3::from([0.0, 0.0, 1.0]));
+
+        let p = Plane::from_offset(Normal3::from([0.0, 0.0, 2.0]), &Number::from(3), 0);
+        assert_eq!(p.point, Point3::from([0.0, 0.0, 3.0]));
+        assert_eq!(p.normal, Normal3::from([0.0, 0.0, 1.0]));
+        assert_eq!(p.u_axis.dot(&p.v_axis), Number::ZERO);
+        assert_eq!(p.normal.dot(&p.u_axis), Number::ZERO);
+        assert_eq!(p.normal.dot(&p.v_axis), Number::ZERO);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n = Normal3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]);
+            let p = Plane::new(Point3::origin(), n, 0);
+            for d in [p.u_axis.dot(&p.v_axis), p.normal.dot(&p.u_axis), p.normal.dot(&p.v_axis)] {
+                assert!(f64::from(&d).abs() < 4.0 / 65536.0, "{:?}", p);
+            }
+            for a in [&p.u_axis, &p.v_axis] {
+                assert!((f64::from(&a.length()) - 1.0).abs() < 4.0 / 65536.0, "{:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn hit() {
+        let p = floor();
+        let r = Ray::new(Point3::from([2.0, 3.0, -1.5]), Dir3::from([0.0, -2.0, 0.0]));
+        let h = p.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert_eq!(h.point, Point3::from([2.0, -1.0, -1.5]));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 2);
+        assert_eq!((h.u, h.v), (Number::from(2), Number::from(-1.5)));
+
+        // from below
+        let r = Ray::new(Point3::from([0.0, -5.0, 0.0]), Dir3::from([0.0, 1.0, 1.0]));
+        let h = p.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(4));
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, -1.0, 0.0]));
+
+        // behind the origin, and outside (t_min, t_max)
+        let r = Ray::new(Point3::from([0.0, 3.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        assert!(p.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+        let r = Ray::new(Point3::from([0.0, 3.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        assert!(p.hit(&r, &Number::ZERO, &Number::from(4)).is_none());
+        assert!(p.hit(&r, &Number::from(4), &Number::MAX).is_none());
+
+        // point - origin is further across than 16.16 holds
+        let p = Plane::new(Point3::from([20000.0, 0.0, 0.0]), Normal3::from([-1.0, 0.0, 0.0]), 0);
+        let r = Ray::new(Point3::from([-20000.0, 0.0, 0.0]), Dir3::from([4.0, 0.0, 0.0]));
+        let h = p.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(10000));
+        assert_eq!(h.point, Point3::from([20000.0, 0.0, 0.0]));
+        assert!(h.front_face);
+    }
+
+    #[test]
+    fn parallel() {
+        let p = floor();
+        // above, and lying in the plane: no divide by zero, no hit
+        for y in [3.0, -1.0] {
+            let r = Ray::new(Point3::from([0.0, y, 0.0]), Dir3::from([1.0, 0.0, 0.5]));
+            assert!(p.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+        }
+
+        // nearly parallel: the hit is too far off for 16.16
+        let r = Ray::new(Point3::from([0.0, 3.0, 0.0]), Dir3::new(Number::ONE, Number::from(-1.0 / 65536.0), Number::ZERO));
+        assert!(p.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+
+        // but a shallow one in range is found
+        let r = Ray::new(Point3::from([0.0, 3.0, 0.0]), Dir3::new(Number::ONE, Number::from(-1.0 / 1024.0), Number::ZERO));
+        let h = p.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(4096));
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = StdRng::seed_from_u64(41);
+        for _ in 0..1000 {
+            let n = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+            let p = Plane::new(Point3::from([rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0)]), Normal3::from(n), 0);
+            let r = Ray::new(
+                Point3::from([rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0)]),
+                Dir3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]),
+            );
+
+            let f = |v: &Dir3| <[f64; 3]>::from(v);
+            let (n, q, o, d) = (<[f64; 3]>::from(&p.normal), <[f64; 3]>::from(&p.point), <[f64; 3]>::from(&r.origin), f(&r.direction));
+            let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+            let t = dot(n, [q[0] - o[0], q[1] - o[1], q[2] - o[2]]) / dot(n, d);
+
+            // the point and u, v have to fit in 16.16 too; leave out those
+            // too near the edge of it to call
+            let x = [o[0] + t * d[0] - q[0], o[1] + t * d[1] - q[1], o[2] + t * d[2] - q[2]];
+            let (u, v) = (dot(x, f(&p.u_axis)), dot(x, f(&p.v_axis)));
+            let reach = [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2], u, v, t].iter().fold(0.0f64, |m, c| m.max(c.abs()));
+            if (reach - 32768.0).abs() < 1.0 {
+                continue;
+            }
+
+            match p.hit(&r, &Number::ZERO, &Number::MAX) {
+                Some(h) => {
+                    assert!((f64::from(&h.t) - t).abs() <= 1.0 / 65536.0, "got {}, want {}", f64::from(&h.t), t);
+                    let eps = 4.0 / 65536.0 * (1.0 + t);
+                    assert!((f64::from(&h.u) - u).abs() < eps && (f64::from(&h.v) - v).abs() < eps, "got {:?}, want {:?}", (f64::from(&h.u), f64::from(&h.v)), (u, v));
+                }
+                None => assert!(t <= 0.0 || reach > 32768.0, "missed t = {}", t),
+            }
+        }
+    }
+
+    #[test]
+    fn checkerboard() {
+        let c = |u: f64, v: f64| checker(&Number::from(u), &Number::from(v));
+        assert!(!c(0.5, 0.5));
+        assert!(c(1.5, 0.5));
+        assert!(c(0.5, 1.5));
+        assert!(!c(1.5, 1.5));
+        assert!(c(-0.5, 0.5));
+        assert!(!c(-0.5, -0.5));
+        assert!(c(-1.5, -0.5));
+        // squares are whole units: 0 and 1 - ulp match, 1 doesn't
+        assert_eq!(c(0.0, 0.0), c(1.0 - 1.0 / 65536.0, 0.0));
+        assert_ne!(c(0.0, 0.0), c(1.0, 0.0));
+    }
+}
@@ -0,0 +1,548 @@
+use crate::int32::Int32;
+use crate::int64::Int64;
+use crate::number::Number;
+
+// a critical point where the polynomial is this close to zero counts as a
+// (double) root: the curve touches zero there without crossing it.
+const TANGENT: Number = Number(Int32 { parts: [4, 0, 0, 0] });
+
+// Real roots of polynomials, in ascending order. A double root is reported
+// once. Roots outside the 16.16 range are dropped.
+//
+// Coefficients are first scaled by a power of two so the largest is in
+// [2^10, 2^11): the roots don't change, small coefficients gain precision,
+// and large ones leave headroom for the intermediates.
+
+pub fn quadratic(a: &Number, b: &Number, c: &Number) -> Vec<Number> {
+    roots(&[a.clone(), b.clone(), c.clone()])
+}
+
+pub fn cubic(a: &Number, b: &Number, c: &Number, d: &Number) -> Vec<Number> {
+    roots(&[a.clone(), b.clone(), c.clone(), d.clone()])
+}
+
+pub fn quartic(a: &Number, b: &Number, c: &Number, d: &Number, e: &Number) -> Vec<Number> {
+    roots(&[a.clone(), b.clone(), c.clone(), d.clone(), e.clone()])
+}
+
+// coefficients summed exactly in 32.32, shifted together so the largest is
+// in [2^10, 2^11) as above, then rounded to 16.16: wide values that don't
+// fit a Number can still be solved for, and small ones keep their bits.
+pub fn from_wide(p: &[Int64]) -> Vec<Number> {
+    let wide_magnitude = |c: &Int64| {
+        let mut m = c.clone();
+        if m.parts[7] >= 0x80 {
+            m.neg();
+        }
+        m
+    };
+    let mut top = p.iter().map(wide_magnitude).fold(Int64::from(0), |m, c| if m.lt_unsigned(&c) { c } else { m });
+    if top == Int64::from(0) {
+        return vec![Number::ZERO; p.len()];
+    }
+
+    let (low, high) = (Int64::from(1i64 << 42), Int64::from(1i64 << 43));
+    let mut shift = 0;
+    while top.lt_unsigned(&low) {
+        top.shl(1);
+        shift += 1;
+    }
+    while !top.lt_unsigned(&high) {
+        top.shr(1);
+        shift -= 1;
+    }
+
+    p.iter()
+        .map(|c| {
+            let mut m = wide_magnitude(c);
+            if shift > 0 {
+                m.shl(shift);
+            } else {
+                m.shr(-shift);
+            }
+            if c.parts[7] >= 0x80 {
+                m.neg();
+            }
+            Number(Int32 { parts: [m.parts[2], m.parts[3], m.parts[4], m.parts[5]] })
+        })
+        .collect()
+}
+
+// where the line o + t d crosses |p| = r, as (near, far): a circle for
+// two components, a sphere for three. b^2 - 4ac overflows 16.16 a few
+// hundred units off, and cancels badly when it doesn't. instead: tc is where
+// the line passes closest to the center, l the offset from it there, and the
+// half chord sqrt(r^2 - |l|^2) is taken exactly in 64 bits. |l| is at most r
+// for any crossing, so nothing here grows with the distance squared. dot
+// products stay wide until divided, or t would be off by an ulp of d . d
+// times the distance. none if it misses, d is zero or a root leaves 16.16.
+pub fn crossings(o: &[Number], d: &[Number], r: &Number) -> Option<(Number, Number)> {
+    // tc = -(o . d) / (d . d)
+    let dd = Number::sum_of_products(d, d);
+    let mut od = Number::sum_of_products(o, d);
+    od.neg();
+    let tc = Number::try_ratio(&od, &dd).ok()?;
+
+    let mut chord = Int64::product(&r.0, &r.0);
+    for (o, d) in o.iter().zip(d) {
+        let mut l = o.clone();
+        l.mul_add(d, &tc);
+        chord.sub(&Int64::product(&l.0, &l.0));
+    }
+    if chord.parts[7] >= 0x80 {
+        return None;
+    }
+    chord.sqrt_rem();
+
+    // the half chord in units of t is chord / |d|, with |d| taken to k extra
+    // bits so its rounding doesn't show.
+    let (mut len, mut k) = (dd, 0);
+    while len.parts[7] < 0x10 {
+        len.shl(2);
+        k += 1;
+    }
+    len.sqrt_rem();
+    chord.shl(k);
+    let dt = Number::try_ratio(&chord, &len).ok()?;
+
+    let (mut near, mut far) = (tc.clone(), tc);
+    near.try_sub(&dt).ok()?;
+    far.try_add(&dt).ok()?;
+    Some((near, far))
+}
+
+// p[0] t^n + p[1] t^(n - 1) + ... + p[n].
+fn roots(p: &[Number]) -> Vec<Number> {
+    let first = p.iter().position(|c| *c != Number::ZERO).unwrap_or(p.len());
+    let p = normalized(&p[first..]);
+    match p.len() {
+        0 | 1 => Vec::new(),
+        2 => linear(&p[0], &p[1]),
+        3 => stable_quadratic(&p[0], &p[1], &p[2]),
+        _ => bracketed(&p),
+    }
+}
+
+fn normalized(p: &[Number]) -> Vec<Number> {
+    let mut p = p.to_vec();
+    if p.is_empty() {
+        return p;
+    }
+
+    let top = Int32::from(1 << 27);
+    let bottom = Int32::from(1 << 26);
+    let largest = |p: &[Number]| {
+        p.iter().fold(Int32::from(0), |m, c| {
+            let mut a = c.0.clone();
+            if a.parts[3] >= 0x80 {
+                a.neg();
+            }
+            // MIN stays negative after neg, and is as large as it gets.
+            if a.parts[3] >= 0x80 || m.lt_unsigned(&a) { a } else { m }
+        })
+    };
+
+    while !largest(&p).lt_unsigned(&top) {
+        for c in p.iter_mut() {
+            c.0.sar(1);
+        }
+    }
+    while largest(&p).lt_unsigned(&bottom) {
+        for c in p.iter_mut() {
+            c.0.shl(1);
+        }
+    }
+    p
+}
+
+fn linear(a: &Number, b: &Number) -> Vec<Number> {
+    let mut t = b.clone();
+    t.neg();
+    match t.try_div(a) {
+        Ok(()) => vec![t],
+        Err(_) => Vec::new(),
+    }
+}
+
+// with q = -(b + sign(b) sqrt(b^2 - 4ac)) / 2 the roots are q / a and c / q;
+// neither subtracts nearly equal numbers, unlike (-b +- sqrt(...)) / 2a when
+// b^2 >> 4ac. the discriminant is taken exactly in 64 bits.
+fn stable_quadratic(a: &Number, b: &Number, c: &Number) -> Vec<Number> {
+    // (b^2 - 4ac) / 4, in 32.32
+    let mut disc = Int64::product(&b.0, &b.0);
+    disc.shr(2);
+    disc.sub(&Int64::product(&a.0, &c.0));
+    if disc.parts[7] >= 0x80 {
+        return Vec::new();
+    }
+    let double = disc.parts == [0; 8];
+
+    // sqrt(b^2 - 4ac) / 2, back in 16.16
+    disc.sqrt_rem();
+    let mut q = Number(Int32 { parts: [disc.parts[0], disc.parts[1], disc.parts[2], disc.parts[3]] });
+    if b.is_negative() {
+        q.neg();
+    }
+    let mut half_b = b.clone();
+    half_b.mul(&Number::HALF);
+    q.add(&half_b);
+    q.neg();
+
+    if q == Number::ZERO {
+        // b = 0 and c = 0
+        return vec![Number::ZERO];
+    }
+
+    let mut ts = Vec::new();
+    let mut t = q.clone();
+    if t.try_div(a).is_ok() {
+        ts.push(t);
+    }
+    let mut t = c.clone();
+    if (ts.is_empty() || !double) && t.try_div(&q).is_ok() {
+        ts.push(t);
+    }
+    if ts.len() == 2 && ts[1].lt(&ts[0]) {
+        ts.swap(0, 1);
+    }
+    ts
+}
+
+// the roots of the derivative split the line into stretches where p is
+// monotonic; each holds at most one root, found by bisection when p changes
+// sign across it. the outer ends are at the Cauchy bound, beyond which p
+// has no roots. a root is only as good as the evaluation of p around it, so
+// clustered roots far from zero come out least accurate.
+fn bracketed(p: &[Number]) -> Vec<Number> {
+    let n = p.len() - 1;
+    let derivative: Vec<Number> = p[..n].iter().enumerate().map(|(i, c)| c.saturating_mul(&Number::from((n - i) as i16))).collect();
+
+    let bound = cauchy_bound(p);
+    let mut lo = bound.clone();
+    lo.neg();
+
+    let mut points = vec![lo.clone()];
+    points.extend(roots(&derivative).into_iter().filter(|t| lo.lt(t) && t.lt(&bound)));
+    points.push(bound);
+
+    let mut found: Vec<Number> = Vec::new();
+    let mut prev: Option<(Number, Number)> = None;
+    for (i, x) in points.iter().enumerate() {
+        let v = eval(p, x);
+        if let Some((l, vl)) = prev {
+            if !tiny(&vl) && !tiny(&v) && vl.is_negative() != v.is_negative() {
+                found.push(bisect(p, l, x.clone(), vl.is_negative()));
+            }
+        }
+        if tiny(&v) && i != 0 && i != points.len() - 1 && found.last() != Some(x) {
+            found.push(x.clone());
+        }
+        prev = Some((x.clone(), v));
+    }
+    found
+}
+
+// 1 + max |p[i] / p[0]|, held at Number::MAX.
+fn cauchy_bound(p: &[Number]) -> Number {
+    let mut bound = Number::ZERO;
+    for c in &p[1..] {
+        let mut r = c.clone();
+        if r.try_div(&p[0]).is_err() {
+            return Number::MAX;
+        }
+        if r.is_negative() {
+            r.neg();
+        }
+        if bound.lt(&r) {
+            bound = r;
+        }
+    }
+    if bound.try_add(&Number::ONE).is_err() {
+        return Number::MAX;
+    }
+    bound
+}
+
+// p(lo) has sign lo_negative, p(hi) the other one.
+fn bisect(p: &[Number], lo: Number, hi: Number, lo_negative: bool) -> Number {
+    let (mut lo, mut hi) = (lo, hi);
+    loop {
+        let mid = Number::dot(&[lo.clone(), hi.clone()], &[Number::HALF, Number::HALF]);
+        if mid == lo {
+            break;
+        }
+
+        let v = eval(p, &mid);
+        if v == Number::ZERO {
+            return mid;
+        }
+        if v.is_negative() == lo_negative {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    if magnitude(&eval(p, &hi)).lt(&magnitude(&eval(p, &lo))) { hi } else { lo }
+}
+
+// Horner's rule, one rounding per step. a step that leaves the 16.16 range
+// is held at MIN or MAX, which keeps the sign: only the sign matters that far
+// from the roots.
+fn eval(p: &[Number], t: &Number) -> Number {
+    let mut r = p[0].clone();
+    for c in &p[1..] {
+        r = c.saturating_mul_add(&r, t);
+    }
+    r
+}
+
+fn tiny(n: &Number) -> bool {
+    !TANGENT.lt(&magnitude(n))
+}
+
+fn magnitude(n: &Number) -> Number {
+    let mut m = n.clone();
+    if m.is_negative() {
+        if m == Number::MIN {
+            return Number::MAX;
+        }
+        m.neg();
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn numbers(xs: &[f64]) -> Vec<Number> {
+        xs.iter().map(|x| Number::from(*x)).collect()
+    }
+
+    fn floats(ns: &[Number]) -> Vec<f64> {
+        ns.iter().map(f64::from).collect()
+    }
+
+    // coefficients of prod (t - r_i), rounded to 16.16.
+    fn from_roots(rs: &[f64]) -> Vec<Number> {
+        let mut p = vec![1.0];
+        for r in rs {
+            let mut next = vec![0.0; p.len() + 1];
+            for (i, c) in p.iter().enumerate() {
+                next[i] += c;
+                next[i + 1] -= c * r;
+            }
+            p = next;
+        }
+        numbers(&p)
+    }
+
+    // roots of the rounded coefficients, by f64 bisection between sign
+    // changes on a fine grid.
+    fn reference(p: &[Number], lo: f64, hi: f64) -> Vec<f64> {
+        let p = floats(p);
+        let eval = |t: f64| p.iter().fold(0.0, |r, c| r * t + c);
+        let steps = 50000;
+        let mut roots = Vec::new();
+        let mut prev = lo;
+        for i in 1..=steps {
+            let x = lo + (hi - lo) * i as f64 / steps as f64;
+            if eval(prev).signum() != eval(x).signum() {
+                let (mut a, mut b) = (prev, x);
+                for _ in 0..100 {
+                    let m = 0.5 * (a + b);
+                    if eval(m).signum() == eval(a).signum() {
+                        a = m;
+                    } else {
+                        b = m;
+                    }
+                }
+                roots.push(a);
+            }
+            prev = x;
+        }
+        roots
+    }
+
+    fn assert_roots(got: &[Number], want: &[f64], eps: f64) {
+        let got = floats(got);
+        assert_eq!(got.len(), want.len(), "got {:?}, want {:?}", got, want);
+        for (g, w) in got.iter().zip(want) {
+            assert!((g - w).abs() <= eps * w.abs().max(1.0), "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    #[test]
+    fn quadratic_exact() {
+        let r = |a: f64, b: f64, c: f64| floats(&quadratic(&Number::from(a), &Number::from(b), &Number::from(c)));
+        assert_eq!(r(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+        assert_eq!(r(2.0, 0.0, -8.0), vec![-2.0, 2.0]);
+        assert_eq!(r(1.0, -2.0, 1.0), vec![1.0]);
+        assert_eq!(r(1.0, 0.0, 1.0), Vec::<f64>::new());
+        assert_eq!(r(1.0, 0.0, 0.0), vec![0.0]);
+        assert_eq!(r(0.0, 2.0, -1.0), vec![0.5]);
+        assert_eq!(r(0.0, 0.0, 1.0), Vec::<f64>::new());
+        assert_eq!(r(-1.0, 5.0, 0.0), vec![0.0, 5.0]);
+    }
+
+    // b^2 >> 4ac: the textbook formula gets the small root from
+    // -b + sqrt(b^2 - 4ac), which cancels to nothing in 16.16.
+    #[test]
+    fn quadratic_cancellation() {
+        for (b, c) in [(-1000.0, 0.001), (-20000.0, 1.0), (30000.0, 1.0), (-0.5, 0.00003)] {
+            let a = 1.0;
+            let got = quadratic(&Number::from(a), &Number::from(b), &Number::from(c));
+            let (b, c) = (f64::from(&Number::from(b)), f64::from(&Number::from(c)));
+            let q = -0.5 * (b + b.signum() * (b * b - 4.0 * a * c).sqrt());
+            let mut want = vec![q / a, c / q];
+            want.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            let got = floats(&got);
+            assert_eq!(got.len(), 2, "{} {}: {:?}", b, c, got);
+            for (g, w) in got.iter().zip(&want) {
+                assert!((g - w).abs() <= 2.0 / 65536.0 * w.abs().max(1.0), "{} {}: got {:?}, want {:?}", b, c, got, want);
+            }
+            // the small root stays relatively accurate.
+            let small = if got[0].abs() < got[1].abs() { got[0] } else { got[1] };
+            let small_want = if want[0].abs() < want[1].abs() { want[0] } else { want[1] };
+            assert!((small - small_want).abs() <= 1.0 / 65536.0, "{} {}: small {} vs {}", b, c, small, small_want);
+        }
+    }
+
+    // (t - 1)^2 - e: roots 1 +- sqrt(e). the discriminant is 4e, which the
+    // exact wide discriminant keeps down to e = 1 ulp.
+    #[test]
+    fn quadratic_near_tangent() {
+        for k in 0..16 {
+            let e = 2f64.powi(-16 + k);
+            let got = floats(&quadratic(&Number::ONE, &Number::from(-2), &Number::from(1.0 - e)));
+            let s = e.sqrt();
+            assert_eq!(got.len(), 2, "e = {}: {:?}", e, got);
+            assert!((got[0] - (1.0 - s)).abs() <= 1.0 / 65536.0 && (got[1] - (1.0 + s)).abs() <= 1.0 / 65536.0, "e = {}: {:?}", e, got);
+        }
+
+        let got = floats(&quadratic(&Number::ONE, &Number::from(-2), &Number::from(1.0 + 1.0 / 65536.0)));
+        assert!(got.is_empty(), "{:?}", got);
+    }
+
+    #[test]
+    fn quadratic_random() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let scale = 2f64.powi(rng.gen_range(-4..8));
+            let p = numbers(&[rng.gen_range(-scale..scale), rng.gen_range(-scale..scale), rng.gen_range(-scale..scale)]);
+            let f = floats(&p);
+            let got = quadratic(&p[0], &p[1], &p[2]);
+
+            let d = f[1] * f[1] - 4.0 * f[0] * f[2];
+            if d < 0.0 {
+                assert!(got.is_empty(), "{:?}: {:?}", f, floats(&got));
+                continue;
+            }
+            let q = -0.5 * (f[1] + f[1].signum() * d.sqrt());
+            let mut want: Vec<f64> = [q / f[0], f[2] / q].into_iter().filter(|t| t.abs() < 32767.0).collect();
+            want.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            if d == 0.0 {
+                want.truncate(1);
+            }
+            assert_roots(&got, &want, 8.0 / 65536.0);
+        }
+    }
+
+    #[test]
+    fn cubic_known() {
+        assert_roots(&roots(&from_roots(&[-2.0, 0.5, 3.0])), &[-2.0, 0.5, 3.0], 2.0 / 65536.0);
+        assert_roots(&roots(&from_roots(&[1.0, 2.0, 3.0])), &[1.0, 2.0, 3.0], 2.0 / 65536.0);
+        assert_roots(&cubic(&Number::ONE, &Number::ZERO, &Number::ZERO, &Number::from(-8)), &[2.0], 2.0 / 65536.0);
+        assert_roots(&cubic(&Number::ONE, &Number::ZERO, &Number::ONE, &Number::ZERO), &[0.0], 2.0 / 65536.0);
+
+        // a leading zero falls back to the quadratic
+        assert_roots(&cubic(&Number::ZERO, &Number::ONE, &Number::from(-3), &Number::from(2)), &[1.0, 2.0], 0.0);
+
+        // a double root touches zero at a critical point
+        assert_roots(&roots(&from_roots(&[1.0, 1.0, -2.0])), &[-2.0, 1.0], 2.0 / 65536.0);
+        // and a triple root is one crossing, found by bisection
+        assert_roots(&roots(&from_roots(&[0.5, 0.5, 0.5])), &[0.5], 1.0 / 256.0);
+    }
+
+    #[test]
+    fn quartic_known() {
+        assert_roots(&roots(&from_roots(&[-3.0, -1.0, 1.0, 3.0])), &[-3.0, -1.0, 1.0, 3.0], 2.0 / 65536.0);
+        assert_roots(&roots(&from_roots(&[0.25, 0.5, 4.0, 8.0])), &[0.25, 0.5, 4.0, 8.0], 2.0 / 65536.0);
+        assert_roots(&quartic(&Number::ONE, &Number::ZERO, &Number::ZERO, &Number::ZERO, &Number::ONE), &[], 0.0);
+        assert_roots(&quartic(&Number::ONE, &Number::ZERO, &Number::from(-5), &Number::ZERO, &Number::from(4)), &[-2.0, -1.0, 1.0, 2.0], 2.0 / 65536.0);
+
+        // two double roots: a ray grazing a torus on both sides.
+        assert_roots(&roots(&from_roots(&[-1.0, -1.0, 2.0, 2.0])), &[-1.0, 2.0], 2.0 / 65536.0);
+    }
+
+    #[test]
+    fn wide_coefficients() {
+        // (t - 1)(t - 3) scaled by 2^20 and by 2^-20: out of 16.16 range,
+        // and below its resolution.
+        let wide = |c: i64, shift: i32| Int64::from(c << (32 + shift));
+        for shift in [20, -20] {
+            let p = from_wide(&[wide(1, shift), wide(-4, shift), wide(3, shift)]);
+            assert_eq!(p, numbers(&[256.0, -1024.0, 768.0]));
+            assert_roots(&quadratic(&p[0], &p[1], &p[2]), &[1.0, 3.0], 0.0);
+        }
+        assert_eq!(from_wide(&[Int64::from(0), Int64::from(0)]), vec![Number::ZERO; 2]);
+    }
+
+    // roots close together, like a ray nearly tangent to a torus tube.
+    #[test]
+    fn near_degenerate() {
+        for k in 2..8 {
+            let e = 2f64.powi(-k);
+            let p = from_roots(&[1.0 - e, 1.0 + e, 3.0]);
+            assert_roots(&roots(&p), &reference(&p, -10.0, 10.0), 4.0 / 65536.0);
+
+            let p = from_roots(&[-2.0, 1.0 - e, 1.0 + e, 3.0]);
+            assert_roots(&roots(&p), &reference(&p, -10.0, 10.0), 4.0 / 65536.0);
+        }
+
+        // leading coefficient nearly zero: one root flies off to large t
+        // and the others stay put.
+        let p = numbers(&[1.0 / 4096.0, 1.0, -3.0, 2.0]);
+        let want = reference(&p, -8192.0, 8192.0);
+        assert_roots(&roots(&p), &want, 8.0 / 65536.0);
+    }
+
+    // how far from a simple root r the 16.16 evaluation can tell p apart
+    // from zero: Horner rounds once per step, an ulp at the scale the
+    // coefficients were normalized to, and step i's rounding is carried
+    // through |r|^(n - i). dividing by |p'(r)| turns that into distance.
+    fn resolution(p: &[Number], r: f64) -> f64 {
+        let f = floats(p);
+        let n = f.len() - 1;
+        let largest = f.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+        let ulp = largest.max(1024.0) / 1024.0 / 65536.0;
+        let carried: f64 = (0..n).map(|i| r.abs().powi(i as i32)).sum();
+        let slope: f64 = (0..n).map(|i| f[i] * (n - i) as f64 * r.powi((n - i - 1) as i32)).sum();
+        2.0 * ulp * carried / slope.abs()
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n = rng.gen_range(3..=4);
+            // well separated roots in [-8, 8]
+            let mut rs: Vec<f64> = (0..n).map(|_| rng.gen_range(-8.0..8.0)).collect();
+            rs.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            if rs.windows(2).any(|w| w[1] - w[0] < 0.25) {
+                continue;
+            }
+            let p = from_roots(&rs);
+            let want = reference(&p, -40.0, 40.0);
+            let got = floats(&roots(&p));
+            assert_eq!(got.len(), want.len(), "got {:?}, want {:?}", got, want);
+            for (g, w) in got.iter().zip(&want) {
+                let eps = 2.0 / 65536.0 + resolution(&p, *w);
+                assert!((g - w).abs() <= eps, "got {:?}, want {:?}, eps {}", got, want, eps);
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::error::{raise, ArithError};
+use crate::int32::Int32;
+use crate::number::Number;
+use crate::transform::{Matrix, Transform};
+use crate::trig::{atan2, sin_cos};
+use crate::vec3::Vec3;
+
+// past this cosine slerp falls back to a normalized lerp; sin(theta) is
+// too small to divide by. 0.9995 in 16.16.
+const NLERP_THRESHOLD: Number = Number(Int32 { parts: [0xDF, 0xFF, 0, 0] });
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quat {
+    pub w: Number,
+    pub x: Number,
+    pub y: Number,
+    pub z: Number,
+}
+
+impl Quat {
+    pub fn identity() -> Self {
+        Self { w: Number::ONE, x: Number::ZERO, y: Number::ZERO, z: Number::ZERO }
+    }
+
+    pub fn from_axis_angle(axis: &Vec3, angle: &Number) -> Self {
+        let mut half = angle.clone();
+        half.mul(&Number::HALF);
+        let (sin, cos) = sin_cos(&half);
+
+        let mut k = axis.clone();
+        k.normalize();
+        k.scale(&sin);
+        Self { w: cos, x: k.x, y: k.y, z: k.z }
+    }
+}
+
+impl Quat {
+    // self = self * other, i.e. other's rotation is applied first.
+    pub fn mul(&mut self, other: &Self) {
+        let (a, b) = (self.clone(), other);
+        let n = |n: &Number| {
+            let mut n = n.clone();
+            n.neg();
+            n
+        };
+
+        self.w = Number::dot(&[a.w.clone(), n(&a.x), n(&a.y), n(&a.z)], &[b.w.clone(), b.x.clone(), b.y.clone(), b.z.clone()]);
+        self.x = Number::dot(&[a.w.clone(), a.x.clone(), a.y.clone(), n(&a.z)], &[b.x.clone(), b.w.clone(), b.z.clone(), b.y.clone()]);
+        self.y = Number::dot(&[a.w.clone(), n(&a.x), a.y.clone(), a.z.clone()], &[b.y.clone(), b.z.clone(), b.w.clone(), b.x.clone()]);
+        self.z = Number::dot(&[a.w.clone(), a.x.clone(), n(&a.y), a.z.clone()], &[b.z.clone(), b.y.clone(), b.x.clone(), b.w.clone()]);
+    }
+
+    pub fn conjugate(&mut self) {
+        self.x.neg();
+        self.y.neg();
+        self.z.neg();
+    }
+
+    pub fn dot(&self, other: &Self) -> Number {
+        Number::dot(
+            &[self.w.clone(), self.x.clone(), self.y.clone(), self.z.clone()],
+            &[other.w.clone(), other.x.clone(), other.y.clone(), other.z.clone()],
+        )
+    }
+
+    // rotations only stay rotations at unit length; renormalize after
+    // every few muls.
+    pub fn normalize(&mut self) {
+        let mut l = self.dot(self);
+        l.sqrt();
+        self.w.div(&l);
+        self.x.div(&l);
+        self.y.div(&l);
+        self.z.div(&l);
+    }
+}
+
+impl Quat {
+    // spherical interpolation from self (t = 0) to other (t = 1) along the
+    // shorter arc.
+    pub fn slerp(&mut self, other: &Self, t: &Number) {
+        let mut b = other.clone();
+        let mut cos = self.dot(&b);
+        if cos.is_negative() {
+            b.w.neg();
+            b.conjugate();
+            cos.neg();
+        }
+
+        let (wa, wb) = if NLERP_THRESHOLD.lt(&cos) {
+            let mut wa = Number::ONE;
+            wa.sub(t);
+            (wa, t.clone())
+        } else {
+            // sin = sqrt(1 - cos^2), theta = atan2(sin, cos)
+            let mut minus_cos = cos.clone();
+            minus_cos.neg();
+            let mut sin = Number::ONE;
+            sin.mul_add(&cos, &minus_cos);
+            sin.sqrt();
+            let theta = atan2(&sin, &cos);
+
+            let mut ta = Number::ONE;
+            ta.sub(t);
+            ta.mul(&theta);
+            let mut tb = t.clone();
+            tb.mul(&theta);
+
+            let (mut wa, _) = sin_cos(&ta);
+            let (mut wb, _) = sin_cos(&tb);
+            wa.div(&sin);
+            wb.div(&sin);
+            (wa, wb)
+        };
+
+        let blend = |a: &Number, b: &Number| Number::dot(&[a.clone(), b.clone()], &[wa.clone(), wb.clone()]);
+        self.w = blend(&self.w, &b.w);
+        self.x = blend(&self.x, &b.x);
+        self.y = blend(&self.y, &b.y);
+        self.z = blend(&self.z, &b.z);
+        self.normalize();
+    }
+}
+
+impl Quat {
+    pub fn to_transform(&self) -> Transform {
+        let Self { w, x, y, z } = self;
+        let n = |n: &Number| {
+            let mut n = n.clone();
+            n.neg();
+            n
+        };
+        // 2 (a b + c d)
+        let twice = |a: &Number, b: &Number, c: &Number, d: &Number| {
+            let mut r = Number::dot(&[a.clone(), c.clone()], &[b.clone(), d.clone()]);
+            r.add(&r.clone());
+            r
+        };
+        // 1 - 2 (a a + b b)
+        let diagonal = |a: &Number, b: &Number| {
+            let mut r = Number::ONE;
+            r.sub(&twice(a, a, b, b));
+            r
+        };
+
+        let m: Matrix = [
+            [diagonal(y, z), twice(x, y, &n(w), z), twice(x, z, w, y), Number::ZERO],
+            [twice(x, y, w, z), diagonal(x, z), twice(y, z, &n(w), x), Number::ZERO],
+            [twice(x, z, &n(w), y), twice(y, z, w, x), diagonal(x, y), Number::ZERO],
+        ];
+        Transform::from_rotation(m)
+    }
+
+    // q v q* / |q|^2, as v + (2w (q x v) + 2 q x (q x v)) / |q|^2, without
+    // building a matrix. a quaternion a few ulp off unit would otherwise
+    // scale v by its norm; only q x v is rounded before the wide sums, and
+    // each is divided by the exact norm once.
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        let Self { w, x, y, z } = self;
+        let q = [w.clone(), x.clone(), y.clone(), z.clone()];
+        let norm = Number::sum_of_products(&q, &q);
+        let n = |n: &Number| {
+            let mut n = n.clone();
+            n.neg();
+            n
+        };
+
+        let mut t = Vec3::new(x.clone(), y.clone(), z.clone());
+        t.cross(v);
+        let rows = [
+            ([w.clone(), y.clone(), n(z)], [t.x.clone(), t.z.clone(), t.y.clone()]),
+            ([w.clone(), z.clone(), n(x)], [t.y.clone(), t.x.clone(), t.z.clone()]),
+            ([w.clone(), x.clone(), n(y)], [t.z.clone(), t.y.clone(), t.x.clone()]),
+        ];
+        let [dx, dy, dz] = rows.map(|(a, b)| {
+            let mut c = Number::sum_of_products(&a, &b);
+            c.shl(1);
+            Number::try_ratio(&c, &norm).unwrap_or_else(|err| {
+                raise(err);
+                match err {
+                    ArithError::DivideByZero => Number::ZERO,
+                    _ if c.parts[7] >= 0x80 => Number::MIN,
+                    _ => Number::MAX,
+                }
+            })
+        });
+
+        let mut r = v.clone();
+        r.add(&Vec3::new(dx, dy, dz));
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    const EPS: f64 = 8.0 / 65536.0;
+
+    fn assert_close(got: &Vec3, want: [f64; 3], eps: f64) {
+        let got = <[f64; 3]>::from(got);
+        for i in 0..3 {
+            assert!((got[i] - want[i]).abs() <= eps, "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    fn to_f64(q: &Quat) -> [f64; 4] {
+        [f64::from(&q.w), f64::from(&q.x), f64::from(&q.y), f64::from(&q.z)]
+    }
+
+    fn reference_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+        [
+            a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+            a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+            a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+            a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+        ]
+    }
+
+    fn random_quat() -> Quat {
+        let mut rng = rand::thread_rng();
+        let axis = Vec3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(0.1..1.0)]);
+        Quat::from_axis_angle(&axis, &Number::from(rng.gen_range(-3.0..3.0)))
+    }
+
+    #[test]
+    fn axis_angle_matches_transform() {
+        for _ in 0..50 {
+            let mut rng = rand::thread_rng();
+            let axis = Vec3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(0.1..1.0)]);
+            let angle = Number::from(rng.gen_range(-3.0..3.0));
+            let q = Quat::from_axis_angle(&axis, &angle);
+            let t = Transform::rotate(&axis, &angle);
+
+            let v = Vec3::from([rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0)]);
+            let want = <[f64; 3]>::from(&t.transform_point(&v));
+            assert_close(&q.rotate(&v), want, 16.0 * EPS);
+            assert_close(&q.to_transform().transform_point(&v), want, 16.0 * EPS);
+        }
+    }
+
+    #[test]
+    fn mul() {
+        for _ in 0..100 {
+            let (a, b) = (random_quat(), random_quat());
+            let mut q = a.clone();
+            q.mul(&b);
+            let want = reference_mul(to_f64(&a), to_f64(&b));
+            let got = to_f64(&q);
+            for i in 0..4 {
+                assert!((got[i] - want[i]).abs() < 2.0 / 65536.0, "got {:?}, want {:?}", got, want);
+            }
+
+            // b first, then a
+            let v = Vec3::from([1.0, 2.0, 3.0]);
+            assert_close(&q.rotate(&v), <[f64; 3]>::from(&a.rotate(&b.rotate(&v))), 4.0 * EPS);
+        }
+    }
+
+    #[test]
+    fn conjugate_undoes() {
+        let q = random_quat();
+        let mut inv = q.clone();
+        inv.conjugate();
+        let v = Vec3::from([4.0, -2.0, 7.0]);
+        assert_close(&inv.rotate(&q.rotate(&v)), [4.0, -2.0, 7.0], 2.0 * EPS);
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quat::identity();
+        let axis = Vec3::from([0.0, 0.0, 1.0]);
+        let b = Quat::from_axis_angle(&axis, &Number::from(2.0));
+
+        for (t, want) in [(0.0, 0.0), (0.25, 0.5), (0.5, 1.0), (1.0, 2.0)] {
+            let mut q = a.clone();
+            q.slerp(&b, &Number::from(t));
+            let w = Quat::from_axis_angle(&axis, &Number::from(want));
+            let (got, want) = (to_f64(&q), to_f64(&w));
+            for i in 0..4 {
+                assert!((got[i] - want[i]).abs() < EPS, "t = {}: got {:?}, want {:?}", t, got, want);
+            }
+        }
+
+        // the other sign of the same rotation still takes the short way.
+        let mut far = b.clone();
+        far.w.neg();
+        far.conjugate();
+        let mut q = a.clone();
+        q.slerp(&far, &Number::from(0.5));
+        let want = to_f64(&Quat::from_axis_angle(&axis, &Number::ONE));
+        let got = to_f64(&q);
+        for i in 0..4 {
+            assert!((got[i] - want[i]).abs() < EPS, "got {:?}, want {:?}", got, want);
+        }
+
+        // nearly equal ends fall back to nlerp.
+        let c = Quat::from_axis_angle(&axis, &Number::from(0.01));
+        let mut q = a.clone();
+        q.slerp(&c, &Number::from(0.5));
+        let want = to_f64(&Quat::from_axis_angle(&axis, &Number::from(0.005)));
+        let got = to_f64(&q);
+        for i in 0..4 {
+            assert!((got[i] - want[i]).abs() < EPS, "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    // a camera turning by a small step 5000 times, renormalized each time,
+    // against the same chain in f64.
+    #[test]
+    fn drift() {
+        let axis = Vec3::from([0.3, 1.0, -0.2]);
+        let step = Quat::from_axis_angle(&axis, &Number::from(0.01));
+        let step_f64 = to_f64(&step);
+
+        let mut q = Quat::identity();
+        let mut q_f64 = [1.0, 0.0, 0.0, 0.0];
+        for _ in 0..5000 {
+            q.mul(&step);
+            q.normalize();
+            q_f64 = reference_mul(q_f64, step_f64);
+        }
+
+        let norm = f64::from(&q.dot(&q));
+        assert!((norm - 1.0).abs() < 4.0 / 65536.0, "norm {}", norm);
+
+        let n = q_f64.iter().map(|c| c * c).sum::<f64>().sqrt();
+        let got = to_f64(&q);
+        let cos: f64 = (0..4).map(|i| got[i] * q_f64[i] / n).sum();
+        let angle = 2.0 * cos.abs().min(1.0).acos();
+        // the flooring in mul and normalize leaves a small bias each step:
+        // about 0.02 rad of the 50 turned.
+        assert!(angle < 1.0 / 32.0, "off by {} rad", angle);
+
+        // columns of the quaternion's matrix stay orthonormal.
+        let r = q.to_transform();
+        for i in 0..3 {
+            for j in 0..3 {
+                let d: f64 = (0..3).map(|k| f64::from(&r.m[k][i]) * f64::from(&r.m[k][j])).sum();
+                let want = if i == j { 1.0 } else { 0.0 };
+                assert!((d - want).abs() < 16.0 / 65536.0, "columns {} {}: {}", i, j, d);
+            }
+        }
+    }
+}
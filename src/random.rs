@@ -0,0 +1,178 @@
+use crate::error::{raise, ArithError};
+use crate::int32::Int32;
+use crate::number::Number;
+
+// xorshift32 (Marsaglia, 2003). Only shifts and xors on Int32, so it ports to
+// Jack as is and gives the same sequence on both sides for the same seed.
+#[derive(Debug)]
+pub struct Random {
+    state: Int32,
+}
+
+impl Random {
+    pub fn new(seed: &Int32) -> Self {
+        let mut r = Self { state: Int32::from(0) };
+        r.seed(seed);
+        r
+    }
+
+    // xorshift never leaves zero, so a zero seed is swapped for a fixed one.
+    pub fn seed(&mut self, seed: &Int32) {
+        self.state = if seed.parts == [0; 4] {
+            Int32::from(0x2545F491)
+        } else {
+            Int32 { parts: seed.parts }
+        };
+    }
+}
+
+impl Random {
+    pub fn next_int(&mut self) -> Int32 {
+        let mut t = Int32 { parts: self.state.parts };
+        t.shl(13);
+        self.state.xor(&t);
+
+        let mut t = Int32 { parts: self.state.parts };
+        t.shr(17);
+        self.state.xor(&t);
+
+        let mut t = Int32 { parts: self.state.parts };
+        t.shl(5);
+        self.state.xor(&t);
+
+        Int32 { parts: self.state.parts }
+    }
+
+    // uniform in [0, 1), from the top 16 bits (the low ones are the weakest).
+    pub fn next_number(&mut self) -> Number {
+        let n = self.next_int();
+        Number(Int32 { parts: [n.parts[2], n.parts[3], 0, 0] })
+    }
+
+    // uniform in [lo, hi). hi - lo must be positive and fit in an i32,
+    // otherwise it's reported as OutOfRange and lo is returned.
+    pub fn next_range(&mut self, lo: &Int32, hi: &Int32) -> Int32 {
+        let mut span = Int32 { parts: hi.parts };
+        span.sub(lo);
+        if span.parts[3] >= 0x80 || !lo.lt(hi) {
+            raise(ArithError::OutOfRange);
+            return Int32 { parts: lo.parts };
+        }
+
+        // span * u >> 16, which is what Number::mul does on the raw bits.
+        let mut offset = Number(span);
+        offset.mul(&self.next_number());
+
+        let mut r = Int32 { parts: lo.parts };
+        r.add(&offset.0);
+        r
+    }
+
+    // uniform in [lo, hi).
+    pub fn next_number_range(&mut self, lo: &Number, hi: &Number) -> Number {
+        let mut r = Number(Int32 { parts: hi.0.parts });
+        r.sub(lo);
+        r.mul(&self.next_number());
+        r.add(lo);
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::error::{set_policy, take_error, Policy};
+
+    use super::*;
+
+    struct Reference(u32);
+
+    impl Reference {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+    }
+
+    #[test]
+    fn next_int() {
+        let mut r = Random::new(&Int32::from(1));
+        assert_eq!(r.next_int(), 270369.into());
+        assert_eq!(r.next_int(), 67634689.into());
+
+        for _ in 0..10 {
+            let seed = rand::random::<u32>() | 1;
+            let mut r = Random::new(&Int32::from(seed as i32));
+            let mut want = Reference(seed);
+            for i in 0..1000 {
+                assert_eq!(r.next_int(), (want.next() as i32).into(), "seed {}, step {}", seed, i);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_seed() {
+        let mut r = Random::new(&Int32::from(0));
+        assert_ne!(r.next_int(), 0.into());
+    }
+
+    #[test]
+    fn reseed() {
+        let mut r = Random::new(&Int32::from(42));
+        let first = r.next_int();
+        r.next_int();
+        r.seed(&Int32::from(42));
+        assert_eq!(r.next_int(), first);
+    }
+
+    #[test]
+    fn next_number() {
+        let seed = rand::random::<u32>() | 1;
+        let mut r = Random::new(&Int32::from(seed as i32));
+        let mut want = Reference(seed);
+        for _ in 0..1000 {
+            assert_eq!(r.next_number(), Number(Int32::from((want.next() >> 16) as i32)));
+        }
+    }
+
+    #[test]
+    fn next_range() {
+        let seed = rand::random::<u32>() | 1;
+        let mut r = Random::new(&Int32::from(seed as i32));
+        let mut want = Reference(seed);
+        for _ in 0..1000 {
+            let lo = rand::thread_rng().gen_range(-1_000_000..1_000_000);
+            let hi = lo + rand::thread_rng().gen_range(1..i32::MAX - 1_000_000);
+            let got = i32::from(r.next_range(&Int32::from(lo), &Int32::from(hi)));
+            let offset = ((hi - lo) as i64 * (want.next() >> 16) as i64) >> 16;
+            assert_eq!(got as i64, lo as i64 + offset);
+            assert!(lo <= got && got < hi, "{} not in [{}, {})", got, lo, hi);
+        }
+    }
+
+    #[test]
+    fn next_range_empty() {
+        set_policy(Policy::Record);
+
+        let mut r = Random::new(&Int32::from(1));
+        assert_eq!(r.next_range(&Int32::from(5), &Int32::from(5)), 5.into());
+        assert_eq!(take_error(), Some(ArithError::OutOfRange));
+
+        assert_eq!(r.next_range(&Int32::from(i32::MIN), &Int32::from(i32::MAX)), i32::MIN.into());
+        assert_eq!(take_error(), Some(ArithError::OutOfRange));
+    }
+
+    #[test]
+    fn next_number_range() {
+        let mut r = Random::new(&Int32::from(7));
+        let (lo, hi) = (Number::from(-3), Number::from(5));
+        for _ in 0..1000 {
+            let n = r.next_number_range(&lo, &hi);
+            let raw = i32::from(Int32 { parts: n.0.parts });
+            assert!((-3 << 16..5 << 16).contains(&raw), "{} not in [-3, 5)", raw as f64 / 65536.0);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::geometry::{Dir3, Normal3, Point3};
+use crate::number::Number;
+use crate::vec3::Vec3;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Dir3,
+    // 1 / direction per component for slab tests. components that are zero
+    // or too small to invert are held at +-Number::MAX.
+    pub inv_direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Dir3) -> Self {
+        let inv_direction = Vec3::new(
+            inverse(&direction.vec3().x),
+            inverse(&direction.vec3().y),
+            inverse(&direction.vec3().z),
+        );
+        Self { origin, direction, inv_direction }
+    }
+
+    pub fn normalized(origin: Point3, direction: Dir3) -> Self {
+        let mut direction = direction;
+        direction.normalize();
+        Self::new(origin, direction)
+    }
+}
+
+impl Ray {
+    pub fn at(&self, t: &Number) -> Point3 {
+        let mut p = self.origin.clone();
+        p.offset(&self.direction, t);
+        p
+    }
+}
+
+fn inverse(n: &Number) -> Number {
+    Number::ONE.saturating_div(n)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hit {
+    pub t: Number,
+    pub point: Point3,
+    // always faces against the ray; front_face tells whether that is the
+    // surface's outward side.
+    pub normal: Normal3,
+    pub front_face: bool,
+    pub material: i16,
+    // surface coordinates, for shapes that have them: (u, v) on a plane,
+    // barycentrics on a triangle. zero otherwise.
+    pub u: Number,
+    pub v: Number,
+}
+
+impl Hit {
+    pub fn new(ray: &Ray, t: Number, outward_normal: Normal3, material: i16) -> Self {
+        let point = ray.at(&t);
+        let mut hit = Self { t, point, normal: outward_normal, front_face: true, material, u: Number::ZERO, v: Number::ZERO };
+        hit.orient(ray);
+        hit
+    }
+
+    pub fn orient(&mut self, ray: &Ray) {
+        self.front_face = self.normal.dot(&ray.direction).is_negative();
+        if !self.front_face {
+            self.normal.neg();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn at() {
+        let r = Ray::new(Point3::from([1.0, 2.0, 3.0]), Dir3::from([0.5, -1.0, 0.0]));
+        assert_eq!(r.at(&Number::from(4)), Point3::from([3.0, -2.0, 3.0]));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let o = [rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)];
+            let d = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+            let t = rng.gen_range(0.0..100.0);
+            let r = Ray::new(Point3::from(o), Dir3::from(d));
+            let p = r.at(&Number::from(t));
+
+            let q = |o: &Number, d: &Number| f64::from(o) + f64::from(d) * f64::from(&Number::from(t));
+            let want = [q(&r.origin.vec3().x, &r.direction.vec3().x), q(&r.origin.vec3().y, &r.direction.vec3().y), q(&r.origin.vec3().z, &r.direction.vec3().z)];
+            let got = <[f64; 3]>::from(&p);
+            for i in 0..3 {
+                assert!(got[i] <= want[i] && want[i] - got[i] < 1.0 / 65536.0, "got {:?}, want {:?}", got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn inv_direction() {
+        let r = Ray::new(Point3::origin(), Dir3::from([2.0, -0.25, 0.0]));
+        assert_eq!(r.inv_direction.x, Number::from(0.5));
+        assert_eq!(r.inv_direction.y, Number::from(-4));
+        assert_eq!(r.inv_direction.z, Number::MAX);
+
+        let r = Ray::new(Point3::origin(), Dir3::new(Number::ULP, Number::from(-1.0 / 65536.0), Number::ONE));
+        assert_eq!(r.inv_direction.x, Number::MAX);
+        assert_eq!(r.inv_direction.y, Number::MIN);
+    }
+
+    #[test]
+    fn normalized() {
+        let r = Ray::normalized(Point3::from([1.0, 1.0, 1.0]), Dir3::from([0.0, -2.0, 0.0]));
+        assert_eq!(r.origin, Point3::from([1.0, 1.0, 1.0]));
+        assert_eq!(r.direction, Dir3::from([0.0, -1.0, 0.0]));
+        assert_eq!(r.inv_direction, Vec3::from([f64::MAX, -1.0, f64::MAX]));
+
+        let r = Ray::normalized(Point3::origin(), Dir3::from([0.0, 3.0, 4.0]));
+        assert!((f64::from(&r.direction.vec3().y) - 0.6).abs() < 2.0 / 65536.0);
+        assert!((f64::from(&r.direction.vec3().z) - 0.8).abs() < 2.0 / 65536.0);
+    }
+
+    #[test]
+    fn hit_orientation() {
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::from([0.0, 0.0, 1.0]));
+
+        let h = Hit::new(&r, Number::from(4), Normal3::from([0.0, 0.0, -1.0]), 7);
+        assert!(h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+        assert_eq!(h.point, Point3::from([0.0, 0.0, -1.0]));
+        assert_eq!(h.material, 7);
+
+        let h = Hit::new(&r, Number::from(6), Normal3::from([0.0, 0.0, 1.0]), 7);
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::aabb::Aabb;
+use crate::disk::level;
+use crate::geometry::{Normal3, Point3};
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// a rectangle in the y = 0 plane, centered on the origin, facing +y:
+// |x| <= half_width, |z| <= half_depth.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rect {
+    pub half_width: Number,
+    pub half_depth: Number,
+    pub material: i16,
+}
+
+impl Rect {
+    pub fn new(half_width: Number, half_depth: Number, material: i16) -> Self {
+        Self { half_width, half_depth, material }
+    }
+}
+
+impl Shape for Rect {
+    // (u, v) are the local x and z of the hit, like Disk.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (t, x, z) = level(ray, &Number::ZERO, t_min, t_max)?;
+        for (c, half) in [(&x, &self.half_width), (&z, &self.half_depth)] {
+            let mut low = half.clone();
+            low.neg();
+            if c.lt(&low) || half.lt(c) {
+                return None;
+            }
+        }
+
+        let mut hit = Hit::new(ray, t, Normal3::new(Number::ZERO, Number::ONE, Number::ZERO), self.material);
+        (hit.u, hit.v) = (x, z);
+        Some(hit)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (mut w, mut d) = (self.half_width.clone(), self.half_depth.clone());
+        w.neg();
+        d.neg();
+        Some(Aabb::new(&Point3::new(w, Number::ZERO, d), &Point3::new(self.half_width.clone(), Number::ZERO, self.half_depth.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::geometry::{Dir3, Point3};
+
+    #[test]
+    fn hit() {
+        let rect = Rect::new(Number::from(2), Number::ONE, 7);
+        let down = |x: f64, z: f64| Ray::new(Point3::from([x, 2.0, z]), Dir3::from([0.0, -1.0, 0.0]));
+
+        let h = rect.hit(&down(1.5, -0.5), &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        assert_eq!(h.material, 7);
+        assert_eq!((h.u, h.v), (Number::from(1.5), Number::from(-0.5)));
+
+        // edges and corners are on it
+        for (x, z) in [(2.0, 0.0), (-2.0, 0.0), (0.0, 1.0), (0.0, -1.0), (2.0, 1.0), (-2.0, -1.0)] {
+            assert!(rect.hit(&down(x, z), &Number::ZERO, &Number::MAX).is_some(), "missed ({}, {})", x, z);
+        }
+        let e = 1.0 / 65536.0;
+        for (x, z) in [(2.0 + e, 0.0), (-2.0 - e, 0.0), (0.0, 1.0 + e), (0.0, -1.0 - e)] {
+            assert!(rect.hit(&down(x, z), &Number::ZERO, &Number::MAX).is_none(), "hit ({}, {})", x, z);
+        }
+
+        let up = Ray::new(Point3::from([0.0, -1.0, 0.0]), Dir3::from([0.0, 1.0, 0.0]));
+        assert!(!rect.hit(&up, &Number::ZERO, &Number::MAX).unwrap().front_face);
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = rand::thread_rng();
+        let (mut hits, mut misses) = (0, 0);
+        for _ in 0..1000 {
+            let rect = Rect::new(Number::from(rng.gen_range(0.5..20.0)), Number::from(rng.gen_range(0.5..20.0)), 0);
+            let r = Ray::new(
+                Point3::from([rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)]),
+                Dir3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]),
+            );
+
+            let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+            let t = -o[1] / d[1];
+            let (x, z) = (o[0] + t * d[0], o[2] + t * d[2]);
+            // how far outside the rectangle (x, z) is; negative inside
+            let out = (x.abs() - f64::from(&rect.half_width)).max(z.abs() - f64::from(&rect.half_depth));
+
+            if out.abs() < 1.0 / 256.0 || !(1.0 / 1024.0..32767.0).contains(&t.abs()) {
+                continue;
+            }
+            match rect.hit(&r, &Number::ZERO, &Number::MAX) {
+                Some(h) => {
+                    hits += 1;
+                    assert!(t > 0.0 && out < 0.0, "hit at t = {}, {} outside", t, out);
+                    assert!((f64::from(&h.u) - x).abs() < 1.0 / 1024.0 && (f64::from(&h.v) - z).abs() < 1.0 / 1024.0);
+                }
+                None => {
+                    misses += 1;
+                    assert!(t < 0.0 || out > 0.0, "missed at t = {}, {} inside", t, -out);
+                }
+            }
+        }
+        assert!(hits > 50 && misses > 50, "{} hits, {} misses", hits, misses);
+    }
+}
@@ -0,0 +1,457 @@
+use crate::csg::Op;
+use crate::geometry::{Normal3, Point3};
+use crate::int32::Int32;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+const QUARTER: Number = Number(Int32 { parts: [0, 0x40, 0, 0] });
+// 1/256: wide enough that a few ulp of rounding in each distance leave the
+// normal within about a percent, narrow enough to stay on the surface.
+const NORMAL_STEP: Number = Number(Int32 { parts: [0, 1, 0, 0] });
+
+// a signed distance field: negative inside, positive outside, and never more
+// than the true distance to the surface, so a ray can always step that far.
+// the primitives sit at the origin, like the analytic shapes in local space;
+// Transformed places them.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Sdf {
+    Sphere { radius: Number },
+    Cuboid { half: Vec3 },
+    // around the y axis
+    Torus { major: Number, minor: Number },
+    // n . p = offset, n unit length; the side n points to is outside
+    Plane { normal: Vec3, offset: Number },
+    Combine { op: Op, left: Box<Sdf>, right: Box<Sdf> },
+    // blended over a band of width k
+    Smooth { op: Op, left: Box<Sdf>, right: Box<Sdf>, k: Number, inv_k: Number },
+    // copies every period along each axis with a nonzero period. the shape
+    // has to fit within its cell for the distance to stay a bound.
+    Repeat { period: Vec3, inv_period: Vec3, inner: Box<Sdf> },
+    // held world to local. only rigid motions keep distances: scale with
+    // Scaled.
+    Transformed { to_local: Transform, inner: Box<Sdf> },
+    Scaled { factor: Number, inv: Number, inner: Box<Sdf> },
+}
+
+impl Sdf {
+    pub fn sphere(radius: Number) -> Self {
+        Sdf::Sphere { radius }
+    }
+
+    pub fn cuboid(half: Vec3) -> Self {
+        Sdf::Cuboid { half }
+    }
+
+    pub fn torus(major: Number, minor: Number) -> Self {
+        Sdf::Torus { major, minor }
+    }
+
+    pub fn plane(normal: Vec3, offset: Number) -> Self {
+        assert!(normal != Vec3::new(Number::ZERO, Number::ZERO, Number::ZERO), "sdf plane needs a nonzero normal");
+        let mut normal = normal;
+        normal.normalize();
+        Sdf::Plane { normal, offset }
+    }
+
+    pub fn union(left: Sdf, right: Sdf) -> Self {
+        Sdf::Combine { op: Op::Union, left: Box::new(left), right: Box::new(right) }
+    }
+
+    pub fn intersection(left: Sdf, right: Sdf) -> Self {
+        Sdf::Combine { op: Op::Intersection, left: Box::new(left), right: Box::new(right) }
+    }
+
+    pub fn difference(left: Sdf, right: Sdf) -> Self {
+        Sdf::Combine { op: Op::Difference, left: Box::new(left), right: Box::new(right) }
+    }
+
+    // k has to be positive, and wide enough that 1 / k fits in 16.16.
+    pub fn smooth(op: Op, left: Sdf, right: Sdf, k: Number) -> Self {
+        let mut inv_k = Number::ONE;
+        assert!(!k.is_negative() && inv_k.try_div(&k).is_ok(), "sdf smooth band out of range");
+        Sdf::Smooth { op, left: Box::new(left), right: Box::new(right), k, inv_k }
+    }
+
+    pub fn repeat(inner: Sdf, period: Vec3) -> Self {
+        let inv = |p: &Number| {
+            let mut r = Number::ZERO;
+            if *p != Number::ZERO {
+                r = Number::ONE;
+                r.div(p);
+            }
+            r
+        };
+        let inv_period = Vec3::new(inv(&period.x), inv(&period.y), inv(&period.z));
+        Sdf::Repeat { period, inv_period, inner: Box::new(inner) }
+    }
+
+    // the shape moved by a rigid transform, object to world.
+    pub fn transformed(inner: Sdf, transform: Transform) -> Self {
+        let mut to_local = transform;
+        to_local.inverse();
+        Sdf::Transformed { to_local, inner: Box::new(inner) }
+    }
+
+    // as smooth's k, factor has to be positive, with 1 / factor in 16.16.
+    pub fn scaled(inner: Sdf, factor: Number) -> Self {
+        let mut inv = Number::ONE;
+        assert!(!factor.is_negative() && inv.try_div(&factor).is_ok(), "sdf scale out of range");
+        Sdf::Scaled { factor, inv, inner: Box::new(inner) }
+    }
+}
+
+impl Sdf {
+    pub fn distance(&self, p: &Point3) -> Number {
+        self.at(p.vec3())
+    }
+
+    fn at(&self, p: &Vec3) -> Number {
+        match self {
+            Sdf::Sphere { radius } => length(&[p.x.clone(), p.y.clone(), p.z.clone()]).saturating_sub(radius),
+            Sdf::Cuboid { half } => {
+                let q = [(&p.x, &half.x), (&p.y, &half.y), (&p.z, &half.z)].map(|(p, h)| p.saturating_abs().saturating_sub(h));
+                let outside = length(&q.clone().map(|q| q.max(Number::ZERO)));
+                let [x, y, z] = q;
+                outside.saturating_add(&x.max(y).max(z).min(Number::ZERO))
+            }
+            Sdf::Torus { major, minor } => {
+                let rho = length(&[p.x.clone(), p.z.clone()]).saturating_sub(major);
+                length(&[rho, p.y.clone()]).saturating_sub(minor)
+            }
+            Sdf::Plane { normal, offset } => {
+                let (n, p) = ([normal.x.clone(), normal.y.clone(), normal.z.clone()], [p.x.clone(), p.y.clone(), p.z.clone()]);
+                Number::saturating_dot(&n, &p).saturating_sub(offset)
+            }
+            Sdf::Combine { op, left, right } => {
+                let (a, b) = (left.at(p), right.at(p));
+                match op {
+                    Op::Union => a.min(b),
+                    Op::Intersection => a.max(b),
+                    Op::Difference => a.max(b.saturating_neg()),
+                }
+            }
+            Sdf::Smooth { op, left, right, k, inv_k } => {
+                let (a, b) = (left.at(p), right.at(p));
+                match op {
+                    Op::Union => smooth_min(a, b, k, inv_k),
+                    Op::Intersection => smooth_min(a.saturating_neg(), b.saturating_neg(), k, inv_k).saturating_neg(),
+                    Op::Difference => smooth_min(a.saturating_neg(), b, k, inv_k).saturating_neg(),
+                }
+            }
+            Sdf::Repeat { period, inv_period, inner } => {
+                let cell = |p: &Number, period: &Number, inv: &Number| {
+                    if *period == Number::ZERO {
+                        return p.clone();
+                    }
+                    let n = floor(&p.saturating_mul(inv).saturating_add(&Number::HALF));
+                    p.saturating_sub(&n.saturating_mul(period))
+                };
+                inner.at(&Vec3::new(
+                    cell(&p.x, &period.x, &inv_period.x),
+                    cell(&p.y, &period.y, &inv_period.y),
+                    cell(&p.z, &period.z, &inv_period.z),
+                ))
+            }
+            Sdf::Transformed { to_local, inner } => inner.at(&to_local.transform_point(p)),
+            Sdf::Scaled { factor, inv, inner } => {
+                let mut q = p.clone();
+                q.scale(inv);
+                inner.at(&q).saturating_mul(factor)
+            }
+        }
+    }
+
+    // the gradient from four samples at the corners of a tetrahedron around
+    // p, which needs one fewer than central differences.
+    pub fn normal(&self, p: &Point3) -> Normal3 {
+        let mut n = Vec3::zero();
+        for k in [[1, -1, -1], [-1, -1, 1], [-1, 1, -1], [1, 1, 1]] {
+            let mut step = Vec3::new(Number::from(k[0]), Number::from(k[1]), Number::from(k[2]));
+            step.scale(&NORMAL_STEP);
+            let mut q = p.vec3().clone();
+            q.add(&step);
+            let mut term = Vec3::new(Number::from(k[0]), Number::from(k[1]), Number::from(k[2]));
+            term.scale(&self.at(&q));
+            n.add(&term);
+        }
+        let mut n = Normal3::new(n.x, n.y, n.z);
+        n.normalize();
+        n
+    }
+}
+
+// sphere tracing: step along the ray by the distance to the nearest surface
+// until that is under epsilon. the steps are measured along the ray, so any
+// direction length works.
+pub struct Marcher {
+    pub sdf: Sdf,
+    pub max_steps: u16,
+    pub epsilon: Number,
+    pub material: i16,
+}
+
+impl Marcher {
+    pub fn new(sdf: Sdf, max_steps: u16, epsilon: Number, material: i16) -> Self {
+        Self { sdf, max_steps, epsilon, material }
+    }
+}
+
+impl Shape for Marcher {
+    // a ray starting inside marches on the negated distance out to the
+    // surface it leaves through. None when the steps run out first, as they
+    // can for rays grazing a surface.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let speed = ray.direction.length();
+        if speed == Number::ZERO {
+            return None;
+        }
+        let inv_speed = Number::ONE.saturating_div(&speed);
+
+        // a ray starting on the surface, as one leaving it does, is inside
+        // if it heads in, and takes epsilon steps until it is clear of it.
+        let mut t = t_min.clone();
+        let start = ray.at(&t);
+        let d = self.sdf.distance(&start);
+        let mut leaving = d.saturating_abs().lt(&self.epsilon);
+        let inside = if leaving { self.sdf.normal(&start).dot(&ray.direction).is_negative() } else { d.is_negative() };
+        for _ in 0..self.max_steps {
+            if !t.lt(t_max) {
+                return None;
+            }
+            let p = ray.at(&t);
+            let mut d = self.sdf.distance(&p);
+            if inside {
+                d = d.saturating_neg();
+            }
+            if !d.lt(&self.epsilon) {
+                leaving = false;
+            } else if !leaving {
+                return Some(Hit::new(ray, t, self.sdf.normal(&p), self.material));
+            } else {
+                d = self.epsilon.clone();
+            }
+            t = t.saturating_add(&d.saturating_mul(&inv_speed));
+        }
+        None
+    }
+}
+
+// the polynomial smooth minimum: min(a, b) less up to k / 4 where a and b are
+// within k of each other.
+fn smooth_min(a: Number, b: Number, k: &Number, inv_k: &Number) -> Number {
+    let h = k.saturating_sub(&a.saturating_sub(&b).saturating_abs()).max(Number::ZERO).saturating_mul(inv_k);
+    let mut dip = h.saturating_mul(&h).saturating_mul(k);
+    dip.mul(&QUARTER);
+    a.min(b).saturating_sub(&dip)
+}
+
+// the 16.16 fraction bits cleared, which rounds two's complement down.
+fn floor(n: &Number) -> Number {
+    let mut r = n.clone();
+    r.0.parts[0] = 0;
+    r.0.parts[1] = 0;
+    r
+}
+
+fn length(xs: &[Number]) -> Number {
+    Number::try_hypot(xs).unwrap_or(Number::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geometry::Dir3;
+    use crate::sphere::Sphere;
+    use crate::torus::Torus;
+
+    const EPS: f64 = 1.0 / 1024.0;
+
+    fn n(x: f64) -> Number {
+        Number::from(x)
+    }
+
+    fn at(s: &Sdf, p: [f64; 3]) -> f64 {
+        f64::from(&s.distance(&Point3::from(p)))
+    }
+
+    fn marcher(sdf: Sdf) -> Marcher {
+        Marcher::new(sdf, 256, n(1.0 / 4096.0), 3)
+    }
+
+    #[test]
+    fn primitives() {
+        let sphere = Sdf::sphere(n(2.0));
+        assert_eq!(at(&sphere, [0.0, 0.0, 0.0]), -2.0);
+        assert_eq!(at(&sphere, [3.0, 0.0, 4.0]), 3.0);
+
+        let cuboid = Sdf::cuboid(Vec3::from([1.0, 2.0, 3.0]));
+        assert_eq!(at(&cuboid, [0.0, 0.0, 0.0]), -1.0);
+        assert_eq!(at(&cuboid, [0.0, 5.0, 0.0]), 3.0);
+        // off a corner, the distance to the corner
+        assert_eq!(at(&cuboid, [4.0, 6.0, 3.0]), 5.0);
+
+        let torus = Sdf::torus(n(3.0), n(1.0));
+        assert_eq!(at(&torus, [3.0, 0.0, 0.0]), -1.0);
+        assert_eq!(at(&torus, [0.0, 0.0, 0.0]), 2.0);
+        assert_eq!(at(&torus, [0.0, 4.0, -3.0]), 3.0);
+
+        let plane = Sdf::plane(Vec3::from([0.0, 2.0, 0.0]), n(-1.0));
+        assert_eq!(at(&plane, [5.0, 3.0, 5.0]), 4.0);
+        assert_eq!(at(&plane, [5.0, -3.0, 5.0]), -2.0);
+
+        // far away, saturating rather than wrapping
+        assert_eq!(at(&sphere, [30000.0, 30000.0, 0.0]), f64::from(&Number::MAX) - 2.0);
+    }
+
+    #[test]
+    fn operators() {
+        let (a, b) = (Sdf::sphere(n(1.0)), Sdf::transformed(Sdf::sphere(n(1.0)), Transform::translate(&Vec3::from([1.5, 0.0, 0.0]))));
+        let p = [0.75, 0.0, 0.0];
+        assert_eq!(at(&Sdf::union(a.clone(), b.clone()), p), -0.25);
+        assert_eq!(at(&Sdf::intersection(a.clone(), b.clone()), p), -0.25);
+        assert_eq!(at(&Sdf::difference(a.clone(), b.clone()), p), 0.25);
+        assert_eq!(at(&Sdf::union(a.clone(), b.clone()), [-3.0, 0.0, 0.0]), 2.0);
+
+        // smoothing reaches k / 4 past the hard result where both are equal,
+        // and leaves it alone where they are k or more apart
+        let k = n(0.5);
+        let smooth = Sdf::smooth(Op::Union, a.clone(), b.clone(), k.clone());
+        assert!((at(&smooth, p) - (-0.25 - 0.125)).abs() < EPS);
+        assert_eq!(at(&smooth, [-3.0, 0.0, 0.0]), 2.0);
+        let smooth = Sdf::smooth(Op::Intersection, a.clone(), b.clone(), k.clone());
+        assert!((at(&smooth, p) - (-0.25 + 0.125)).abs() < EPS);
+        let smooth = Sdf::smooth(Op::Difference, a.clone(), b, k);
+        assert_eq!(at(&smooth, p), 0.25);
+        assert!((at(&smooth, [1.75, 0.0, 0.0]) - (0.75 + 0.125)).abs() < EPS);
+
+        // every 4 along x and z, not along y
+        let grid = Sdf::repeat(a.clone(), Vec3::from([4.0, 0.0, 4.0]));
+        for (x, z) in [(0.0, 0.0), (4.0, 0.0), (-8.0, 12.0)] {
+            assert_eq!(at(&grid, [x + 0.5, 0.0, z]), -0.5);
+            assert_eq!(at(&grid, [x, 3.0, z]), 2.0);
+        }
+        assert_eq!(at(&grid, [2.0, 0.0, 0.0]), 1.0);
+
+        let big = Sdf::scaled(a, n(4.0));
+        assert_eq!(at(&big, [0.0, 10.0, 0.0]), 6.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "sdf smooth band out of range")]
+    fn smooth_rejects_zero_band() {
+        Sdf::smooth(Op::Union, Sdf::sphere(n(1.0)), Sdf::sphere(n(2.0)), Number::ZERO);
+    }
+
+    #[test]
+    #[should_panic(expected = "sdf scale out of range")]
+    fn scaled_rejects_zero() {
+        Sdf::scaled(Sdf::sphere(n(1.0)), Number::ZERO);
+    }
+
+    #[test]
+    #[should_panic(expected = "sdf plane needs a nonzero normal")]
+    fn plane_rejects_zero_normal() {
+        Sdf::plane(Vec3::from([0.0, 0.0, 0.0]), n(1.0));
+    }
+
+    #[test]
+    fn marches_to_analytic_shapes() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let shapes: [(Sdf, Box<dyn Shape>); 2] = [
+            (Sdf::sphere(n(2.0)), Box::new(Sphere::new(Point3::origin(), n(2.0), 3))),
+            (Sdf::torus(n(2.0), n(0.75)), Box::new(Torus::new(n(2.0), n(0.75), 3))),
+        ];
+        for (sdf, exact) in shapes {
+            let m = marcher(sdf);
+            let mut hits = 0;
+            for _ in 0..100 {
+                // aimed near the shape, from outside its bounding sphere
+                let o = [0, 1, 2].map(|_| rng.gen_range(-6.0..6.0));
+                if o.iter().map(|x| x * x).sum::<f64>() < 9.0 {
+                    continue;
+                }
+                let at = [0, 1, 2].map(|_| rng.gen_range(-2.5..2.5));
+                let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+                match (m.hit(&r, &Number::ZERO, &Number::MAX), exact.hit(&r, &Number::ZERO, &Number::MAX)) {
+                    (Some(h), Some(e)) => {
+                        hits += 1;
+                        // epsilon in distance is epsilon / |d| / cos in t
+                        let (d, ne) = (<[f64; 3]>::from(&r.direction), <[f64; 3]>::from(&e.normal));
+                        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                        let cos = -(d[0] * ne[0] + d[1] * ne[1] + d[2] * ne[2]) / len;
+                        if cos < 0.1 {
+                            continue;
+                        }
+                        let err = (f64::from(&h.t) - f64::from(&e.t)).abs() * len;
+                        assert!(err < 1.0 / 512.0 / cos, "t {} vs {}", f64::from(&h.t), f64::from(&e.t));
+                        assert!(h.front_face);
+                        let nh = <[f64; 3]>::from(&h.normal);
+                        let dot: f64 = (0..3).map(|i| nh[i] * ne[i]).sum();
+                        assert!(dot > 0.999, "normal {:?} vs {:?}", nh, ne);
+                        assert_eq!(h.material, 3);
+                    }
+                    (None, None) => {}
+                    // both agree but for grazing rays, which may run out of
+                    // steps or just clip a surface: on it, to epsilon, and
+                    // along it
+                    (Some(h), None) => {
+                        assert!(!h.t.lt(&Number::ZERO));
+                        let gap = f64::from(&m.sdf.distance(&h.point)).abs();
+                        assert!(gap < f64::from(&m.epsilon), "{:?} is {} off the surface", h.point, gap);
+                        let (d, nh) = (<[f64; 3]>::from(&r.direction), <[f64; 3]>::from(&h.normal));
+                        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                        let cos = (d[0] * nh[0] + d[1] * nh[1] + d[2] * nh[2]).abs() / len;
+                        assert!(cos < 0.1, "clipped {:?} at {:?}", r, h.point);
+                    }
+                    (None, Some(e)) => {
+                        let (d, ne) = (<[f64; 3]>::from(&r.direction), <[f64; 3]>::from(&e.normal));
+                        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                        let cos = -(d[0] * ne[0] + d[1] * ne[1] + d[2] * ne[2]) / len;
+                        assert!(cos < 0.1, "missed {:?} at {:?}", r, e.point);
+                    }
+                }
+            }
+            assert!(hits > 20, "{} hits", hits);
+        }
+    }
+
+    #[test]
+    fn inside_limits_and_ranges() {
+        let m = marcher(Sdf::cuboid(Vec3::from([1.0, 1.0, 1.0])));
+        // from inside, out through the far face
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, 2.0]));
+        let h = m.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!((f64::from(&h.t) - 0.5).abs() < EPS);
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+
+        // starting on the surface, the next surface along
+        let r = Ray::new(Point3::from([0.0, 0.0, -1.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = m.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!((f64::from(&h.t) - 2.0).abs() < EPS);
+
+        // outside the range, or out of steps
+        let r = Ray::new(Point3::from([0.0, 0.0, -5.0]), Dir3::from([0.0, 0.0, 1.0]));
+        assert!(m.hit(&r, &Number::ZERO, &n(3.0)).is_none());
+        assert!((f64::from(&m.hit(&r, &Number::ZERO, &Number::MAX).unwrap().t) - 4.0).abs() < EPS);
+        let short = Marcher::new(Sdf::cuboid(Vec3::from([1.0, 1.0, 1.0])), 0, n(1.0 / 4096.0), 0);
+        assert!(short.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+
+        // a field of spheres, blended into a floor
+        let spheres = Sdf::repeat(Sdf::sphere(n(1.0)), Vec3::from([4.0, 0.0, 4.0]));
+        let floor = Sdf::plane(Vec3::from([0.0, 1.0, 0.0]), n(-0.5));
+        let m = marcher(Sdf::smooth(Op::Union, spheres, floor, n(0.25)));
+        let r = Ray::new(Point3::from([8.0, 10.0, -4.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = m.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!((f64::from(&h.t) - 9.0).abs() < EPS);
+        let r = Ray::new(Point3::from([6.0, 10.0, -6.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = m.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!((f64::from(&h.t) - 10.5).abs() < EPS);
+    }
+}
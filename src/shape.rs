@@ -0,0 +1,60 @@
+use crate::aabb::Aabb;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+
+// a stretch of the ray inside a solid, from the hit where it goes in to the
+// one where it comes out. an end is None when it lies outside (t_min,
+// t_max): the ray is already inside at t_min, or still inside at t_max.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    pub enter: Option<Hit>,
+    pub exit: Option<Hit>,
+}
+
+pub trait Shape {
+    // the nearest hit with t_min < t < t_max.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit>;
+
+    // whether anything lies in (t_min, t_max), for shadow rays, which don't
+    // need the nearest hit. shapes holding many others stop at the first.
+    fn occluded(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> bool {
+        self.hit(ray, t_min, t_max).is_some()
+    }
+
+    // a box around the whole shape, for a Bvh. None for unbounded shapes,
+    // such as planes.
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    // every stretch of (t_min, t_max) the ray spends inside the shape, in
+    // order, for CSG. by default the hits are walked one after another,
+    // front_face read as going in and a back face as coming out. that suits
+    // closed solids, and planes as half-spaces. a ray grazing a solid, where
+    // both ends are at the same t, is only seen going in; shapes that find
+    // both ends at once override this.
+    fn spans(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut open: Option<Option<Hit>> = None;
+        let mut from = t_min.clone();
+        while let Some(hit) = self.hit(ray, &from, t_max) {
+            from = hit.t.clone();
+            if hit.front_face {
+                if open.is_none() {
+                    open = Some(Some(hit));
+                }
+                continue;
+            }
+            match open.take() {
+                Some(enter) => spans.push(Span { enter, exit: Some(hit) }),
+                // coming out before going in: inside from the start
+                None if spans.is_empty() => spans.push(Span { enter: None, exit: Some(hit) }),
+                None => {}
+            }
+        }
+        if let Some(enter) = open {
+            spans.push(Span { enter, exit: None });
+        }
+        spans
+    }
+}
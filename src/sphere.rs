@@ -0,0 +1,195 @@
+use crate::aabb::Aabb;
+use crate::geometry::{Dir3, Normal3, Point3};
+use crate::number::Number;
+use crate::poly::crossings;
+use crate::ray::{Hit, Ray};
+use crate::shape::{Shape, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sphere {
+    pub center: Point3,
+    pub radius: Number,
+    pub material: i16,
+}
+
+impl Sphere {
+    pub fn new(center: Point3, radius: Number, material: i16) -> Self {
+        Self { center, radius, material }
+    }
+}
+
+impl Sphere {
+    // the two crossings of the surface, worked around the center so nothing
+    // grows with the distance squared; see poly::crossings.
+    fn roots(&self, ray: &Ray) -> Option<(Number, Number)> {
+        let (oc, d) = (ray.origin.minus(&self.center).into_vec3(), ray.direction.vec3());
+        crossings(&[oc.x, oc.y, oc.z], &[d.x.clone(), d.y.clone(), d.z.clone()], &self.radius)
+    }
+
+    fn surface(&self, ray: &Ray, t: Number) -> Hit {
+        let mut normal = ray.at(&t).minus(&self.center).into_vec3();
+        normal.x.div(&self.radius);
+        normal.y.div(&self.radius);
+        normal.z.div(&self.radius);
+        Hit::new(ray, t, Normal3::new(normal.x, normal.y, normal.z), self.material)
+    }
+}
+
+impl Shape for Sphere {
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let (near, far) = self.roots(ray)?;
+        for t in [near, far] {
+            if t_min.lt(&t) && t.lt(t_max) {
+                return Some(self.surface(ray, t));
+            }
+        }
+        None
+    }
+
+    // both ends from the one chord, even where they meet.
+    fn spans(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Vec<Span> {
+        let Some((near, far)) = self.roots(ray) else { return Vec::new() };
+        if !t_min.lt(&far) || !near.lt(t_max) {
+            return Vec::new();
+        }
+        let enter = if t_min.lt(&near) { Some(self.surface(ray, near)) } else { None };
+        let exit = if far.lt(t_max) { Some(self.surface(ray, far)) } else { None };
+        vec![Span { enter, exit }]
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let r = Dir3::new(self.radius.clone(), self.radius.clone(), self.radius.clone());
+        let (mut lo, mut hi) = (self.center.clone(), self.center.clone());
+        lo.sub(&r);
+        hi.add(&r);
+        Some(Aabb::new(&lo, &hi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::geometry::Dir3;
+
+    // the textbook solution in f64, on the same rounded inputs, plus the
+    // distance of the ray's line from the center.
+    fn reference(s: &Sphere, r: &Ray, t_min: f64, t_max: f64) -> (Option<(f64, [f64; 3])>, f64) {
+        let (c, rad) = (<[f64; 3]>::from(&s.center), f64::from(&s.radius));
+        let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+        let oc = [o[0] - c[0], o[1] - c[1], o[2] - c[2]];
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+        let (a, h, cc) = (dot(d, d), dot(oc, d), dot(oc, oc) - rad * rad);
+        let tc = -h / a;
+        let l = [oc[0] + tc * d[0], oc[1] + tc * d[1], oc[2] + tc * d[2]];
+        let miss = dot(l, l).sqrt();
+
+        let disc = h * h - a * cc;
+        if disc < 0.0 {
+            return (None, miss);
+        }
+        for t in [(-h - disc.sqrt()) / a, (-h + disc.sqrt()) / a] {
+            if t_min < t && t < t_max {
+                let p = [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2]];
+                return (Some((t, [(p[0] - c[0]) / rad, (p[1] - c[1]) / rad, (p[2] - c[2]) / rad])), miss);
+            }
+        }
+        (None, miss)
+    }
+
+    #[test]
+    fn simple() {
+        let s = Sphere::new(Point3::from([0.0, 0.0, -5.0]), Number::ONE, 3);
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, -1.0]));
+
+        let h = s.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(4));
+        assert_eq!(h.point, Point3::from([0.0, 0.0, -4.0]));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 3);
+
+        // from inside, the far side, facing back in
+        let h = s.hit(&r, &Number::from(4), &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(6));
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+
+        // the interval is open at both ends
+        assert!(s.hit(&r, &Number::from(6), &Number::MAX).is_none());
+        assert!(s.hit(&r, &Number::ZERO, &Number::from(4)).is_none());
+
+        // a direction that isn't unit length scales t
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, -2.0]));
+        assert_eq!(s.hit(&r, &Number::ZERO, &Number::MAX).unwrap().t, Number::from(2));
+
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 1.0, 0.0]));
+        assert!(s.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+    }
+
+    // far enough away that oc . oc, r^2 and b^2 all leave the 16.16 range.
+    #[test]
+    fn distant() {
+        let s = Sphere::new(Point3::from([0.0, 0.0, -400.0]), Number::from(200), 0);
+        let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, -1.0]));
+        let h = s.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(200));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+
+        // a small sphere 300 units out, just touched
+        let s = Sphere::new(Point3::from([3.0, 0.0, -300.0]), Number::from(3), 0);
+        let h = s.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(300));
+        assert_eq!(h.point, Point3::from([0.0, 0.0, -300.0]));
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = rand::thread_rng();
+        let (mut hits, mut misses) = (0, 0);
+        for _ in 0..2000 {
+            let far: f64 = rng.gen_range(1.0..300.0);
+            let center = [rng.gen_range(-far..far), rng.gen_range(-far..far), rng.gen_range(-far..far)];
+            let radius = rng.gen_range(0.5..far.min(50.0));
+            let s = Sphere::new(Point3::from(center), Number::from(radius), 0);
+
+            // aim near the sphere, with directions of varying length
+            let target = [
+                center[0] + rng.gen_range(-1.5..1.5) * radius,
+                center[1] + rng.gen_range(-1.5..1.5) * radius,
+                center[2] + rng.gen_range(-1.5..1.5) * radius,
+            ];
+            let origin = [rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)];
+            let norm = ((0..3).map(|i| (target[i] - origin[i]).powi(2)).sum::<f64>()).sqrt();
+            let scale = rng.gen_range(0.25..2.0) / norm;
+            let dir = [(target[0] - origin[0]) * scale, (target[1] - origin[1]) * scale, (target[2] - origin[2]) * scale];
+            let r = Ray::new(Point3::from(origin), Dir3::from(dir));
+
+            let got = s.hit(&r, &Number::ZERO, &Number::MAX);
+            let (want, miss) = reference(&s, &r, 0.0, 32767.0);
+
+            // too close to grazing to call either way
+            if (miss - f64::from(&s.radius)).abs() < 1.0 / 256.0 {
+                continue;
+            }
+            match (got, want) {
+                (None, None) => misses += 1,
+                (Some(h), Some((t, n))) => {
+                    hits += 1;
+                    // t is in units of |d|; compare distances along the ray.
+                    let eps = 1.0 / 1024.0;
+                    let len = dir.iter().map(|c| c * c).sum::<f64>().sqrt();
+                    assert!((f64::from(&h.t) - t).abs() * len < eps, "t: got {}, want {}", f64::from(&h.t), t);
+                    let outward = <[f64; 3]>::from(&h.normal).map(|c| if h.front_face { c } else { -c });
+                    for i in 0..3 {
+                        assert!((outward[i] - n[i]).abs() < eps, "normal: got {:?}, want {:?}", outward, n);
+                    }
+                }
+                (got, want) => panic!("got {:?}, want {:?}, {} from the center", got, want, miss),
+            }
+        }
+        assert!(hits > 100 && misses > 100, "{} hits, {} misses", hits, misses);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::aabb::Aabb;
+use crate::geometry::Normal3;
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::poly;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// a torus around the y axis, centered on the origin: a tube of radius minor
+// swept around a circle of radius major in the y = 0 plane.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Torus {
+    pub major: Number,
+    pub minor: Number,
+    pub material: i16,
+}
+
+impl Torus {
+    pub fn new(major: Number, minor: Number, material: i16) -> Self {
+        Self { major, minor, material }
+    }
+}
+
+impl Shape for Torus {
+    // the surface is (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2), a quartic
+    // in t. its coefficients are 4th powers of lengths, so before forming
+    // them the ray is restarted where it passes closest to the center (a
+    // line further out than R + r misses), lengths are scaled by a power of
+    // two so R + r is in [16, 32), and t by one so |d| is. then the second
+    // order terms are rounded to 16.16 with 24 bits or more to spare, and
+    // the coefficients are their products, exact in 32.32.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let direction = ray.direction.vec3();
+        let d = [direction.x.clone(), direction.y.clone(), direction.z.clone()];
+        let o = ray.origin.vec3();
+        let mut tc = Number::try_dot_ratio(&[o.x.clone(), o.y.clone(), o.z.clone()], &d, &d, &d).ok()?;
+        tc.neg();
+        let mut start = ray.origin.clone();
+        start.offset(&ray.direction, &tc);
+
+        let mut bound = self.major.clone();
+        bound.try_add(&self.minor).ok()?;
+        let s = start.vec3();
+        let [x, y, z] = [s.x.clone(), s.y.clone(), s.z.clone()];
+        if Int64::product(&bound.0, &bound.0).lt_unsigned(&Number::sum_of_products(&[x.clone(), y.clone(), z.clone()], &[x, y, z])) {
+            return None;
+        }
+
+        // lengths by 2^a, and t - tc = 2^(b - a) sigma.
+        let a = exponent(&bound)?;
+        let b = exponent(&Number::try_hypot(&d).ok()?)?;
+        let o = [scaled(&s.x, a)?, scaled(&s.y, a)?, scaled(&s.z, a)?];
+        let d = [scaled(&d[0], b)?, scaled(&d[1], b)?, scaled(&d[2], b)?];
+        let (major, minor) = (scaled(&self.major, a)?, scaled(&self.minor, a)?);
+
+        let (dd, od, oo) = (Number::dot(&d, &d), Number::dot(&o, &d), Number::dot(&o, &o));
+        let flat = |u: &[Number; 3], v: &[Number; 3]| Number::dot(&[u[0].clone(), u[2].clone()], &[v[0].clone(), v[2].clone()]);
+        let (dxz, oxz_d, oxz) = (flat(&d, &d), flat(&o, &d), flat(&o, &o));
+        let mut r2 = major.clone();
+        r2.mul(&major);
+        // |o|^2 + R^2 - r^2
+        let mut neg_minor = minor.clone();
+        neg_minor.neg();
+        let mut k = Number::dot(&[major.clone(), minor], &[major, neg_minor]);
+        k.add(&oo);
+
+        let product = |u: &Number, v: &Number| Int64::product(&u.0, &v.0);
+        let twice = |mut n: Int64, times: i16| {
+            n.shl(times);
+            n
+        };
+        // dd^2 s^4 + 4 dd od s^3 + (4 od^2 + 2 dd k - 4 R^2 dxz) s^2
+        //   + (4 od k - 8 R^2 oxz_d) s + k^2 - 4 R^2 oxz
+        let a4 = product(&dd, &dd);
+        let a3 = twice(product(&dd, &od), 2);
+        let mut a2 = twice(product(&od, &od), 1);
+        a2.add(&product(&dd, &k));
+        a2.sub(&twice(product(&r2, &dxz), 1));
+        let mut a1 = product(&od, &k);
+        a1.sub(&twice(product(&r2, &oxz_d), 1));
+        let mut a0 = product(&k, &k);
+        a0.sub(&twice(product(&r2, &oxz), 2));
+        let p = poly::from_wide(&[a4, a3, twice(a2, 1), twice(a1, 2), a0]);
+
+        for sigma in poly::quartic(&p[0], &p[1], &p[2], &p[3], &p[4]) {
+            let Some(step) = scaled(&sigma, b - a) else { continue };
+            let mut t = tc.clone();
+            if t.try_add(&step).is_err() || !t_min.lt(&t) || !t.lt(t_max) {
+                continue;
+            }
+
+            // away from the nearest point of the center circle
+            let mut p = start.clone();
+            p.offset(&ray.direction, &step);
+            let rho = Number::hypot(&[p.vec3().x.clone(), p.vec3().z.clone()]);
+            let mut normal = Normal3::new(Number::ZERO, Number::ONE, Number::ZERO);
+            if rho != Number::ZERO {
+                let mut center = [p.vec3().x.clone(), p.vec3().z.clone()];
+                for c in center.iter_mut() {
+                    c.div(&rho);
+                    c.mul(&self.major);
+                }
+                let p = p.into_vec3();
+                let (mut x, mut z) = (p.x, p.z);
+                x.sub(&center[0]);
+                z.sub(&center[1]);
+                normal = Normal3::new(x, p.y, z);
+                normal.normalize();
+            }
+            return Some(Hit::new(ray, t, normal, self.material));
+        }
+        None
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (mut r, mut y) = (self.major.clone(), self.minor.clone());
+        r.add(&self.minor);
+        y.neg();
+        Some(Aabb::around_y(&r, &y, &self.minor))
+    }
+}
+
+// k with 2^k n in [16, 32), for n > 0.
+fn exponent(n: &Number) -> Option<i16> {
+    if n.is_negative() || *n == Number::ZERO {
+        return None;
+    }
+    let (mut n, mut k) = (n.clone(), 0);
+    while n.lt(&Number::from(16)) {
+        n.add(&n.clone());
+        k += 1;
+    }
+    while !n.lt(&Number::from(32)) {
+        n.0.sar(1);
+        k -= 1;
+    }
+    Some(k)
+}
+
+// 2^k n, floored when k < 0.
+fn scaled(n: &Number, k: i16) -> Option<Number> {
+    let mut n = n.clone();
+    if k < 0 {
+        n.0.sar(-k);
+    }
+    for _ in 0..k {
+        n.try_add(&n.clone()).ok()?;
+    }
+    Some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geometry::{Dir3, Point3};
+
+    fn ring() -> Torus {
+        Torus::new(Number::from(2), Number::from(0.5), 10)
+    }
+
+    // the first root above t_min of the quartic in f64, on the same rounded
+    // inputs: sampled finely across the bounding sphere, then bisected.
+    fn reference(tor: &Torus, r: &Ray, t_min: f64) -> Option<f64> {
+        let (big, small) = (f64::from(&tor.major), f64::from(&tor.minor));
+        let (o, d) = (<[f64; 3]>::from(&r.origin), <[f64; 3]>::from(&r.direction));
+        let f = |t: f64| {
+            let p = [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2]];
+            let k = p[0] * p[0] + p[1] * p[1] + p[2] * p[2] + big * big - small * small;
+            k * k - 4.0 * big * big * (p[0] * p[0] + p[2] * p[2])
+        };
+
+        let dd = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+        let tc = -(o[0] * d[0] + o[1] * d[1] + o[2] * d[2]) / dd;
+        let half = (big + small) / dd.sqrt();
+        let (lo, hi) = ((tc - half).max(t_min), tc + half);
+        let steps = 20000;
+        let mut prev = lo;
+        for i in 1..=steps {
+            let t = lo + (hi - lo) * i as f64 / steps as f64;
+            if (f(prev) < 0.0) != (f(t) < 0.0) {
+                let (mut a, mut b) = (prev, t);
+                for _ in 0..60 {
+                    let m = 0.5 * (a + b);
+                    if (f(a) < 0.0) == (f(m) < 0.0) {
+                        a = m;
+                    } else {
+                        b = m;
+                    }
+                }
+                return Some(0.5 * (a + b));
+            }
+            prev = t;
+        }
+        None
+    }
+
+    #[test]
+    fn hits() {
+        let tor = ring();
+
+        // across the tube, through the hole, and out the other side
+        let r = Ray::new(Point3::from([-5.0, 0.0, 0.0]), Dir3::from([1.0, 0.0, 0.0]));
+        let h = tor.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(2.5));
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 10);
+        let h = tor.hit(&r, &Number::from(2.5), &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(3.5));
+        assert!(!h.front_face);
+        let h = tor.hit(&r, &Number::from(3.5), &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(6.5));
+        assert_eq!(h.normal, Normal3::from([-1.0, 0.0, 0.0]));
+
+        // down onto the top of the tube, and down through the hole
+        let r = Ray::new(Point3::from([0.0, 3.0, 2.0]), Dir3::from([0.0, -1.0, 0.0]));
+        let h = tor.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.t, Number::from(2.5));
+        assert_eq!(h.normal, Normal3::from([0.0, 1.0, 0.0]));
+        let r = Ray::new(Point3::from([0.0, 3.0, 0.0]), Dir3::from([0.0, -1.0, 0.0]));
+        assert!(tor.hit(&r, &Number::ZERO, &Number::MAX).is_none());
+
+        // from inside the tube, and far away with a short direction
+        let r = Ray::new(Point3::from([2.0, 0.0, 0.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = tor.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert!(!h.front_face);
+        // sqrt(4 + z^2) - 2 = 1/2
+        assert!((f64::from(&h.t) - 1.5).abs() < 1.0 / 4096.0, "{:?}", h.t);
+        let r = Ray::new(Point3::from([0.0, 0.0, -300.0]), Dir3::from([0.0, 0.0, 0.125]));
+        let h = tor.hit(&r, &Number::ZERO, &Number::MAX).unwrap();
+        assert_eq!(h.point, Point3::from([0.0, 0.0, -2.5]));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+    }
+
+    // how often 16.16 misses a hit f64 finds, finds one f64 doesn't, or puts
+    // it somewhere else, over rays aimed at the torus. run with --nocapture
+    // for the figures. typically about half the rays hit, none are missed,
+    // spurious or mislocated, and the worst is under 1/1024 along the ray.
+    #[test]
+    fn report_against_f64() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let n = 2000;
+        let (mut agree, mut hits, mut missed, mut spurious, mut mislocated, mut worst) = (0, 0, 0, 0, 0, 0.0f64);
+        for _ in 0..n {
+            let big: f64 = rng.gen_range(0.5..20.0);
+            let tor = Torus::new(Number::from(big), Number::from(rng.gen_range(0.05..0.9) * big), 0);
+            let reach = f64::from(&tor.major) + f64::from(&tor.minor);
+            let target = [0, 1, 2].map(|_| rng.gen_range(-reach..reach));
+            let origin = [0, 1, 2].map(|_| rng.gen_range(-60.0..60.0));
+            let norm = (0..3).map(|i| (target[i] - origin[i]).powi(2)).sum::<f64>().sqrt();
+            let scale = rng.gen_range(0.25..2.0) / norm;
+            let r = Ray::new(Point3::from(origin), Dir3::from([0, 1, 2].map(|i| (target[i] - origin[i]) * scale)));
+            let len = f64::from(&r.direction.length());
+
+            match (tor.hit(&r, &Number::ZERO, &Number::MAX), reference(&tor, &r, 0.0)) {
+                (None, None) => agree += 1,
+                (None, Some(_)) => missed += 1,
+                (Some(_), None) => spurious += 1,
+                (Some(h), Some(t)) => {
+                    hits += 1;
+                    let off = (f64::from(&h.t) - t).abs() * len;
+                    worst = worst.max(off);
+                    if off < 1.0 / 256.0 {
+                        agree += 1;
+                    } else {
+                        mislocated += 1;
+                    }
+                }
+            }
+        }
+        println!(
+            "torus vs f64 over {} rays, {} hitting: {} agree, {} missed, {} spurious, {} mislocated by over 1/256; worst {:.5}",
+            n, hits, agree, missed, spurious, mislocated, worst
+        );
+        assert!(missed + spurious <= n / 100, "{} missed, {} spurious", missed, spurious);
+        assert!(mislocated <= n / 100, "{} mislocated", mislocated);
+    }
+}
@@ -0,0 +1,408 @@
+use crate::error::raise;
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::trig::sin_cos;
+use crate::vec3::Vec3;
+
+// Rows of a 3x4 affine matrix; the implied last row is [0 0 0 1].
+pub type Matrix = [[Number; 4]; 3];
+
+// An affine transform together with its inverse. The constructors build
+// both sides, so inverting only swaps them; from_rows is the one place a
+// general inverse is computed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Transform {
+    pub m: Matrix,
+    pub inv: Matrix,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self { m: identity(), inv: identity() }
+    }
+
+    pub fn translate(v: &Vec3) -> Self {
+        let mut t = Self::identity();
+        t.m[0][3] = v.x.clone();
+        t.m[1][3] = v.y.clone();
+        t.m[2][3] = v.z.clone();
+
+        let mut v = v.clone();
+        v.neg();
+        t.inv[0][3] = v.x;
+        t.inv[1][3] = v.y;
+        t.inv[2][3] = v.z;
+        t
+    }
+
+    pub fn scale(v: &Vec3) -> Self {
+        let mut t = Self::identity();
+        for (i, s) in [&v.x, &v.y, &v.z].into_iter().enumerate() {
+            t.m[i][i] = s.clone();
+            t.inv[i][i] = Number::ONE;
+            t.inv[i][i].div(s);
+        }
+        t
+    }
+
+    pub fn rotate_x(angle: &Number) -> Self {
+        Self::rotate_plane(1, 2, angle)
+    }
+
+    pub fn rotate_y(angle: &Number) -> Self {
+        Self::rotate_plane(2, 0, angle)
+    }
+
+    pub fn rotate_z(angle: &Number) -> Self {
+        Self::rotate_plane(0, 1, angle)
+    }
+
+    // rotates axis a towards axis b.
+    fn rotate_plane(a: usize, b: usize, angle: &Number) -> Self {
+        let (sin, cos) = sin_cos(angle);
+        let mut m = identity();
+        m[a][a] = cos.clone();
+        m[b][b] = cos;
+        m[b][a] = sin.clone();
+        m[a][b] = sin;
+        m[a][b].neg();
+        Self::from_rotation(m)
+    }
+
+    // right-handed rotation about an arbitrary axis (Rodrigues' formula):
+    // cos I + sin [k]x + (1 - cos) k k^T.
+    pub fn rotate(axis: &Vec3, angle: &Number) -> Self {
+        let mut k = axis.clone();
+        k.normalize();
+        let k = [k.x, k.y, k.z];
+
+        let (sin, cos) = sin_cos(angle);
+        let mut one_minus_cos = Number::ONE;
+        one_minus_cos.sub(&cos);
+
+        let mut m = identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                let mut kk = k[i].clone();
+                kk.mul(&k[j]);
+                m[i][j] = if i == j { cos.clone() } else { Number::ZERO };
+                m[i][j].mul_add(&kk, &one_minus_cos);
+            }
+        }
+
+        // [k]x = [[0, -z, y], [z, 0, -x], [-y, x, 0]]
+        for (i, j, c) in [(2, 1, 0), (0, 2, 1), (1, 0, 2)] {
+            let mut s = k[c].clone();
+            s.mul(&sin);
+            m[i][j].add(&s);
+            m[j][i].sub(&s);
+        }
+
+        Self::from_rotation(m)
+    }
+
+    // a pure rotation. its transpose would be its inverse, but for the
+    // few ulp m is off from orthogonal, which a scale then magnifies by
+    // its largest over its smallest factor; the adjugate has no such error.
+    pub fn from_rotation(m: Matrix) -> Self {
+        Self::from_rows(m)
+    }
+
+    // a general affine matrix; its inverse is the adjugate of the linear part
+    // over the determinant. the adjugate's entries, 2x2 minors, are exact
+    // wide and divided by the determinant wide, so each entry of the inverse
+    // is rounded once. a singular matrix raises DivideByZero.
+    pub fn from_rows(m: Matrix) -> Self {
+        // adj[i][j] is (b x c)_i for rows b, c after j: the columns of the
+        // adjugate are b x c, c x a and a x b.
+        let minor = |i: usize, j: usize| {
+            let (b, c, i1, i2) = ((j + 1) % 3, (j + 2) % 3, (i + 1) % 3, (i + 2) % 3);
+            let mut x = Int64::product(&m[b][i1].0, &m[c][i2].0);
+            x.sub(&Int64::product(&m[b][i2].0, &m[c][i1].0));
+            x
+        };
+        let adj = [0, 1, 2].map(|i| [0, 1, 2].map(|j| minor(i, j)));
+
+        // a . (b x c), with b x c rounded to 16.16 first
+        let one = Int64::from(1i64 << 32);
+        let cofactors = [0, 1, 2].map(|i| {
+            Number::try_ratio(&adj[i][0], &one).unwrap_or(if adj[i][0].parts[7] >= 0x80 { Number::MIN } else { Number::MAX })
+        });
+        let det = Number::sum_of_products(&m[0][..3], &cofactors);
+
+        let mut inv = identity();
+        for (i, row) in inv.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().take(3).enumerate() {
+                *e = Number::try_ratio(&adj[i][j], &det).unwrap_or_else(|err| {
+                    raise(err);
+                    let negative = (adj[i][j].parts[7] >= 0x80) ^ (det.parts[7] >= 0x80);
+                    if adj[i][j].parts == [0; 8] {
+                        Number::ZERO
+                    } else if negative {
+                        Number::MIN
+                    } else {
+                        Number::MAX
+                    }
+                });
+            }
+        }
+
+        let mut t = Vec3::new(m[0][3].clone(), m[1][3].clone(), m[2][3].clone());
+        t.neg();
+        let t = apply(&inv, &t, &Number::ZERO);
+        inv[0][3] = t.x;
+        inv[1][3] = t.y;
+        inv[2][3] = t.z;
+
+        Self { m, inv }
+    }
+}
+
+impl Transform {
+    // self = self * other, i.e. other is applied first.
+    pub fn mul(&mut self, other: &Self) {
+        self.m = multiply(&self.m, &other.m);
+        self.inv = multiply(&other.inv, &self.inv);
+    }
+
+    pub fn inverse(&mut self) {
+        std::mem::swap(&mut self.m, &mut self.inv);
+    }
+}
+
+impl Transform {
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        apply(&self.m, p, &Number::ONE)
+    }
+
+    pub fn transform_direction(&self, d: &Vec3) -> Vec3 {
+        apply(&self.m, d, &Number::ZERO)
+    }
+
+    // by the inverse transpose, so normals stay perpendicular to transformed
+    // surfaces. the result is not renormalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        let v = [n.x.clone(), n.y.clone(), n.z.clone()];
+        let col = |j: usize| Number::dot(&[self.inv[0][j].clone(), self.inv[1][j].clone(), self.inv[2][j].clone()], &v);
+        Vec3::new(col(0), col(1), col(2))
+    }
+}
+
+fn identity() -> Matrix {
+    let mut m = [
+        [Number::ZERO, Number::ZERO, Number::ZERO, Number::ZERO],
+        [Number::ZERO, Number::ZERO, Number::ZERO, Number::ZERO],
+        [Number::ZERO, Number::ZERO, Number::ZERO, Number::ZERO],
+    ];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = Number::ONE;
+    }
+    m
+}
+
+// rows . [x y z w], each rounded once.
+fn apply(m: &Matrix, v: &Vec3, w: &Number) -> Vec3 {
+    let v = [v.x.clone(), v.y.clone(), v.z.clone(), w.clone()];
+    Vec3::new(Number::dot(&m[0], &v), Number::dot(&m[1], &v), Number::dot(&m[2], &v))
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut r = identity();
+    for (i, row) in r.iter_mut().enumerate() {
+        for (j, e) in row.iter_mut().enumerate() {
+            let w = if j == 3 { Number::ONE } else { Number::ZERO };
+            *e = Number::dot(&a[i], &[b[0][j].clone(), b[1][j].clone(), b[2][j].clone(), w]);
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    // M * M^-1 is within this of I per linear entry, and within this per unit
+    // of translation in the last column, for compositions of rotations,
+    // translations and scales in [0.5, 2].
+    const TOLERANCE: f64 = 1.0 / 1024.0;
+
+    type Reference = [[f64; 4]; 3];
+
+    fn reference(m: &Matrix) -> Reference {
+        let mut r = [[0.0; 4]; 3];
+        for i in 0..3 {
+            for j in 0..4 {
+                r[i][j] = f64::from(&m[i][j]);
+            }
+        }
+        r
+    }
+
+    fn reference_mul(a: &Reference, b: &Reference) -> Reference {
+        let mut r = [[0.0; 4]; 3];
+        for i in 0..3 {
+            for j in 0..4 {
+                r[i][j] = (0..3).map(|k| a[i][k] * b[k][j]).sum::<f64>() + if j == 3 { a[i][3] } else { 0.0 };
+            }
+        }
+        r
+    }
+
+    fn reference_rotate(k: [f64; 3], angle: f64) -> Reference {
+        let n = (k[0] * k[0] + k[1] * k[1] + k[2] * k[2]).sqrt();
+        let k = [k[0] / n, k[1] / n, k[2] / n];
+        let (s, c) = angle.sin_cos();
+        [
+            [c + k[0] * k[0] * (1.0 - c), k[0] * k[1] * (1.0 - c) - k[2] * s, k[0] * k[2] * (1.0 - c) + k[1] * s, 0.0],
+            [k[1] * k[0] * (1.0 - c) + k[2] * s, c + k[1] * k[1] * (1.0 - c), k[1] * k[2] * (1.0 - c) - k[0] * s, 0.0],
+            [k[2] * k[0] * (1.0 - c) - k[1] * s, k[2] * k[1] * (1.0 - c) + k[0] * s, c + k[2] * k[2] * (1.0 - c), 0.0],
+        ]
+    }
+
+    fn assert_matrix(got: &Matrix, want: &Reference, eps: f64) {
+        assert_matrix_scaled(got, want, eps, eps);
+    }
+
+    fn assert_matrix_scaled(got: &Matrix, want: &Reference, eps: f64, translation_eps: f64) {
+        let got = reference(got);
+        for i in 0..3 {
+            for j in 0..4 {
+                let eps = if j == 3 { translation_eps } else { eps };
+                assert!((got[i][j] - want[i][j]).abs() <= eps, "got {:?}, want {:?}", got, want);
+            }
+        }
+    }
+
+    fn translation(m: &Matrix) -> f64 {
+        (0..3).map(|i| f64::from(&m[i][3]).abs()).fold(1.0, f64::max)
+    }
+
+    fn assert_identity(t: &Transform) {
+        let eps = TOLERANCE * translation(&t.m).max(translation(&t.inv));
+        assert_matrix_scaled(&multiply(&t.m, &t.inv), &reference(&identity()), TOLERANCE, eps);
+    }
+
+    fn random_transform() -> (Transform, Reference) {
+        let mut rng = rand::thread_rng();
+        let axis = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(0.1..1.0)];
+        let angle = f64::from(&Number::from(rng.gen_range(-3.0..3.0)));
+        let mut t = Transform::rotate(&Vec3::from(axis), &Number::from(angle));
+        let mut r = reference_rotate(<[f64; 3]>::from(&Vec3::from(axis)), angle);
+
+        let s = [rng.gen_range(0.5..2.0), rng.gen_range(0.5..2.0), rng.gen_range(0.5..2.0)];
+        t.mul(&Transform::scale(&Vec3::from(s)));
+        let s = <[f64; 3]>::from(&Vec3::from(s));
+        r = reference_mul(&r, &[[s[0], 0.0, 0.0, 0.0], [0.0, s[1], 0.0, 0.0], [0.0, 0.0, s[2], 0.0]]);
+
+        let v = [rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0)];
+        let mut moved = Transform::translate(&Vec3::from(v));
+        moved.mul(&t);
+        let v = <[f64; 3]>::from(&Vec3::from(v));
+        r = reference_mul(&[[1.0, 0.0, 0.0, v[0]], [0.0, 1.0, 0.0, v[1]], [0.0, 0.0, 1.0, v[2]]], &r);
+
+        (moved, r)
+    }
+
+    #[test]
+    fn basic() {
+        let t = Transform::translate(&Vec3::from([1.0, 2.0, 3.0]));
+        assert_eq!(t.transform_point(&Vec3::zero()), Vec3::from([1.0, 2.0, 3.0]));
+        assert_eq!(t.transform_direction(&Vec3::from([1.0, 0.0, 0.0])), Vec3::from([1.0, 0.0, 0.0]));
+
+        let t = Transform::scale(&Vec3::from([2.0, 4.0, 0.5]));
+        assert_eq!(t.transform_point(&Vec3::from([1.0, 1.0, 1.0])), Vec3::from([2.0, 4.0, 0.5]));
+        assert_eq!(t.inv[1][1], Number::from(0.25));
+
+        let t = Transform::rotate_z(&crate::trig::HALF_PI);
+        let p = <[f64; 3]>::from(&t.transform_point(&Vec3::from([1.0, 0.0, 0.0])));
+        assert!(p[0].abs() < 4.0 / 65536.0 && (p[1] - 1.0).abs() < 4.0 / 65536.0 && p[2] == 0.0, "{:?}", p);
+    }
+
+    #[test]
+    fn axis_rotations_match_rotate() {
+        let angle = Number::from(0.7);
+        let axes = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        let ts = [Transform::rotate_x(&angle), Transform::rotate_y(&angle), Transform::rotate_z(&angle)];
+        for (axis, t) in axes.iter().zip(&ts) {
+            assert_matrix(&t.m, &reference_rotate(*axis, f64::from(&angle)), 4.0 / 65536.0);
+            assert_matrix(&Transform::rotate(&Vec3::from(*axis), &angle).m, &reference(&t.m), 4.0 / 65536.0);
+        }
+    }
+
+    #[test]
+    fn against_reference() {
+        for _ in 0..50 {
+            let (t, r) = random_transform();
+            assert_matrix(&t.m, &r, 32.0 / 65536.0);
+            assert_identity(&t);
+
+            let p = [10.0, -20.0, 5.0];
+            let got = <[f64; 3]>::from(&t.transform_point(&Vec3::from(p)));
+            for i in 0..3 {
+                let want = (0..3).map(|k| r[i][k] * p[k]).sum::<f64>() + r[i][3];
+                assert!((got[i] - want).abs() < 1.0 / 64.0, "got {:?}, want {}", got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        for _ in 0..50 {
+            let (t, _) = random_transform();
+            let mut inv = t.clone();
+            inv.inverse();
+            let p = Vec3::from([3.0, -7.0, 11.0]);
+            let back = <[f64; 3]>::from(&inv.transform_point(&t.transform_point(&p)));
+            for (got, want) in back.iter().zip([3.0, -7.0, 11.0]) {
+                // off by (M^-1 M - I) p and an ulp or two of rounding. each
+                // factor's inverse is rounded once per entry, so M^-1 M is
+                // within a few ulp of I, times up to 4 where the scales
+                // differ: under 8 ulp, times |p|_1 = 21
+                assert!((got - want).abs() < 1.0 / 256.0, "got {:?}", back);
+            }
+        }
+    }
+
+    #[test]
+    fn from_rows() {
+        for _ in 0..50 {
+            let (t, _) = random_transform();
+            let general = Transform::from_rows(t.m.clone());
+            assert_identity(&general);
+            assert_matrix_scaled(&general.inv, &reference(&t.inv), TOLERANCE, TOLERANCE * translation(&t.inv));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn singular() {
+        let mut rows = identity();
+        rows[2][2] = Number::ZERO;
+        Transform::from_rows(rows);
+    }
+
+    #[test]
+    fn normals() {
+        // a plane x = y squashed along y: its normal has to tilt the other way.
+        let t = Transform::scale(&Vec3::from([1.0, 0.5, 1.0]));
+        let n = t.transform_normal(&Vec3::from([1.0, -1.0, 0.0]));
+        assert_eq!(n, Vec3::from([1.0, -2.0, 0.0]));
+
+        let tangent = t.transform_direction(&Vec3::from([1.0, 1.0, 0.0]));
+        assert_eq!(tangent.dot(&n), Number::ZERO);
+
+        for _ in 0..50 {
+            let (t, _) = random_transform();
+            let (a, b) = (Vec3::from([1.0, 2.0, 0.5]), Vec3::from([-0.5, 0.0, 1.0]));
+            let mut n = a.clone();
+            n.cross(&b);
+            let n = t.transform_normal(&n);
+            let ta = t.transform_direction(&a);
+            let tb = t.transform_direction(&b);
+            assert!(f64::from(&ta.dot(&n)).abs() < 1.0 / 256.0);
+            assert!(f64::from(&tb.dot(&n)).abs() < 1.0 / 256.0);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use crate::aabb::Aabb;
+use crate::geometry::{Dir3, Normal3, Point3};
+use crate::int32::Int32;
+use crate::int64::Int64;
+use crate::number::Number;
+use crate::ray::{Hit, Ray};
+use crate::shape::Shape;
+
+// Möller–Trumbore computes det = e1 . (d x e2), which goes to zero as the ray
+// turns parallel to the triangle. det and the numerators are exact but for
+// what triple truncates below 32.32, so a det within a few units of its
+// last place is taken for zero: parallel, no hit.
+const EPSILON: Int64 = Int64 { parts: [4, 0, 0, 0, 0, 0, 0, 0] };
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Triangle {
+    pub vertices: [Point3; 3],
+    // per-vertex normals for smooth shading; the face normal otherwise.
+    pub normals: Option<[Normal3; 3]>,
+    // skip hits from behind, i.e. where the vertices run clockwise as seen
+    // from the ray.
+    pub cull: bool,
+    pub material: i16,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point3; 3], material: i16) -> Self {
+        Self { vertices, normals: None, cull: false, material }
+    }
+
+    pub fn smooth(vertices: [Point3; 3], normals: [Normal3; 3], material: i16) -> Self {
+        Self { vertices, normals: Some(normals), cull: false, material }
+    }
+}
+
+impl Triangle {
+    // (v1 - v0) x (v2 - v0): counter-clockwise vertices face it.
+    pub fn face_normal(&self) -> Normal3 {
+        let (e1, e2) = (self.vertices[1].minus(&self.vertices[0]), self.vertices[2].minus(&self.vertices[0]));
+        let mut n = plane_normal(&e1, &e2).unwrap_or_else(Dir3::zero);
+        n.normalize();
+        Normal3::perpendicular(n)
+    }
+
+    // (1 - u - v) n0 + u n1 + v n2, renormalized.
+    fn shading_normal(normals: &[Normal3; 3], u: &Number, v: &Number) -> Normal3 {
+        let mut w = Number::ONE;
+        w.sub(u);
+        w.sub(v);
+        let weights = [w, u.clone(), v.clone()];
+        let blend = |c: fn(&Normal3) -> &Number| {
+            Number::dot(&weights, &[c(&normals[0]).clone(), c(&normals[1]).clone(), c(&normals[2]).clone()])
+        };
+        let mut n = Normal3::new(blend(|n| &n.vec3().x), blend(|n| &n.vec3().y), blend(|n| &n.vec3().z));
+        n.normalize();
+        n
+    }
+}
+
+impl Shape for Triangle {
+    // the barycentric tests run on the numerators, before dividing by det:
+    // u = s . (d x e2) / det and v = s . (e1 x d) / det are in range iff
+    // both numerators are at least 0 and their sum at most det. edges and
+    // vertices count as inside, so a ray through a shared edge hits both
+    // triangles rather than slipping between them. hit.u and hit.v are the
+    // weights of v1 and v2; t = s . (e1 x e2) / det.
+    //
+    // the numerators are triple products, with s as long as the distance to
+    // the triangle: they and det are taken exactly, wide, and divided wide.
+    // so this holds at any distance, for triangles down to a few ulp across
+    // and up to edges of about 180 units, where e1 x e2 leaves 16.16.
+    fn hit(&self, ray: &Ray, t_min: &Number, t_max: &Number) -> Option<Hit> {
+        let e1 = self.vertices[1].minus(&self.vertices[0]);
+        let e2 = self.vertices[2].minus(&self.vertices[0]);
+        let s = ray.origin.minus(&self.vertices[0]);
+        let d = &ray.direction;
+
+        let mut det = triple(&e1, d, &e2);
+        let negative = |x: &Int64| x.parts[7] >= 0x80;
+        if self.cull && (negative(&det) || !EPSILON.lt_unsigned(&det)) {
+            return None;
+        }
+
+        let (mut u, mut v, mut t) = (triple(&s, d, &e2), triple(&s, &e1, d), triple(&s, &e1, &e2));
+        if negative(&det) {
+            det.neg();
+            u.neg();
+            v.neg();
+            t.neg();
+        }
+        if !EPSILON.lt_unsigned(&det) {
+            return None;
+        }
+
+        let mut uv = u.clone();
+        uv.add(&v);
+        if negative(&u) || negative(&v) || det.lt_unsigned(&uv) {
+            return None;
+        }
+
+        let t = Number::try_ratio(&t, &det).ok()?;
+        if !t_min.lt(&t) || !t.lt(t_max) {
+            return None;
+        }
+        let (u, v) = (Number::try_ratio(&u, &det).ok()?, Number::try_ratio(&v, &det).ok()?);
+
+        let mut n = plane_normal(&e1, &e2)?;
+        n.normalize();
+        let mut hit = Hit::new(ray, t, Normal3::perpendicular(n), self.material);
+        if let Some(normals) = &self.normals {
+            // front_face stays with the geometry; the shading normal is
+            // turned to the same side.
+            let mut n = Self::shading_normal(normals, &u, &v);
+            if !hit.front_face {
+                n.neg();
+            }
+            hit.normal = n;
+        }
+        (hit.u, hit.v) = (u, v);
+        Some(hit)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let mut bounds = Aabb::new(&self.vertices[0], &self.vertices[1]);
+        bounds.expand(&self.vertices[2]);
+        Some(bounds)
+    }
+}
+
+// a x b exactly, in 32.32.
+fn wide_cross(a: &Dir3, b: &Dir3) -> [Int64; 3] {
+    let (a, b) = (a.vec3(), b.vec3());
+    let minor = |p: &Number, q: &Number, r: &Number, s: &Number| {
+        let mut x = Int64::product(&p.0, &q.0);
+        x.sub(&Int64::product(&r.0, &s.0));
+        x
+    };
+    [minor(&a.y, &b.z, &a.z, &b.y), minor(&a.z, &b.x, &a.x, &b.z), minor(&a.x, &b.y, &a.y, &b.x)]
+}
+
+// a . (b x c), exact but for the bits below 32.32. b x c is split into its
+// 16.16 part and the 16 bits under that, each dotted with a wide; the
+// second dot is shifted down 16 bits, flooring, before they are summed.
+fn triple(a: &Dir3, b: &Dir3, c: &Dir3) -> Int64 {
+    let bc = wide_cross(b, c);
+    let high = bc.each_ref().map(|x| Number(Int32 { parts: [x.parts[2], x.parts[3], x.parts[4], x.parts[5]] }));
+    let low = bc.each_ref().map(|x| Number(Int32 { parts: [x.parts[0], x.parts[1], 0, 0] }));
+    let a = a.vec3();
+    let a = [a.x.clone(), a.y.clone(), a.z.clone()];
+
+    let mut r = Number::sum_of_products(&a, &high);
+    let below = Number::sum_of_products(&a, &low).parts;
+    let ext = if below[7] >= 0x80 { 0xFF } else { 0 };
+    r.add(&Int64 { parts: [below[2], below[3], below[4], below[5], below[6], below[7], ext, ext] });
+    r
+}
+
+// e1 x e2, scaled by a power of two that brings its largest component into
+// [2^13, 2^14), then truncated to 16.16. only its direction is used, and
+// rounded unscaled it keeps few bits, or none, for a triangle a fraction of
+// a unit across. None for a degenerate triangle.
+fn plane_normal(e1: &Dir3, e2: &Dir3) -> Option<Dir3> {
+    let mut n = wide_cross(e1, e2);
+    if n.iter().all(|c| c.parts == [0; 8]) {
+        return None;
+    }
+
+    // on magnitudes, for shr doesn't carry the sign.
+    let negative = n.each_ref().map(|c| c.parts[7] >= 0x80);
+    n.iter_mut().filter(|c| c.parts[7] >= 0x80).for_each(Int64::neg);
+    let reaches = |n: &[Int64; 3], bit: i64| n.iter().any(|c| !c.lt_unsigned(&Int64::from(1i64 << (bit + 32))));
+    while !reaches(&n, 13) {
+        n.iter_mut().for_each(|c| c.shl(1));
+    }
+    while reaches(&n, 14) {
+        n.iter_mut().for_each(|c| c.shr(1));
+    }
+
+    let [x, y, z] = [0, 1, 2].map(|i| {
+        let c = &n[i].parts;
+        let mut c = Number(Int32 { parts: [c[2], c[3], c[4], c[5]] });
+        if negative[i] {
+            c.neg();
+        }
+        c
+    });
+    Some(Dir3::new(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // z = 0, counter-clockwise seen from +z
+    fn unit() -> Triangle {
+        Triangle::new([Point3::from([0.0, 0.0, 0.0]), Point3::from([1.0, 0.0, 0.0]), Point3::from([0.0, 1.0, 0.0])], 4)
+    }
+
+    fn down(x: f64, y: f64) -> Ray {
+        Ray::new(Point3::from([x, y, 2.0]), Dir3::from([0.0, 0.0, -1.0]))
+    }
+
+    fn hit(tri: &Triangle, r: &Ray) -> Option<Hit> {
+        tri.hit(r, &Number::ZERO, &Number::MAX)
+    }
+
+    #[test]
+    fn interior() {
+        let tri = unit();
+        let h = hit(&tri, &down(0.25, 0.5)).unwrap();
+        assert_eq!(h.t, Number::from(2));
+        assert_eq!(h.point, Point3::from([0.25, 0.5, 0.0]));
+        assert_eq!((h.u, h.v), (Number::from(0.25), Number::from(0.5)));
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+        assert!(h.front_face);
+        assert_eq!(h.material, 4);
+        assert_eq!(tri.face_normal(), Normal3::from([0.0, 0.0, 1.0]));
+
+        assert!(hit(&tri, &down(0.75, 0.5)).is_none());
+        assert!(hit(&tri, &down(-0.25, 0.5)).is_none());
+        assert!(tri.hit(&down(0.25, 0.5), &Number::ZERO, &Number::from(2)).is_none());
+    }
+
+    #[test]
+    fn edges_and_vertices() {
+        let tri = unit();
+        // on each edge, and at each vertex
+        for (x, y) in [(0.5, 0.0), (0.0, 0.5), (0.5, 0.5), (0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] {
+            let h = hit(&tri, &down(x, y)).unwrap_or_else(|| panic!("missed ({}, {})", x, y));
+            assert_eq!((h.u, h.v), (Number::from(x), Number::from(y)));
+        }
+        // an ulp outside each edge
+        let e = 1.0 / 65536.0;
+        for (x, y) in [(0.5, -e), (-e, 0.5), (0.5 + e, 0.5), (1.0 + e, 0.0), (0.0, 1.0 + e)] {
+            assert!(hit(&tri, &down(x, y)).is_none(), "hit ({}, {})", x, y);
+        }
+
+        // two triangles sharing the diagonal of a square: rays along it hit
+        // at least one of them.
+        let other = Triangle::new([Point3::from([1.0, 0.0, 0.0]), Point3::from([1.0, 1.0, 0.0]), Point3::from([0.0, 1.0, 0.0])], 4);
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let x = rng.gen_range(0.0..1.0);
+            let r = down(x, 1.0 - f64::from(&Number::from(x)));
+            assert!(hit(&tri, &r).is_some() || hit(&other, &r).is_some(), "slipped through at x = {}", x);
+        }
+    }
+
+    #[test]
+    fn back_faces() {
+        let mut tri = unit();
+        let up = Ray::new(Point3::from([0.25, 0.25, -2.0]), Dir3::from([0.0, 0.0, 1.0]));
+
+        let h = hit(&tri, &up).unwrap();
+        assert!(!h.front_face);
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, -1.0]));
+        assert_eq!((h.u, h.v), (Number::from(0.25), Number::from(0.25)));
+
+        tri.cull = true;
+        assert!(hit(&tri, &up).is_none());
+        assert!(hit(&tri, &down(0.25, 0.25)).is_some());
+    }
+
+    #[test]
+    fn parallel() {
+        let tri = unit();
+        for o in [[-1.0, 0.25, 0.0], [-1.0, 0.25, 1.0]] {
+            let r = Ray::new(Point3::from(o), Dir3::from([1.0, 0.0, 0.0]));
+            assert!(hit(&tri, &r).is_none());
+        }
+        // nearly parallel: det is under epsilon
+        let r = Ray::new(Point3::from([-1.0, 0.25, 0.0]), Dir3::new(Number::ONE, Number::ZERO, Number::from(-2.0 / 65536.0)));
+        assert!(hit(&tri, &r).is_none());
+    }
+
+    #[test]
+    fn smooth_normals() {
+        let v = [Point3::from([0.0, 0.0, 0.0]), Point3::from([1.0, 0.0, 0.0]), Point3::from([0.0, 1.0, 0.0])];
+        let s = 0.5f64.sqrt();
+        let n = [Normal3::from([0.0, 0.0, 1.0]), Normal3::from([s, 0.0, s]), Normal3::from([0.0, s, s])];
+        let tri = Triangle::smooth(v, n, 0);
+
+        let h = hit(&tri, &down(0.0, 0.0)).unwrap();
+        assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+
+        let h = hit(&tri, &down(0.5, 0.0)).unwrap();
+        let got = <[f64; 3]>::from(&h.normal);
+        // halfway between n0 and n1: tilted 22.5 degrees toward +x
+        let l = (s * s + (1.0 + s) * (1.0 + s)).sqrt();
+        let want = [s / l, 0.0, (1.0 + s) / l];
+        for i in 0..3 {
+            assert!((got[i] - want[i]).abs() < 4.0 / 65536.0, "got {:?}, want {:?}", got, want);
+        }
+
+        // from behind, the shading normal turns with the face normal
+        let up = Ray::new(Point3::from([0.5, 0.0, -2.0]), Dir3::from([0.0, 0.0, 1.0]));
+        let h = hit(&tri, &up).unwrap();
+        assert!(!h.front_face);
+        let back = <[f64; 3]>::from(&h.normal);
+        for i in 0..3 {
+            assert!((back[i] + want[i]).abs() < 4.0 / 65536.0, "got {:?}, want {:?}", back, want);
+        }
+    }
+
+    #[test]
+    fn far() {
+        // t * det and the numerators would be far out of 16.16 here
+        for size in [20.0, 60.0, 150.0] {
+            let h = size / 2.0;
+            let tri = Triangle::new([[-h, -h, -100.0], [h, -h, -100.0], [0.0, h, -100.0]].map(Point3::from), 0);
+            let r = Ray::new(Point3::origin(), Dir3::from([0.0, 0.0, -1.0]));
+            let hit = hit(&tri, &r).unwrap_or_else(|| panic!("missed the {} unit triangle", size));
+            assert_eq!(hit.t, Number::from(100));
+            assert_eq!((hit.u, hit.v), (Number::from(0.25), Number::from(0.5)));
+        }
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut hits = 0;
+        for _ in 0..500 {
+            let c = [0, 1, 2].map(|_| rng.gen_range(-400.0..400.0));
+            let mut p = || Point3::from(c.map(|c| c + rng.gen_range(-30.0..30.0)));
+            let tri = Triangle::new([p(), p(), p()], 0);
+            let o = [0, 1, 2].map(|_| rng.gen_range(-400.0..400.0));
+            let at = c.map(|c| c + rng.gen_range(-10.0..10.0));
+            let r = Ray::new(Point3::from(o), Dir3::from([0, 1, 2].map(|i| at[i] - o[i])));
+
+            // where the line meets the plane, in f64 from the same inputs
+            let f = |p: &Point3| <[f64; 3]>::from(p);
+            let (v, d) = (tri.vertices.each_ref().map(f), <[f64; 3]>::from(&r.direction));
+            let sub = |a: [f64; 3], b: [f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+            let cross = |a: [f64; 3], b: [f64; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+            let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+            let (e1, e2, s) = (sub(v[1], v[0]), sub(v[2], v[0]), sub(f(&r.origin), v[0]));
+            let det = dot(e1, cross(d, e2));
+            let (u, w, t) = (dot(s, cross(d, e2)) / det, dot(s, cross(e1, d)) / det, dot(s, cross(e1, e2)) / det);
+
+            // the rounded cross products move the plane by about an ulp of
+            // each, over the angle the ray makes with it
+            let len = |a: [f64; 3]| dot(a, a).sqrt();
+            let slope = len(cross(e1, e2)) * len(d) / det.abs();
+            let eps = slope * (len(s) + len(e1) + len(e2)) * len(d) * 4.0 / 65536.0 + 1.0 / 4096.0;
+            let margin = eps / len(e1).min(len(e2));
+            let inside = u > margin && w > margin && u + w < 1.0 - margin && t > eps;
+            let outside = u < -margin || w < -margin || u + w > 1.0 + margin || t < -eps;
+            match hit(&tri, &r) {
+                Some(h) => {
+                    assert!(!outside, "hit outside: u {} v {} t {}", u, w, t);
+                    hits += 1;
+                    assert!((f64::from(&h.t) - t).abs() < eps, "t: got {}, want {}, eps {}", f64::from(&h.t), t, eps);
+                }
+                None => assert!(!inside || slope > 1e3, "missed: u {} v {} t {}", u, w, t),
+            }
+        }
+        assert!(hits > 60, "{} hits", hits);
+    }
+
+    #[test]
+    fn small() {
+        // e1 x e2 is under an ulp or two in 16.16 for these
+        for leg in [1.0 / 200.0, 1.0 / 300.0] {
+            let tri = Triangle::new([[0.0, 0.0, 0.0], [leg, 0.0, 0.0], [0.0, leg, 0.0]].map(Point3::from), 0);
+            let r = Ray::new(Point3::from([leg / 4.0, leg / 4.0, 5.0]), Dir3::from([0.0, 0.0, -1.0]));
+            let h = hit(&tri, &r).unwrap_or_else(|| panic!("missed the triangle with legs {}", leg));
+            assert_eq!(h.t, Number::from(5));
+            assert_eq!(h.normal, Normal3::from([0.0, 0.0, 1.0]));
+            assert_eq!(tri.face_normal(), Normal3::from([0.0, 0.0, 1.0]));
+        }
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..500 {
+            let size = rng.gen_range(1.0 / 400.0..1.0 / 20.0);
+            let c = [0, 1, 2].map(|_| rng.gen_range(-10.0..10.0));
+            let mut p = || Point3::from(c.map(|c| c + rng.gen_range(-size..size)));
+            let tri = Triangle::new([p(), p(), p()], 0);
+
+            let f = |p: &Point3| <[f64; 3]>::from(p);
+            let v = tri.vertices.each_ref().map(f);
+            let sub = |a: [f64; 3], b: [f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+            let cross = |a: [f64; 3], b: [f64; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+            let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+            let (e1, e2) = (sub(v[1], v[0]), sub(v[2], v[0]));
+            let n = cross(e1, e2);
+            let len = dot(n, n).sqrt();
+            if len < size * size / 16.0 {
+                continue;
+            }
+
+            // from about 5 units off, at a point well inside
+            let (a, b) = (rng.gen_range(0.2..0.4), rng.gen_range(0.2..0.4));
+            let at = [0, 1, 2].map(|i| v[0][i] + a * e1[i] + b * e2[i]);
+            let o = at.map(|c| c + rng.gen_range(-5.0..5.0));
+            let r = Ray::new(Point3::from(o), Dir3::from(sub(at, o)));
+            let (o, d) = (f(&r.origin), <[f64; 3]>::from(&r.direction));
+            if dot(d, n).abs() < dot(d, d).sqrt() * len / 4.0 {
+                continue;
+            }
+
+            let t = -dot(sub(o, v[0]), n) / dot(d, n);
+            let h = hit(&tri, &r).unwrap_or_else(|| panic!("missed {:?} from {:?}", v, o));
+            assert!((f64::from(&h.t) - t).abs() < 2.0 / 65536.0, "t: got {}, want {}", f64::from(&h.t), t);
+            let got = <[f64; 3]>::from(&tri.face_normal());
+            for i in 0..3 {
+                assert!((got[i] - n[i] / len).abs() < 4.0 / 65536.0, "normal: got {:?}, want {:?}", got, n.map(|c| c / len));
+            }
+        }
+    }
+
+    #[test]
+    fn random_against_f64() {
+        let mut rng = rand::thread_rng();
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let mut p = || Point3::from([rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)]);
+            let tri = Triangle::new([p(), p(), p()], 0);
+            let r = Ray::new(Point3::from([0.0, 0.0, 0.0]), Dir3::from([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]));
+
+            let f = |p: &Point3| <[f64; 3]>::from(p);
+            let (v0, v1, v2) = (f(&tri.vertices[0]), f(&tri.vertices[1]), f(&tri.vertices[2]));
+            let (o, d) = (f(&r.origin), <[f64; 3]>::from(&r.direction));
+            let sub = |a: [f64; 3], b: [f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+            let cross = |a: [f64; 3], b: [f64; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+            let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+            let (e1, e2) = (sub(v1, v0), sub(v2, v0));
+            let pv = cross(d, e2);
+            let det = dot(e1, pv);
+            let s = sub(o, v0);
+            let q = cross(s, e1);
+            let (u, v, t) = (dot(s, pv) / det, dot(d, q) / det, dot(e2, q) / det);
+
+            // the numerators carry rounding from the cross products, about
+            // an ulp times the lengths multiplied in; det divides it out.
+            let len = |a: [f64; 3]| dot(a, a).sqrt();
+            let noise = (len(e1) * len(e2) + len(s) * (len(e1) + len(e2))) * (1.0 + len(d)) / det.abs() / 65536.0;
+            let eps = 1.0 / 1024.0 + 4.0 * noise;
+            let inside = u > eps && v > eps && u + v < 1.0 - eps && t > eps;
+            let outside = u < -eps || v < -eps || u + v > 1.0 + eps || t < -eps;
+            match hit(&tri, &r) {
+                Some(h) => {
+                    assert!(!outside, "hit outside: u {} v {} t {}", u, v, t);
+                    hits += 1;
+                    assert!((f64::from(&h.t) - t).abs() < eps, "t: got {}, want {}", f64::from(&h.t), t);
+                    assert!((f64::from(&h.u) - u).abs() < eps && (f64::from(&h.v) - v).abs() < eps, "uv: got {:?}, want {:?}", (&h.u, &h.v), (u, v));
+                }
+                None => assert!(!inside || det.abs() < 4.0 / 65536.0, "missed: u {} v {} t {}", u, v, t),
+            }
+        }
+        assert!(hits > 50, "{} hits", hits);
+    }
+}
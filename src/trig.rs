@@ -0,0 +1,189 @@
+use crate::int32::Int32;
+use crate::number::Number;
+
+pub const PI: Number = Number(Int32 { parts: [0x3F, 0x24, 0x03, 0] });
+pub const TAU: Number = Number(Int32 { parts: [0x7F, 0x48, 0x06, 0] });
+pub const HALF_PI: Number = Number(Int32 { parts: [0x20, 0x92, 0x01, 0] });
+
+// CORDIC runs on raw Int32s in 2.29 fixed point, for guard bits: atan(2^-i)
+// and the gain prod(1 / sqrt(1 + 2^-2i)) at that scale.
+const ATAN: [i32; 24] = [
+    421657428, 248918915, 131521918, 66762579, 33510843, 16771758, 8387925, 4194219,
+    2097141, 1048575, 524288, 262144, 131072, 65536, 32768, 16384,
+    8192, 4096, 2048, 1024, 512, 256, 128, 64,
+];
+const GAIN: i32 = 326016437;
+
+// sine and cosine of an angle in radians, by CORDIC rotation: 24 rounds of
+// shifts and adds, good to about 1/65536 (plus ~0.2/65536 per turn the
+// angle is away from zero, from rounding 2pi).
+pub fn sin_cos(angle: &Number) -> (Number, Number) {
+    // angle - round(angle / 2pi) * 2pi, in [-pi, pi]
+    let mut turns = angle.clone();
+    turns.div(&TAU);
+    turns.add(&Number(Int32::from(0x8000)));
+    turns.0.parts[0] = 0;
+    turns.0.parts[1] = 0;
+    turns.neg();
+
+    let mut z = angle.clone();
+    z.mul_add(&turns, &TAU);
+
+    // cos(z - pi) = -cos(z) and sin(z - pi) = -sin(z)
+    let mut minus_half_pi = HALF_PI;
+    minus_half_pi.neg();
+    let flip = HALF_PI.lt(&z) || z.lt(&minus_half_pi);
+    if HALF_PI.lt(&z) {
+        z.sub(&PI);
+    } else if z.lt(&minus_half_pi) {
+        z.add(&PI);
+    }
+
+    let mut z = z.0;
+    z.shl(13);
+    let mut x = Int32::from(GAIN);
+    let mut y = Int32::from(0);
+    for (i, a) in ATAN.iter().enumerate() {
+        let mut dx = y.clone();
+        dx.sar(i as i16);
+        let mut dy = x.clone();
+        dy.sar(i as i16);
+
+        let a = Int32::from(*a);
+        if z.parts[3] >= 0x80 {
+            x.add(&dx);
+            y.sub(&dy);
+            z.add(&a);
+        } else {
+            x.sub(&dx);
+            y.add(&dy);
+            z.sub(&a);
+        }
+    }
+
+    let mut sin = Number(rounded(y));
+    let mut cos = Number(rounded(x));
+    if flip {
+        sin.neg();
+        cos.neg();
+    }
+
+    (sin, cos)
+}
+
+// the angle of (x, y) in [-pi, pi], by CORDIC vectoring: (x, y) is rotated
+// onto the x axis and the rotations summed. atan2(0, 0) is 0.
+pub fn atan2(y: &Number, x: &Number) -> Number {
+    if x.0.parts == [0; 4] && y.0.parts == [0; 4] {
+        return Number::ZERO;
+    }
+
+    // scale up or down so the larger of |x| and |y| is in [2^28, 2^29); the
+    // vector grows by up to 1.65 * sqrt(2) on the way and must fit in 2.29.
+    let (mut x, mut y) = (x.0.clone(), y.0.clone());
+    while !(within(&x, 29) && within(&y, 29)) {
+        x.sar(1);
+        y.sar(1);
+    }
+    while within(&x, 28) && within(&y, 28) {
+        x.shl(1);
+        y.shl(1);
+    }
+
+    // fold the left half-plane over by pi.
+    let mut offset = Number::ZERO;
+    if x.parts[3] >= 0x80 {
+        offset = PI;
+        if y.parts[3] >= 0x80 {
+            offset.neg();
+        }
+        x.neg();
+        y.neg();
+    }
+
+    let mut z = Int32::from(0);
+    for (i, a) in ATAN.iter().enumerate() {
+        let mut dx = y.clone();
+        dx.sar(i as i16);
+        let mut dy = x.clone();
+        dy.sar(i as i16);
+
+        let a = Int32::from(*a);
+        if y.parts[3] >= 0x80 {
+            x.sub(&dx);
+            y.add(&dy);
+            z.sub(&a);
+        } else {
+            x.add(&dx);
+            y.sub(&dy);
+            z.add(&a);
+        }
+    }
+
+    let mut angle = Number(rounded(z));
+    angle.add(&offset);
+    angle
+}
+
+// whether -2^bits <= n < 2^bits.
+fn within(n: &Int32, bits: i16) -> bool {
+    let mut hi = Int32::from(1);
+    hi.shl(bits);
+    let mut lo = hi.clone();
+    lo.neg();
+    n.lt(&hi) && !n.lt(&lo)
+}
+
+// 2.29 back to 16.16, to nearest.
+fn rounded(n: Int32) -> Int32 {
+    let mut n = n;
+    n.add(&Int32::from(1 << 12));
+    n.sar(13);
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    const EPS: f64 = 4.0 / 65536.0;
+
+    #[test]
+    fn constants() {
+        assert!((f64::from(&PI) - std::f64::consts::PI).abs() < 1.0 / 65536.0);
+        assert!((f64::from(&TAU) - std::f64::consts::TAU).abs() < 1.0 / 65536.0);
+        assert!((f64::from(&HALF_PI) - std::f64::consts::FRAC_PI_2).abs() < 1.0 / 65536.0);
+    }
+
+    #[test]
+    fn sin_cos() {
+        let (s, c) = super::sin_cos(&Number::ZERO);
+        assert!(f64::from(&s).abs() < EPS && (f64::from(&c) - 1.0).abs() < EPS);
+
+        for _ in 0..1000 {
+            let a = f64::from(&Number::from(rand::thread_rng().gen_range(-100.0..100.0)));
+            let (s, c) = super::sin_cos(&Number::from(a));
+            assert!((f64::from(&s) - a.sin()).abs() < EPS, "sin {}: got {}, want {}", a, f64::from(&s), a.sin());
+            assert!((f64::from(&c) - a.cos()).abs() < EPS, "cos {}: got {}, want {}", a, f64::from(&c), a.cos());
+        }
+    }
+
+    #[test]
+    fn atan2() {
+        assert_eq!(super::atan2(&Number::ZERO, &Number::ZERO), Number::ZERO);
+        assert!((f64::from(&super::atan2(&Number::ZERO, &Number::from(-3))) - std::f64::consts::PI).abs() < EPS);
+        assert!((f64::from(&super::atan2(&Number::MIN, &Number::MIN)) + 0.75 * std::f64::consts::PI).abs() < EPS);
+        assert!((f64::from(&super::atan2(&Number::ULP, &Number::ULP)) - 0.25 * std::f64::consts::PI).abs() < EPS);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let scale = 2f64.powi(rng.gen_range(-12..14));
+            let (y, x) = (Number::from(rng.gen_range(-scale..scale)), Number::from(rng.gen_range(-scale..scale)));
+            let want = f64::from(&y).atan2(f64::from(&x));
+            let got = f64::from(&super::atan2(&y, &x));
+            assert!((got - want).abs() < EPS, "atan2({:?}, {:?}): got {}, want {}", y, x, got, want);
+        }
+    }
+}
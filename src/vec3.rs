@@ -0,0 +1,305 @@
+use crate::int32::Int32;
+use crate::number::Number;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Vec3 {
+    pub x: Number,
+    pub y: Number,
+    pub z: Number,
+}
+
+impl Vec3 {
+    pub fn new(x: Number, y: Number, z: Number) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(Number::ZERO, Number::ZERO, Number::ZERO)
+    }
+}
+
+// host-side, like From<f64> for Number.
+impl From<[f64; 3]> for Vec3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self::new(Number::from(v[0]), Number::from(v[1]), Number::from(v[2]))
+    }
+}
+
+impl From<&Vec3> for [f64; 3] {
+    fn from(v: &Vec3) -> Self {
+        [f64::from(&v.x), f64::from(&v.y), f64::from(&v.z)]
+    }
+}
+
+impl Vec3 {
+    pub fn add(&mut self, other: &Self) {
+        self.x.add(&other.x);
+        self.y.add(&other.y);
+        self.z.add(&other.z);
+    }
+
+    pub fn sub(&mut self, other: &Self) {
+        self.x.sub(&other.x);
+        self.y.sub(&other.y);
+        self.z.sub(&other.z);
+    }
+
+    pub fn scale(&mut self, s: &Number) {
+        self.x.mul(s);
+        self.y.mul(s);
+        self.z.mul(s);
+    }
+
+    pub fn neg(&mut self) {
+        self.x.neg();
+        self.y.neg();
+        self.z.neg();
+    }
+}
+
+impl Vec3 {
+    pub fn dot(&self, other: &Self) -> Number {
+        Number::dot(
+            &[self.x.clone(), self.y.clone(), self.z.clone()],
+            &[other.x.clone(), other.y.clone(), other.z.clone()],
+        )
+    }
+
+    pub fn cross(&mut self, other: &Self) {
+        let x = det2(&self.y, &self.z, &other.y, &other.z);
+        let y = det2(&self.z, &self.x, &other.z, &other.x);
+        let z = det2(&self.x, &self.y, &other.x, &other.y);
+        *self = Self::new(x, y, z);
+    }
+
+    pub fn length_squared(&self) -> Number {
+        self.dot(self)
+    }
+
+    // squaring a component above ~181 leaves the 16.16 range, so the length
+    // is taken on the wide sum of squares rather than on length_squared.
+    pub fn length(&self) -> Number {
+        Number::hypot(&[self.x.clone(), self.y.clone(), self.z.clone()])
+    }
+
+    // divides through by the largest component first, so the length being
+    // divided by is in [1, sqrt(3)] for huge and tiny vectors alike.
+    pub fn normalize(&mut self) {
+        let mut m = self.largest_component();
+        if m == Number::MIN {
+            // -32768 has no positive counterpart; halving keeps the direction.
+            self.scale(&Number(Int32::from(0x8000)));
+            m = self.largest_component();
+        }
+
+        self.x.div(&m);
+        self.y.div(&m);
+        self.z.div(&m);
+
+        let l = self.length();
+        self.x.div(&l);
+        self.y.div(&l);
+        self.z.div(&l);
+    }
+
+    fn largest_component(&self) -> Number {
+        let mut m = Number::ZERO;
+        for c in [&self.x, &self.y, &self.z] {
+            let mut a = c.clone();
+            if a.is_negative() {
+                a.neg();
+            }
+            if a == Number::MIN {
+                return a;
+            }
+            if m.lt(&a) {
+                m = a;
+            }
+        }
+        m
+    }
+}
+
+impl Vec3 {
+    pub fn min(&mut self, other: &Self) {
+        if other.x.lt(&self.x) {
+            self.x = other.x.clone();
+        }
+        if other.y.lt(&self.y) {
+            self.y = other.y.clone();
+        }
+        if other.z.lt(&self.z) {
+            self.z = other.z.clone();
+        }
+    }
+
+    pub fn max(&mut self, other: &Self) {
+        if self.x.lt(&other.x) {
+            self.x = other.x.clone();
+        }
+        if self.y.lt(&other.y) {
+            self.y = other.y.clone();
+        }
+        if self.z.lt(&other.z) {
+            self.z = other.z.clone();
+        }
+    }
+}
+
+// a * d - b * c, rounded once.
+fn det2(a: &Number, b: &Number, c: &Number, d: &Number) -> Number {
+    let mut nc = c.clone();
+    nc.neg();
+    Number::dot(&[a.clone(), b.clone()], &[d.clone(), nc])
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    const EPS: f64 = 4.0 / 65536.0;
+
+    fn random(scale: f64) -> ([f64; 3], Vec3) {
+        let mut rng = rand::thread_rng();
+        let f = [rng.gen_range(-scale..scale), rng.gen_range(-scale..scale), rng.gen_range(-scale..scale)];
+        let v = Vec3::new(Number::from(f[0]), Number::from(f[1]), Number::from(f[2]));
+        ([f64::from(&v.x), f64::from(&v.y), f64::from(&v.z)], v)
+    }
+
+    fn assert_close(v: &Vec3, want: [f64; 3], eps: f64) {
+        let got = [f64::from(&v.x), f64::from(&v.y), f64::from(&v.z)];
+        for i in 0..3 {
+            assert!((got[i] - want[i]).abs() <= eps, "got {:?}, want {:?}", got, want);
+        }
+    }
+
+    #[test]
+    fn add_sub_neg() {
+        for _ in 0..100 {
+            let ((fa, a), (fb, b)) = (random(1000.0), random(1000.0));
+
+            let mut v = a.clone();
+            v.add(&b);
+            assert_close(&v, [fa[0] + fb[0], fa[1] + fb[1], fa[2] + fb[2]], 0.0);
+
+            let mut v = a.clone();
+            v.sub(&b);
+            assert_close(&v, [fa[0] - fb[0], fa[1] - fb[1], fa[2] - fb[2]], 0.0);
+
+            let mut v = a.clone();
+            v.neg();
+            assert_close(&v, [-fa[0], -fa[1], -fa[2]], 0.0);
+        }
+    }
+
+    #[test]
+    fn scale() {
+        for _ in 0..100 {
+            let (fa, a) = random(100.0);
+            let s = rand::thread_rng().gen_range(-100.0..100.0);
+            let mut v = a.clone();
+            v.scale(&Number::from(s));
+            let s = f64::from(&Number::from(s));
+            assert_close(&v, [fa[0] * s, fa[1] * s, fa[2] * s], EPS);
+        }
+    }
+
+    #[test]
+    fn dot_cross() {
+        for _ in 0..100 {
+            let ((fa, a), (fb, b)) = (random(100.0), random(100.0));
+
+            let d = a.dot(&b);
+            let want = fa[0] * fb[0] + fa[1] * fb[1] + fa[2] * fb[2];
+            assert!((f64::from(&d) - want).abs() <= EPS, "dot: got {}, want {}", f64::from(&d), want);
+
+            let mut c = a.clone();
+            c.cross(&b);
+            assert_close(
+                &c,
+                [
+                    fa[1] * fb[2] - fa[2] * fb[1],
+                    fa[2] * fb[0] - fa[0] * fb[2],
+                    fa[0] * fb[1] - fa[1] * fb[0],
+                ],
+                EPS,
+            );
+        }
+    }
+
+    #[test]
+    fn length_normalize() {
+        let v = Vec3::new(Number::from(3), Number::from(4), Number::from(12));
+        assert_eq!(v.length_squared(), Number::from(169));
+        assert_eq!(v.length(), Number::from(13));
+
+        for _ in 0..100 {
+            let (fa, a) = random(100.0);
+            let want = (fa[0] * fa[0] + fa[1] * fa[1] + fa[2] * fa[2]).sqrt();
+            assert!((f64::from(&a.length()) - want).abs() <= EPS, "length: got {}, want {}", f64::from(&a.length()), want);
+
+            let mut n = a.clone();
+            n.normalize();
+            assert_close(&n, [fa[0] / want, fa[1] / want, fa[2] / want], EPS);
+        }
+    }
+
+    #[test]
+    fn min_max() {
+        let a = Vec3::new(Number::from(1), Number::from(-5), Number::from(3));
+        let b = Vec3::new(Number::from(2), Number::from(-6), Number::from(3));
+
+        let mut v = a.clone();
+        v.min(&b);
+        assert_eq!(v, Vec3::new(Number::from(1), Number::from(-6), Number::from(3)));
+
+        let mut v = a.clone();
+        v.max(&b);
+        assert_eq!(v, Vec3::new(Number::from(2), Number::from(-5), Number::from(3)));
+    }
+
+    #[test]
+    fn length_normalize_sweep() {
+        let mut rng = rand::thread_rng();
+        for k in -16..=15 {
+            for _ in 0..20 {
+                let dir = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0f64..1.0)];
+                let norm = (dir[0] * dir[0] + dir[1] * dir[1] + dir[2] * dir[2]).sqrt();
+                let m = 2f64.powi(k).min(32767.0) / norm;
+                let v = Vec3::new(Number::from(dir[0] * m), Number::from(dir[1] * m), Number::from(dir[2] * m));
+                if v == Vec3::zero() {
+                    continue;
+                }
+
+                let f = [f64::from(&v.x), f64::from(&v.y), f64::from(&v.z)];
+                let want = (f[0] * f[0] + f[1] * f[1] + f[2] * f[2]).sqrt();
+                let got = f64::from(&v.length());
+                assert!(got <= want && want - got < 1.0 / 65536.0, "length 2^{}: got {}, want {}", k, got, want);
+
+                let mut n = v.clone();
+                n.normalize();
+                assert_close(&n, [f[0] / want, f[1] / want, f[2] / want], EPS);
+            }
+        }
+    }
+
+    #[test]
+    fn length_of_extremes() {
+        let v = Vec3::new(Number::from(200), Number::from(200), Number::from(200));
+        assert!((f64::from(&v.length()) - 200.0 * 3f64.sqrt()).abs() < 1.0 / 65536.0);
+
+        let v = Vec3::new(Number::MAX, Number::ZERO, Number::ZERO);
+        assert_eq!(v.length(), Number::MAX);
+
+        let mut v = Vec3::new(Number::MIN, Number::MIN, Number::ZERO);
+        v.normalize();
+        let h = f64::from(&v.x);
+        assert!((h + 0.5f64.sqrt()).abs() < EPS && v.x == v.y && v.z == Number::ZERO, "{:?}", v);
+
+        let mut v = Vec3::new(Number::ULP, Number::ULP, Number::ULP);
+        v.normalize();
+        assert_close(&v, [1.0 / 3f64.sqrt(); 3], EPS);
+    }
+}